@@ -0,0 +1,47 @@
+// Keeps the pages, `/pkg` bundle and assets the app was last loaded with, so
+// it still starts without a connection. The dataset isn't cached here: the
+// app keeps it in IndexedDB and answers queries from it while offline.
+const CACHE = "exoplanet-query-app-v1";
+const SNAPSHOT = "/assets/snapshot.json.gz";
+
+self.addEventListener("install", () => self.skipWaiting());
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((keys) => Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key))))
+      .then(() => self.clients.claim()),
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  const url = new URL(request.url);
+  if (request.method !== "GET" || url.origin !== self.location.origin) return;
+  if (url.pathname.startsWith("/api/") || url.pathname === SNAPSHOT) return;
+
+  // Pages name the bundle under its version, so always try the network
+  // first to stay in step with the server, falling back to the copy from the
+  // last visit. Pages fall back to the home page, whose app can
+  // route to the rest.
+  event.respondWith(
+    fetch(request)
+      .then((response) => {
+        if (response.ok) {
+          const copy = response.clone();
+          event.waitUntil(caches.open(CACHE).then((cache) => cache.put(request, copy)));
+        }
+        return response;
+      })
+      .catch(async () => {
+        const cached = await caches.match(request);
+        if (cached) return cached;
+        if (request.mode === "navigate") {
+          const home = await caches.match("/");
+          if (home) return home;
+        }
+        return Response.error();
+      }),
+  );
+});
@@ -0,0 +1,8 @@
+-- Every record query filters on default_flag, so lead each index with it and
+-- SQLite can seek straight to the default rows in the filtered range.
+CREATE INDEX IF NOT EXISTS exoplanet_data_disc_year ON exoplanet_data (default_flag, disc_year);
+CREATE INDEX IF NOT EXISTS exoplanet_data_hostname ON exoplanet_data (default_flag, hostname);
+CREATE INDEX IF NOT EXISTS exoplanet_data_pl_name ON exoplanet_data (default_flag, pl_name);
+CREATE INDEX IF NOT EXISTS exoplanet_data_discoverymethod ON exoplanet_data (default_flag, discoverymethod);
+CREATE INDEX IF NOT EXISTS exoplanet_data_disc_facility ON exoplanet_data (default_flag, disc_facility);
+CREATE INDEX IF NOT EXISTS exoplanet_data_releasedate ON exoplanet_data (default_flag, releasedate);
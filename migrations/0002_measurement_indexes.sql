@@ -0,0 +1,6 @@
+-- The measurements most often filtered and plotted on.
+CREATE INDEX IF NOT EXISTS exoplanet_data_pl_orbper ON exoplanet_data (default_flag, pl_orbper);
+CREATE INDEX IF NOT EXISTS exoplanet_data_pl_rade ON exoplanet_data (default_flag, pl_rade);
+CREATE INDEX IF NOT EXISTS exoplanet_data_pl_bmasse ON exoplanet_data (default_flag, pl_bmasse);
+CREATE INDEX IF NOT EXISTS exoplanet_data_sy_dist ON exoplanet_data (default_flag, sy_dist);
+CREATE INDEX IF NOT EXISTS exoplanet_data_st_spectype ON exoplanet_data (default_flag, st_spectype);
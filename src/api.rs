@@ -1,63 +1,367 @@
 use crate::model::{
-    data::Data,
-    input::{Input, PageKind},
+    cache::CacheMetrics,
+    console::ConsoleResult,
+    data::{Data, PlanetData},
+    input::{Input, PageKind, Sort},
+    limits::{LimitError, QueryLimits},
+    plot::PlotData,
+    similarity::SimilarityParameter,
+    stats::Statistics,
+    system::PlanetarySystem,
+    timeline::{StackBy, Timeline},
+    user::{AuthStatus, HistoryEntry, Role, SavedQuery, User},
 };
 use cfg_if::cfg_if;
 use leptos::*;
 
 cfg_if! {
     if #[cfg(feature = "ssr")] {
-        use sqlx::{ Connection, SqliteConnection, QueryBuilder, Sqlite };
+        use crate::config::Config;
+        use crate::model::filter::Predicate;
+        use crate::storage::{records_sql, Dialect, Sql, Storage};
+        use actix_web::{web, HttpRequest};
 
-        pub async fn db() -> Result<SqliteConnection, ServerFnError> {
-            use dotenvy::dotenv;
-            use std::env;
+        static POOL: std::sync::OnceLock<crate::storage::Pool> = std::sync::OnceLock::new();
 
-            dotenv()?;
-            Ok(SqliteConnection::connect(&env::var("DATABASE_URL")?).await?)
+        /// Opens the pool of connections to the SQLite or Postgres database
+        /// named in the configuration. `main` calls this once at startup.
+        pub async fn open_storage(config: &Config) -> Result<(), ServerFnError> {
+            let database = &config.database;
+            let pool = crate::storage::Pool::connect(&database.url, database.max_connections).await?;
+            // Opening it twice keeps the first pool.
+            let _ = POOL.set(pool);
+            Ok(())
         }
 
-        pub async fn find_records(query: Vec<Input>, anchor_id: i64, page_direction: PageKind,) -> Result<Option<Data>, ServerFnError> {
-            use crate::model::data::PlanetData;
+        /// A connection to the database from the pool.
+        pub async fn storage() -> Result<crate::storage::Connection, ServerFnError> {
+            match POOL.get() {
+                Some(pool) => pool.acquire().await,
+                None => Err(ServerFnError::ServerError("the database isn't open".to_string())),
+            }
+        }
+
+        /// The configuration `main` registers as app data, read from the
+        /// request being answered.
+        pub fn app_config() -> Result<web::Data<Config>, ServerFnError> {
+            use_context::<HttpRequest>()
+                .and_then(|req| req.app_data::<web::Data<Config>>().cloned())
+                .ok_or_else(|| ServerFnError::ServerError("the configuration isn't registered".to_string()))
+        }
+
+        /// The query limits, from the `limits` section of the configuration.
+        pub fn limits(config: &Config) -> QueryLimits {
+            QueryLimits {
+                timeout: std::time::Duration::from_millis(config.limits.timeout_ms),
+                max_filters: config.limits.max_filters,
+                max_value_length: config.limits.max_value_length,
+            }
+        }
+
+        /// Connects to the database with the query time budget in place:
+        /// any statement still running once it's spent is cancelled.
+        pub async fn limited_storage(limits: &QueryLimits) -> Result<crate::storage::Connection, ServerFnError> {
+            let mut conn = storage().await?;
+            conn.set_timeout(limits.timeout).await?;
+            Ok(conn)
+        }
+
+        /// Reports statements cancelled by the time budget of `limits` as
+        /// timeouts.
+        pub fn query_error(limits: &QueryLimits) -> impl Fn(sqlx::Error) -> ServerFnError {
+            let timeout = limits.timeout;
+            move |error| match crate::storage::is_timeout(&error) {
+                true => LimitError::Timeout(timeout).into(),
+                false => error.into(),
+            }
+        }
+
+        /// Compiles the filters, rejecting queries over the configured
+        /// limits.
+        fn compile_predicates(limits: &QueryLimits, query: Vec<Input>) -> Result<Vec<Predicate>, ServerFnError> {
+            limits.check(&query)?;
+            Ok(query.into_iter().map(Predicate::compile).collect::<Result<_, _>>()?)
+        }
+
+        /// Builds the query for a page of records with the filters checked
+        /// against the limits.
+        fn records_query(config: &Config, dialect: Dialect, query: Vec<Input>, sort: Option<Sort>, anchor_id: i64, page_direction: &PageKind) -> Result<(Sql, Option<i64>), ServerFnError> {
+            limits(config).check(&query)?;
+            records_sql(dialect, query, sort, anchor_id, page_direction, config.limits.page_size)
+        }
+
+        /// Finds a page of records, answering repeated queries from the
+        /// cache until it expires or the data is re-imported. Explained
+        /// queries always run so their plan and timing are real.
+        pub async fn find_records(config: &Config, query: Vec<Input>, sort: Option<Sort>, anchor_id: i64, page_direction: PageKind, explain: bool) -> Result<Option<Data>, ServerFnError> {
+            use crate::cache::{generation, lookup, store, QueryKey};
+
+            if explain {
+                return explain_records(config, query, sort, anchor_id, page_direction).await;
+            }
+            // Checked before the cache, so an oversize query is refused even
+            // when its page is cached, and keyed by the filters as compiled.
+            let limits = limits(config);
+            let filters = compile_predicates(&limits, query.clone())?;
+            let key = QueryKey::new(&filters, &sort, anchor_id, &page_direction);
+            let generation = generation();
+            if let Some(data) = lookup(&key) {
+                return Ok(data);
+            }
+            let mut conn = limited_storage(&limits).await?;
+            let (sql, offset) = records_query(config, conn.dialect(), query, sort, anchor_id, &page_direction)?;
+            let planet_data = conn.fetch_planets(&sql).await.map_err(query_error(&limits))?;
+            let data = match offset {
+                Some(offset) => Data::build_sorted(planet_data, offset),
+                None => Data::build(planet_data),
+            };
+            store(key, data.clone(), generation);
+            Ok(data)
+        }
+
+        /// Fetches a page of records along with the database's plan for the
+        /// query and how long it took.
+        async fn explain_records(config: &Config, query: Vec<Input>, sort: Option<Sort>, anchor_id: i64, page_direction: PageKind) -> Result<Option<Data>, ServerFnError> {
+            use crate::model::explain::QueryPlan;
+            use std::time::Instant;
+
+            let limits = limits(config);
+            let mut conn = limited_storage(&limits).await?;
+            let (sql, offset) = records_query(config, conn.dialect(), query, sort, anchor_id, &page_direction)?;
+            let steps = conn.explain(&sql).await.map_err(query_error(&limits))?;
+
+            let start = Instant::now();
+            let planet_data = conn.fetch_planets(&sql).await.map_err(query_error(&limits))?;
+            let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+            let plan = QueryPlan { sql: sql.text(), steps, elapsed_ms };
+            let data = match offset {
+                Some(offset) => Data::build_sorted(planet_data, offset),
+                None => Data::build(planet_data),
+            };
+            Ok(data.map(|data| Data { plan: Some(plan), ..data }))
+        }
+
+        /// Groups the planets matching `query` by `group_by` and summarises
+        /// `column` over each group.
+        pub async fn find_stats(config: &Config, query: Vec<Input>, group_by: String, column: String) -> Result<Statistics, ServerFnError> {
+            use crate::model::columns::{find_column, ColumnKind};
+
+            let group_column = find_column(&group_by)
+                .filter(|column| !column.hidden)
+                .ok_or_else(|| ServerFnError::ServerError(format!("unknown group-by field '{group_by}'")))?;
+            let value_column = find_column(&column)
+                .filter(|column| !column.hidden && matches!(column.kind, ColumnKind::Integer | ColumnKind::Real))
+                .ok_or_else(|| ServerFnError::ServerError(format!("'{column}' is not a numeric field")))?;
+            let limits = limits(config);
+            let filters = compile_predicates(&limits, query)?;
+
+            let mut conn = limited_storage(&limits).await?;
+            let groups = conn.group_stats(&filters, group_column, value_column).await.map_err(query_error(&limits))?;
+
+            Ok(Statistics { group_by, column, groups })
+        }
+
+        /// A plot's axes and filters, checked and compiled.
+        pub struct PlotQuery {
+            x: String,
+            y: String,
+            x_column: &'static crate::model::columns::Column,
+            y_column: &'static crate::model::columns::Column,
+            filters: Vec<Predicate>,
+        }
+
+        /// Checks a plot's axes are numeric fields and its filters are
+        /// within the limits, without touching the database.
+        pub fn plot_query(config: &Config, query: Vec<Input>, x: String, y: String) -> Result<PlotQuery, ServerFnError> {
+            use crate::model::columns::{find_column, ColumnKind};
+
+            let numeric = |id: &str| {
+                find_column(id)
+                    .filter(|column| !column.hidden && matches!(column.kind, ColumnKind::Integer | ColumnKind::Real))
+                    .ok_or_else(|| ServerFnError::ServerError(format!("'{id}' is not a numeric field")))
+            };
+            let x_column = numeric(&x)?;
+            let y_column = numeric(&y)?;
+            let filters = compile_predicates(&limits(config), query)?;
+
+            Ok(PlotQuery { x, y, x_column, y_column, filters })
+        }
+
+        pub async fn fetch_plot(config: &Config, plot: PlotQuery) -> Result<PlotData, ServerFnError> {
+            use crate::model::plot::PLOT_LIMIT;
+
+            let PlotQuery { x, y, x_column, y_column, filters } = plot;
+            let limits = limits(config);
+            let mut conn = limited_storage(&limits).await?;
+            let mut points = conn.plot_points(&filters, x_column, y_column, PLOT_LIMIT + 1).await.map_err(query_error(&limits))?;
+            let truncated = points.len() > PLOT_LIMIT;
+            points.truncate(PLOT_LIMIT);
+
+            Ok(PlotData { x, y, points, truncated })
+        }
+
+        pub async fn find_plot(config: &Config, query: Vec<Input>, x: String, y: String) -> Result<PlotData, ServerFnError> {
+            fetch_plot(config, plot_query(config, query, x, y)?).await
+        }
+
+        pub async fn find_timeline(config: &Config, query: Vec<Input>, stack_by: StackBy) -> Result<Timeline, ServerFnError> {
+            use crate::model::columns::find_column;
+
+            let category = find_column(stack_by.column())
+                .ok_or_else(|| ServerFnError::ServerError(format!("unknown field '{}'", stack_by.column())))?;
+            let limits = limits(config);
+            let filters = compile_predicates(&limits, query)?;
+
+            let mut conn = limited_storage(&limits).await?;
+            let counts = conn.year_counts(&filters, category).await.map_err(query_error(&limits))?;
+
+            Ok(Timeline { stack_by, counts })
+        }
+
+        /// Finds the multi-planet systems with a planet matching the filters,
+        /// largest first, with all of their planets.
+        pub async fn find_systems(config: &Config, query: Vec<Input>) -> Result<Vec<PlanetarySystem>, ServerFnError> {
+            use crate::storage::systems_sql;
+
+            let limits = limits(config);
+            let filters = compile_predicates(&limits, query)?;
 
-            let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new("select discoverymethod as discovery_method, releasedate as release_date, * from exoplanet_data WHERE default_flag = true");
+            let mut conn = limited_storage(&limits).await?;
+            let sql = systems_sql(conn.dialect(), &filters);
+            let planets = conn.fetch_planets(&sql).await.map_err(query_error(&limits))?;
 
-            for input in query {
-                builder.push(format!(" AND {} {} ", input.field.as_str(), input.comparison_op.as_str()));
-                builder.push_bind(input.value);
+            Ok(PlanetarySystem::group(planets))
+        }
+
+        /// Finds the planets with the given ids, in the order of `ids`.
+        pub async fn find_planets(config: &Config, ids: Vec<i64>) -> Result<Vec<PlanetData>, ServerFnError> {
+            use crate::model::data::MAX_COMPARE;
+
+            if ids.len() > MAX_COMPARE {
+                return Err(ServerFnError::ServerError(format!("at most {MAX_COMPARE} planets can be compared")));
             }
 
-            match page_direction {
-                PageKind::Next => {
-                    builder.push(" AND id > ");
-                    builder.push_bind(anchor_id);
-                    builder.push(" ORDER BY id LIMIT 100;");
-                    let mut conn = db().await?;
-                    let planet_data = builder.build_query_as::<'_, PlanetData>().fetch_all(&mut conn).await?;
-
-                    Ok(Data::build(planet_data))
-                },
-                PageKind::Prev => {
-                    builder.push(" AND id < ");
-                    builder.push_bind(anchor_id);
-                    builder.push(" ORDER BY id DESC LIMIT 100;");
-                    let mut conn = db().await?;
-                    let planet_data = builder.build_query_as::<'_, PlanetData>().fetch_all(&mut conn).await?;
-
-                    Ok(Data::build(planet_data))
-                },
+            let mut conn = storage().await?;
+            fetch_planets(&mut conn, &ids, &limits(config)).await
+        }
+
+        /// Fetches the planets with the given ids, in the order of `ids`.
+        async fn fetch_planets(conn: &mut impl Storage, ids: &[i64], limits: &QueryLimits) -> Result<Vec<PlanetData>, ServerFnError> {
+            use crate::storage::planets_sql;
+
+            if ids.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let sql = planets_sql(conn.dialect(), ids);
+            let mut planets = conn.fetch_planets(&sql).await.map_err(query_error(limits))?;
+            planets.sort_by_key(|planet| ids.iter().position(|id| *id == planet.id));
+
+            Ok(planets)
+        }
+
+        /// Finds the `count` planets most similar to planet `id` on the given
+        /// parameters, most similar first.
+        pub async fn find_similar(config: &Config, id: i64, parameters: Vec<SimilarityParameter>, count: usize) -> Result<Option<Data>, ServerFnError> {
+            use crate::model::similarity::most_similar;
+
+            let limits = limits(config);
+            let mut conn = limited_storage(&limits).await?;
+            let candidates = conn.similarity_candidates(id).await.map_err(query_error(&limits))?;
+
+            let ids = most_similar(&candidates, id, &parameters, count)?
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>();
+            let planets = fetch_planets(&mut conn, &ids, &limits).await?;
+
+            Ok(Data::build_sorted(planets, 0))
+        }
+
+        pub async fn find_planet(config: &Config, id: i64) -> Result<Option<PlanetData>, ServerFnError> {
+            let mut conn = storage().await?;
+            Ok(fetch_planets(&mut conn, &[id], &limits(config)).await?.into_iter().next())
+        }
+
+        /// Adds a new query to the history of the user signed in to `req`.
+        /// Failures are logged rather than failing the query.
+        async fn remember_query(req: &HttpRequest, query: &[Input], sort: &Option<Sort>) {
+            use crate::auth::{db, request_user, store, AuthError};
+
+            let result = async {
+                if let Some(user) = request_user(req).await? {
+                    store::record_history(&mut db().await?, &user, query, sort).await?;
+                }
+                Ok::<_, AuthError>(())
+            };
+            if let Err(error) = result.await {
+                leptos::logging::error!("couldn't record the query in the history: {}", error);
+            }
+        }
+
+        /// Adds the query in a request to [`query_db`] to the history when
+        /// it's a new one, for requests answered without running it.
+        pub async fn remember_request(req: &HttpRequest) {
+            if let Ok(args) = serde_qs::from_str::<QueryDb>(req.query_string()) {
+                if args.anchor_id == 0 {
+                    remember_query(req, &args.query, &args.sort).await;
+                }
+            }
+        }
+
+        /// Whether the SQL console is turned on with `features.sql_console`.
+        pub fn console_enabled(config: &Config) -> bool {
+            config.features.sql_console
+        }
+
+        /// Runs a statement from the SQL console, unable to write and within
+        /// the query time budget, returning at most `CONSOLE_ROW_LIMIT` rows.
+        pub async fn find_console_rows(config: &Config, statement: String) -> Result<ConsoleResult, ServerFnError> {
+            use crate::model::console::{check_statement, ConsoleError, CONSOLE_ROW_LIMIT};
+
+            if !console_enabled(config) {
+                return Err(ConsoleError::Disabled.into());
             }
+            let statement = check_statement(&statement)?;
+            let limits = limits(config);
+            let mut conn = crate::storage::Connection::connect_read_only(&config.database.url).await?;
+            conn.set_timeout(limits.timeout).await?;
+            conn.fetch_text(statement, CONSOLE_ROW_LIMIT).await.map_err(query_error(&limits))
         }
     }
 }
 
-#[server(QueryDb, "/api")]
+// Read-only queries are sent as GETs, so browsers can revalidate the results
+// they have against the dataset version; see `http_cache`.
+#[server(QueryDb, "/api", "GetJson")]
 pub async fn query_db(
     query: Vec<Input>,
+    sort: Option<Sort>,
     anchor_id: i64,
     page_direction: PageKind,
+    explain: bool,
 ) -> Result<Option<Data>, ServerFnError> {
-    match find_records(query, anchor_id, page_direction).await {
+    // Only new queries go in the history, not their other pages.
+    if let (0, Some(req)) = (anchor_id, use_context::<HttpRequest>()) {
+        remember_query(&req, &query, &sort).await;
+    }
+    match find_records(&app_config()?, query, sort, anchor_id, page_direction, explain).await {
+        Ok(results) => Ok(results),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(QueryStats, "/api", "GetJson")]
+pub async fn query_stats(
+    query: Vec<Input>,
+    group_by: String,
+    column: String,
+) -> Result<Statistics, ServerFnError> {
+    match find_stats(&app_config()?, query, group_by, column).await {
         Ok(results) => Ok(results),
         Err(error) => {
             leptos::logging::error!("{}", error);
@@ -65,3 +369,282 @@ pub async fn query_db(
         }
     }
 }
+
+#[server(QueryPlot, "/api", "GetJson")]
+pub async fn query_plot(
+    query: Vec<Input>,
+    x: String,
+    y: String,
+) -> Result<PlotData, ServerFnError> {
+    match find_plot(&app_config()?, query, x, y).await {
+        Ok(results) => Ok(results),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(QueryTimeline, "/api", "GetJson")]
+pub async fn query_timeline(
+    query: Vec<Input>,
+    stack_by: StackBy,
+) -> Result<Timeline, ServerFnError> {
+    match find_timeline(&app_config()?, query, stack_by).await {
+        Ok(results) => Ok(results),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(QuerySystems, "/api", "GetJson")]
+pub async fn query_systems(query: Vec<Input>) -> Result<Vec<PlanetarySystem>, ServerFnError> {
+    match find_systems(&app_config()?, query).await {
+        Ok(results) => Ok(results),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(FindSimilar, "/api", "GetJson")]
+pub async fn find_similar_planets(
+    id: i64,
+    parameters: Vec<SimilarityParameter>,
+    count: usize,
+) -> Result<Option<Data>, ServerFnError> {
+    match find_similar(&app_config()?, id, parameters, count).await {
+        Ok(results) => Ok(results),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(GetPlanet, "/api", "GetJson")]
+pub async fn get_planet(id: i64) -> Result<Option<PlanetData>, ServerFnError> {
+    match find_planet(&app_config()?, id).await {
+        Ok(result) => Ok(result),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(GetPlanets, "/api", "GetJson")]
+pub async fn get_planets(ids: Vec<i64>) -> Result<Vec<PlanetData>, ServerFnError> {
+    match find_planets(&app_config()?, ids).await {
+        Ok(results) => Ok(results),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+/// Replaces `exoplanet_data` with the archive CSV export at `import.path`,
+/// returning the number of rows imported.
+#[server(ImportData, "/api")]
+pub async fn import_data() -> Result<u64, ServerFnError> {
+    use crate::auth::require_role;
+    use crate::import::import_csv;
+
+    require_role(Role::Admin).await?;
+    let path = app_config()?
+        .import
+        .path
+        .clone()
+        .ok_or_else(|| ServerFnError::ServerError("import.path isn't set".to_string()))?;
+    let mut conn = storage().await?;
+    match import_csv(&mut conn, &path).await {
+        Ok(rows) => {
+            leptos::logging::log!("Imported {} rows from {}", rows, path.display());
+            // Cached pages, the snapshot and results kept by browsers were
+            // read from the replaced table.
+            crate::cache::invalidate();
+            crate::snapshot::invalidate();
+            crate::http_cache::data_changed();
+            crate::storage::schema_changed();
+            #[cfg(feature = "columnar")]
+            crate::storage::columnar::reload(&mut conn).await?;
+            Ok(rows)
+        }
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(SqlConsoleEnabled, "/api")]
+pub async fn sql_console_enabled() -> Result<bool, ServerFnError> {
+    Ok(console_enabled(&app_config()?))
+}
+
+/// Runs a single read-only `SELECT` for the admin SQL console, which is off
+/// unless `features.sql_console` is set.
+#[server(RunSql, "/api")]
+pub async fn run_sql(statement: String) -> Result<ConsoleResult, ServerFnError> {
+    crate::auth::require_role(Role::Analyst).await?;
+    match find_console_rows(&app_config()?, statement).await {
+        Ok(result) => Ok(result),
+        Err(error) => {
+            leptos::logging::error!("{}", error);
+            Err(error)
+        }
+    }
+}
+
+#[server(CacheStatistics, "/api")]
+pub async fn cache_statistics() -> Result<CacheMetrics, ServerFnError> {
+    crate::auth::require_role(Role::Admin).await?;
+    Ok(crate::cache::metrics())
+}
+
+/// Empties the query cache, so pages are read from the database again.
+#[server(FlushCache, "/api")]
+pub async fn flush_cache() -> Result<(), ServerFnError> {
+    crate::auth::require_role(Role::Admin).await?;
+    crate::cache::invalidate();
+    Ok(())
+}
+
+#[server(GetAuthStatus, "/api")]
+pub async fn get_auth_status() -> Result<AuthStatus, ServerFnError> {
+    use crate::auth::{current_role, current_user, database_url, oidc_configured};
+
+    Ok(AuthStatus {
+        enabled: database_url().is_some(),
+        oidc: oidc_configured(),
+        user: current_user().await?,
+        role: current_role().await?,
+    })
+}
+
+/// Signs in a local user, starting a session kept in a cookie.
+#[server(Login, "/api")]
+pub async fn login(username: String, password: String) -> Result<User, ServerFnError> {
+    use crate::auth::{db, session_cookie, set_cookie, store};
+
+    let mut conn = db().await?;
+    let user = store::authenticate(&mut conn, &username, &password).await?;
+    let token = store::create_session(&mut conn, &user).await?;
+    set_cookie(session_cookie(token));
+    Ok(user)
+}
+
+#[server(Logout, "/api")]
+pub async fn logout() -> Result<(), ServerFnError> {
+    use crate::auth::{db, removal_cookie, set_cookie, store, SESSION_COOKIE};
+
+    let token = use_context::<HttpRequest>()
+        .and_then(|req| req.cookie(SESSION_COOKIE))
+        .map(|cookie| cookie.value().to_string());
+    if let Some(token) = token {
+        store::delete_session(&mut db().await?, &token).await?;
+    }
+    set_cookie(removal_cookie(SESSION_COOKIE));
+    Ok(())
+}
+
+#[server(SaveQuery, "/api")]
+pub async fn save_query(
+    name: String,
+    query: Vec<Input>,
+    sort: Option<Sort>,
+) -> Result<(), ServerFnError> {
+    use crate::auth::{signed_in, store};
+
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ServerFnError::ServerError(
+            "enter a name for the query".to_string(),
+        ));
+    }
+    let (mut conn, user) = signed_in().await?;
+    Ok(store::save_query(&mut conn, &user, name, &query, &sort).await?)
+}
+
+#[server(ListSavedQueries, "/api")]
+pub async fn list_saved_queries() -> Result<Vec<SavedQuery>, ServerFnError> {
+    use crate::auth::{signed_in, store};
+
+    let (mut conn, user) = signed_in().await?;
+    Ok(store::saved_queries(&mut conn, &user).await?)
+}
+
+#[server(DeleteSavedQuery, "/api")]
+pub async fn delete_saved_query(id: i64) -> Result<(), ServerFnError> {
+    use crate::auth::{signed_in, store};
+
+    let (mut conn, user) = signed_in().await?;
+    Ok(store::delete_saved_query(&mut conn, &user, id).await?)
+}
+
+#[server(QueryHistory, "/api")]
+pub async fn query_history() -> Result<Vec<HistoryEntry>, ServerFnError> {
+    use crate::auth::{signed_in, store};
+
+    let (mut conn, user) = signed_in().await?;
+    Ok(store::history(&mut conn, &user).await?)
+}
+
+/// The signed-in user's choice of output table columns, if they're signed in
+/// and have made one.
+#[server(GetColumnLayout, "/api")]
+pub async fn get_column_layout() -> Result<Option<Vec<String>>, ServerFnError> {
+    use crate::auth::{current_user, db, store};
+
+    match current_user().await? {
+        Some(user) => Ok(store::column_layout(&mut db().await?, &user).await?),
+        None => Ok(None),
+    }
+}
+
+#[server(SaveColumnLayout, "/api")]
+pub async fn save_column_layout(columns: Vec<String>) -> Result<(), ServerFnError> {
+    use crate::auth::{signed_in, store};
+    use crate::model::columns::find_column;
+
+    if let Some(id) = columns
+        .iter()
+        .find(|id| find_column(id).map_or(true, |column| column.hidden))
+    {
+        return Err(ServerFnError::ServerError(format!("unknown field '{id}'")));
+    }
+    let (mut conn, user) = signed_in().await?;
+    Ok(store::save_column_layout(&mut conn, &user, &columns).await?)
+}
+
+#[server(ListUsers, "/api")]
+pub async fn list_users() -> Result<Vec<User>, ServerFnError> {
+    use crate::auth::{db, require_role, store};
+
+    require_role(Role::Admin).await?;
+    Ok(store::users(&mut db().await?).await?)
+}
+
+/// Gives a user a role. Admins can't change their own, so there's always
+/// one left.
+#[server(SetRole, "/api")]
+pub async fn set_role(username: String, role: Role) -> Result<User, ServerFnError> {
+    use crate::auth::{current_user, db, require_role, store};
+
+    require_role(Role::Admin).await?;
+    if current_user()
+        .await?
+        .is_some_and(|user| user.username == username)
+    {
+        return Err(ServerFnError::ServerError(
+            "you can't change your own role".to_string(),
+        ));
+    }
+    store::set_role(&mut db().await?, &username, role)
+        .await?
+        .ok_or_else(|| ServerFnError::ServerError(format!("no user '{username}'")))
+}
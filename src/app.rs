@@ -1,15 +1,51 @@
-use crate::components::input::Home;
+use crate::{
+    api::sql_console_enabled,
+    components::{
+        account::{provide_account, Account, AccountMenu, LoginPage},
+        admin::Admin,
+        compare::{provide_compare_tray, Compare, CompareTrayBar},
+        console::SqlConsole,
+        input::{ActiveFilters, DisplayUnits, Home},
+        offline::{provide_data_status, DataFreshness},
+        planet::PlanetPage,
+        timeline::DiscoveryTimeline,
+    },
+    model::{input::Input, units::UnitSystem, user::Role},
+};
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
 
+/// The bundle's stylesheet, under its version when the server renders the
+/// page. Hydration keeps the server's link, which has the same id.
+fn stylesheet_href() -> String {
+    #[cfg(feature = "ssr")]
+    let dir = crate::http_cache::bundle_dir();
+    #[cfg(not(feature = "ssr"))]
+    let dir = "pkg";
+    format!("/{dir}/exoplanet_query_app.css")
+}
+
 #[component]
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
 
+    let (filters, set_filters) = create_signal(Vec::<Input>::new());
+    provide_context(ActiveFilters {
+        filters,
+        set_filters,
+    });
+    let (system, set_system) = create_signal(UnitSystem::default());
+    provide_context(DisplayUnits { system, set_system });
+    provide_compare_tray();
+    provide_data_status();
+    provide_account();
+    let account: Account = use_context().unwrap();
+    let console_enabled = create_resource(|| (), |_| sql_console_enabled());
+
     view! {
-        <Stylesheet id="leptos" href="/pkg/exoplanet_query_app.css"/>
+        <Stylesheet id="leptos" href=stylesheet_href()/>
         <Link rel="icon" type_="image/x-icon" href="/assets/favicon.ico"/>
         // sets the document title
         <Title text="Exoplanet Query App"/>
@@ -19,11 +55,45 @@ pub fn App() -> impl IntoView {
                     <A href="/" class="btn btn-ghost normal-case text-xl">
                         "Home"
                     </A>
+                    <A href="/timeline" class="btn btn-ghost normal-case text-xl">
+                        "Timeline"
+                    </A>
+                    // The static build has no server to administer, and only
+                    // admins may.
+                    <Show when=move || !cfg!(feature = "csr") && account.allows(Role::Admin) fallback=|| ()>
+                        <A href="/admin" class="btn btn-ghost normal-case text-xl">
+                            "Admin"
+                        </A>
+                    </Show>
+                    // Analysts reach the console from here rather than the
+                    // admin page.
+                    <Suspense fallback=|| ()>
+                        <Show
+                            when=move || {
+                                !cfg!(feature = "csr") && account.role() == Some(Role::Analyst)
+                                    && console_enabled.get().and_then(Result::ok).unwrap_or(false)
+                            }
+                            fallback=|| ()
+                        >
+                            <A href="/admin/sql" class="btn btn-ghost normal-case text-xl">
+                                "SQL Console"
+                            </A>
+                        </Show>
+                    </Suspense>
+                    <DataFreshness/>
+                    <AccountMenu/>
                 </div>
             </nav>
+            <CompareTrayBar/>
             <main>
                 <Routes>
                     <Route path="/" view=Home/>
+                    <Route path="/timeline" view=DiscoveryTimeline/>
+                    <Route path="/compare" view=Compare/>
+                    <Route path="/planet/:id" view=PlanetPage/>
+                    <Route path="/admin" view=Admin/>
+                    <Route path="/admin/sql" view=SqlConsole/>
+                    <Route path="/login" view=LoginPage/>
                 </Routes>
             </main>
         </Router>
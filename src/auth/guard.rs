@@ -0,0 +1,126 @@
+//! Keeps pages from visitors without the role they need.
+//!
+//! Server functions check roles themselves with
+//! [`require_role`](crate::auth::require_role); this covers the pages
+//! rendered for them, so a viewer asking for `/admin` is turned away before
+//! the page renders rather than shown one that fails on every action.
+
+use crate::{
+    auth::{anonymous_role, database_url, request_user},
+    model::user::Role,
+};
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorInternalServerError,
+    http::header::LOCATION,
+    HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+};
+
+/// Middleware requiring a role for paths under each of its prefixes. The
+/// longest matching prefix decides.
+#[derive(Clone, Default)]
+pub struct RoleGuard {
+    paths: Rc<Vec<(&'static str, Role)>>,
+}
+
+impl RoleGuard {
+    pub fn new() -> RoleGuard {
+        RoleGuard::default()
+    }
+
+    /// Requires `role` for `prefix` and the paths below it.
+    pub fn path(mut self, prefix: &'static str, role: Role) -> RoleGuard {
+        Rc::make_mut(&mut self.paths).push((prefix, role));
+        self
+    }
+
+    fn required_role(&self, path: &str) -> Option<Role> {
+        self.paths
+            .iter()
+            .filter(|(prefix, _)| {
+                path.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, role)| *role)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RoleGuard
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RoleGuardMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RoleGuardMiddleware {
+            service: Rc::new(service),
+            guard: self.clone(),
+        }))
+    }
+}
+
+pub struct RoleGuardMiddleware<S> {
+    service: Rc<S>,
+    guard: RoleGuard,
+}
+
+impl<S, B> Service<ServiceRequest> for RoleGuardMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let required = self.guard.required_role(req.path());
+
+        Box::pin(async move {
+            if let Some(role) = required {
+                let user = request_user(req.request()).await.map_err(|error| {
+                    leptos::logging::error!("{}", error);
+                    ErrorInternalServerError(error)
+                })?;
+                let refusal = match user {
+                    Some(user) if user.role >= role => None,
+                    Some(_) => Some(
+                        HttpResponse::Forbidden().body(format!("This page needs the {role} role.")),
+                    ),
+                    None if anonymous_role(req.request()) >= role => None,
+                    // There's nowhere to sign in.
+                    None if database_url().is_none() => Some(HttpResponse::Forbidden().body(
+                        format!("This page needs the {role} role, and sign-in is off."),
+                    )),
+                    None => Some(
+                        HttpResponse::Found()
+                            .insert_header((LOCATION, "/login"))
+                            .finish(),
+                    ),
+                };
+                if let Some(refusal) = refusal {
+                    return Ok(req.into_response(refusal).map_into_right_body());
+                }
+            }
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
@@ -0,0 +1,175 @@
+//! Optional sign-in: local users with argon2-hashed passwords, and an OpenID
+//! Connect provider when one is configured.
+//!
+//! Each user has a [`Role`] limiting what they can do. Visitors who aren't
+//! signed in are viewers.
+//!
+//! Users, their sessions and what they keep (saved queries, history and
+//! column layouts) live in the SQLite database at `auth.database_url`,
+//! separate from the dataset so it works alongside Postgres too. Without it
+//! sign-in is off and everyone uses the app anonymously as a viewer, unless
+//! `features.open_admin` gives them every role.
+
+pub mod guard;
+pub mod oidc;
+pub mod password;
+pub mod session;
+pub mod store;
+
+use actix_web::{
+    cookie::Cookie,
+    http::header::{HeaderValue, SET_COOKIE},
+    web, HttpRequest,
+};
+use leptos::{use_context, ServerFnError};
+use leptos_actix::ResponseOptions;
+use sqlx::{pool::PoolConnection, sqlite::SqliteConnectOptions, Sqlite, SqlitePool};
+use std::{fmt, str::FromStr, sync::OnceLock};
+
+use crate::{
+    config::Config,
+    model::user::{Role, User},
+};
+
+pub use guard::RoleGuard;
+pub use oidc::{
+    identity_provider, oidc_callback, oidc_configured, oidc_login, Identity, IdentityProvider,
+};
+pub use session::{removal_cookie, session_cookie, SESSION_COOKIE};
+
+#[derive(Debug)]
+pub enum AuthError {
+    Disabled,
+    SignedOut,
+    Forbidden(Role),
+    InvalidCredentials,
+    UsernameTaken(String),
+    Hash(String),
+    Provider(String),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Disabled => write!(f, "sign-in isn't enabled on this server"),
+            AuthError::SignedOut => write!(f, "sign in to do that"),
+            AuthError::Forbidden(role) => write!(f, "that needs the {role} role"),
+            AuthError::InvalidCredentials => write!(f, "wrong username or password"),
+            AuthError::UsernameTaken(username) => write!(f, "'{username}' is already taken"),
+            AuthError::Hash(error) => write!(f, "couldn't hash the password: {error}"),
+            AuthError::Provider(error) => write!(f, "the sign-in provider failed: {error}"),
+            AuthError::Database(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl From<sqlx::Error> for AuthError {
+    fn from(error: sqlx::Error) -> Self {
+        AuthError::Database(error)
+    }
+}
+
+/// The user database's URL, when sign-in is enabled.
+pub fn database_url() -> Option<String> {
+    crate::config::config().auth.database_url.clone()
+}
+
+static USERS: OnceLock<SqlitePool> = OnceLock::new();
+
+/// Opens the user database, when sign-in is enabled, and creates its tables.
+/// `main` calls this once at startup; [`db`] then shares its connections.
+pub async fn open_database() -> Result<(), AuthError> {
+    let Some(url) = database_url() else {
+        return Ok(());
+    };
+    // Every connection cascades deletes from users to their sessions.
+    let options = SqliteConnectOptions::from_str(&url)?.foreign_keys(true);
+    let pool = SqlitePool::connect_with(options).await?;
+    store::create_tables(&mut *pool.acquire().await?).await?;
+    // Opening it twice keeps the first pool.
+    let _ = USERS.set(pool);
+    Ok(())
+}
+
+/// A connection to the user database from the pool [`open_database`]
+/// opened.
+pub async fn db() -> Result<PoolConnection<Sqlite>, AuthError> {
+    let pool = USERS.get().ok_or(AuthError::Disabled)?;
+    Ok(pool.acquire().await?)
+}
+
+/// The user signed in to `req`, if sign-in is enabled and its session
+/// cookie is current.
+pub async fn request_user(req: &HttpRequest) -> Result<Option<User>, AuthError> {
+    let Some(cookie) = req.cookie(SESSION_COOKIE) else {
+        return Ok(None);
+    };
+    if USERS.get().is_none() {
+        return Ok(None);
+    }
+    store::session_user(&mut db().await?, cookie.value()).await
+}
+
+/// The user signed in to the request a server function is handling.
+pub async fn current_user() -> Result<Option<User>, AuthError> {
+    match use_context::<HttpRequest>() {
+        Some(req) => request_user(&req).await,
+        None => Ok(None),
+    }
+}
+
+/// What visitors who aren't signed in may do, going by the configuration
+/// registered as app data. With sign-in off nobody can show they're more
+/// than a viewer, so only `features.open_admin` lets them.
+pub fn anonymous_role(req: &HttpRequest) -> Role {
+    let open = req
+        .app_data::<web::Data<Config>>()
+        .is_some_and(|config| config.auth.database_url.is_none() && config.features.open_admin);
+    if open {
+        Role::Admin
+    } else {
+        Role::Viewer
+    }
+}
+
+/// What the visitor making `req` may do.
+pub async fn request_role(req: &HttpRequest) -> Result<Role, AuthError> {
+    Ok(request_user(req)
+        .await?
+        .map_or_else(|| anonymous_role(req), |user| user.role))
+}
+
+/// What the visitor making the request a server function is handling may do.
+pub async fn current_role() -> Result<Role, AuthError> {
+    match use_context::<HttpRequest>() {
+        Some(req) => request_role(&req).await,
+        None => Ok(Role::Viewer),
+    }
+}
+
+/// Fails unless the visitor has at least `role`.
+pub async fn require_role(role: Role) -> Result<(), AuthError> {
+    if current_role().await? >= role {
+        Ok(())
+    } else {
+        Err(AuthError::Forbidden(role))
+    }
+}
+
+/// Sets a cookie on the response to the server function being handled.
+pub fn set_cookie(cookie: Cookie<'static>) {
+    let response = use_context::<ResponseOptions>();
+    if let (Some(response), Ok(value)) = (response, HeaderValue::from_str(&cookie.to_string())) {
+        response.append_header(SET_COOKIE, value);
+    }
+}
+
+/// The signed-in user with a connection to their data, for server functions
+/// that only make sense signed in.
+pub async fn signed_in() -> Result<(PoolConnection<Sqlite>, User), ServerFnError> {
+    let user = current_user().await?.ok_or(AuthError::SignedOut)?;
+    Ok((db().await?, user))
+}
@@ -0,0 +1,240 @@
+//! Sign-in through an OpenID Connect provider with the authorization code
+//! flow.
+//!
+//! The provider sits behind [`IdentityProvider`] so it can be swapped for a
+//! mock in tests. [`OidcProvider`] finds a real provider's endpoints through
+//! discovery and reads the user from its userinfo endpoint, with the client
+//! secret authenticating the code exchange.
+
+use crate::auth::{
+    database_url, db,
+    session::{new_token, private_cookie, removal_cookie, session_cookie},
+    store, AuthError,
+};
+use actix_web::{
+    cookie::time::Duration,
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get,
+    http::header::LOCATION,
+    web, HttpRequest, HttpResponse,
+};
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Holds the `state` sent to the provider until it comes back.
+const STATE_COOKIE: &str = "oidc_state";
+
+/// Who the provider says signed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// Identifies the user across sign-ins: the issuer and its subject.
+    pub subject: String,
+    pub username: Option<String>,
+}
+
+#[async_trait]
+pub trait IdentityProvider: Send + Sync {
+    /// Where to send the browser to sign in, passing `state` back to the
+    /// callback.
+    fn authorize_url(&self, state: &str) -> String;
+
+    /// Trades the code from the callback for the user's identity.
+    async fn exchange(&self, code: &str) -> Result<Identity, AuthError>;
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    sub: String,
+    preferred_username: Option<String>,
+    email: Option<String>,
+}
+
+pub struct OidcProvider {
+    client: reqwest::Client,
+    client_id: String,
+    client_secret: String,
+    redirect_url: String,
+    discovery: Discovery,
+}
+
+fn provider_error(error: reqwest::Error) -> AuthError {
+    AuthError::Provider(error.to_string())
+}
+
+impl OidcProvider {
+    /// Finds the endpoints of the provider at `issuer` from its discovery
+    /// document.
+    pub async fn discover(
+        issuer: &str,
+        client_id: String,
+        client_secret: String,
+        redirect_url: String,
+    ) -> Result<OidcProvider, AuthError> {
+        let client = reqwest::Client::new();
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        );
+        let discovery = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json::<Discovery>()
+            .await
+            .map_err(provider_error)?;
+        Ok(OidcProvider {
+            client,
+            client_id,
+            client_secret,
+            redirect_url,
+            discovery,
+        })
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for OidcProvider {
+    fn authorize_url(&self, state: &str) -> String {
+        Url::parse_with_params(
+            &self.discovery.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("scope", "openid profile email"),
+                ("state", state),
+            ],
+        )
+        .map_or_else(
+            |_| self.discovery.authorization_endpoint.clone(),
+            String::from,
+        )
+    }
+
+    async fn exchange(&self, code: &str) -> Result<Identity, AuthError> {
+        let token = self
+            .client
+            .post(&self.discovery.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json::<TokenResponse>()
+            .await
+            .map_err(provider_error)?;
+        let info = self
+            .client
+            .get(&self.discovery.userinfo_endpoint)
+            .bearer_auth(token.access_token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?
+            .json::<UserInfo>()
+            .await
+            .map_err(provider_error)?;
+        Ok(Identity {
+            subject: format!("{} {}", self.discovery.issuer, info.sub),
+            username: info.preferred_username.or(info.email),
+        })
+    }
+}
+
+/// Whether sign-in is enabled with a provider in `auth.oidc`.
+pub fn oidc_configured() -> bool {
+    database_url().is_some() && crate::config::config().auth.oidc.is_some()
+}
+
+/// The provider configured in `auth.oidc`, if sign-in is enabled. The
+/// configuration is validated at startup, so its settings are all there.
+pub async fn identity_provider() -> Result<Option<Arc<dyn IdentityProvider>>, AuthError> {
+    let Some(oidc) = crate::config::config()
+        .auth
+        .oidc
+        .as_ref()
+        .filter(|_| oidc_configured())
+    else {
+        return Ok(None);
+    };
+    let provider = OidcProvider::discover(
+        &oidc.issuer,
+        oidc.client_id.clone(),
+        oidc.client_secret.clone(),
+        oidc.redirect_url.clone(),
+    )
+    .await?;
+    Ok(Some(Arc::new(provider)))
+}
+
+/// Sends the browser to the provider to sign in.
+#[get("/auth/oidc/login")]
+pub async fn oidc_login(provider: web::Data<dyn IdentityProvider>) -> HttpResponse {
+    let state = new_token();
+    HttpResponse::Found()
+        .insert_header((LOCATION, provider.authorize_url(&state)))
+        .cookie(private_cookie(STATE_COOKIE, state, Duration::minutes(10)))
+        .finish()
+}
+
+#[derive(Deserialize)]
+pub struct Callback {
+    code: String,
+    state: String,
+}
+
+/// Where the provider sends the browser back to, signing the user in.
+#[get("/auth/oidc/callback")]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    callback: web::Query<Callback>,
+    provider: web::Data<dyn IdentityProvider>,
+) -> actix_web::Result<HttpResponse> {
+    // The state ties the callback to a sign-in this browser started.
+    if req
+        .cookie(STATE_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        != Some(callback.state.clone())
+    {
+        return Err(ErrorBadRequest("the sign-in attempt has expired"));
+    }
+
+    let sign_in = async {
+        let identity = provider.exchange(&callback.code).await?;
+        let mut conn = db().await?;
+        let user = store::oidc_user(&mut conn, &identity).await?;
+        store::create_session(&mut conn, &user).await
+    };
+    let token = sign_in.await.map_err(|error| {
+        leptos::logging::error!("{}", error);
+        ErrorInternalServerError(error)
+    })?;
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, "/"))
+        .cookie(session_cookie(token))
+        .cookie(removal_cookie(STATE_COOKIE))
+        .finish())
+}
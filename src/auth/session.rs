@@ -0,0 +1,45 @@
+use actix_web::cookie::{time::Duration, Cookie, SameSite};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+pub const SESSION_COOKIE: &str = "session";
+/// How long a sign-in lasts.
+pub const SESSION_DAYS: i64 = 30;
+
+/// A random token for a session or sign-in attempt.
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+/// What the user database keeps for a session token: its SHA-256, so
+/// reading the database doesn't let anyone sign in.
+pub fn token_hash(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// A cookie the browser sends back with every request, only over HTTPS,
+/// but scripts can't read.
+pub fn private_cookie(name: &'static str, value: String, max_age: Duration) -> Cookie<'static> {
+    Cookie::build(name, value)
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .max_age(max_age)
+        .finish()
+}
+
+pub fn session_cookie(token: String) -> Cookie<'static> {
+    private_cookie(SESSION_COOKIE, token, Duration::days(SESSION_DAYS))
+}
+
+/// Replaces a cookie with an expired one, so the browser drops it.
+pub fn removal_cookie(name: &'static str) -> Cookie<'static> {
+    private_cookie(name, String::new(), Duration::ZERO)
+}
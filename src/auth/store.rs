@@ -0,0 +1,380 @@
+//! The user database: accounts, sessions and what users keep.
+
+use crate::{
+    auth::{
+        password,
+        session::{new_token, token_hash, SESSION_DAYS},
+        AuthError, Identity,
+    },
+    model::{
+        input::{Input, Sort},
+        user::{HistoryEntry, Role, SavedQuery, User, HISTORY_LIMIT},
+    },
+};
+use sqlx::{sqlite::SqliteRow, Executor, Row, SqliteConnection};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT,
+    oidc_subject TEXT UNIQUE,
+    role TEXT NOT NULL DEFAULT 'viewer'
+);
+-- `token` is the hash of the cookie's token.
+CREATE TABLE IF NOT EXISTS sessions (
+    token TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    expires_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS saved_queries (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    query TEXT NOT NULL,
+    sort TEXT,
+    UNIQUE (user_id, name)
+);
+CREATE TABLE IF NOT EXISTS query_history (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    query TEXT NOT NULL,
+    sort TEXT,
+    ran_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS query_history_user ON query_history (user_id, id);
+CREATE TABLE IF NOT EXISTS column_layouts (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    columns TEXT NOT NULL
+);
+";
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Queries and sorts are kept as JSON.
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("queries serialize to JSON")
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, sqlx::Error> {
+    serde_json::from_str(json).map_err(|error| sqlx::Error::Decode(Box::new(error)))
+}
+
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .is_some_and(|error| error.is_unique_violation())
+}
+
+/// Reads a user from a row with their `id`, `username` and `role`.
+fn user_from_row(row: &SqliteRow) -> User {
+    User {
+        id: row.get("id"),
+        username: row.get("username"),
+        role: Role::parse(row.get("role")).unwrap_or_default(),
+    }
+}
+
+pub async fn create_tables(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    conn.execute(SCHEMA).await?;
+    // Databases from before roles get everyone as a viewer.
+    let has_role = sqlx::query("SELECT 1 FROM pragma_table_info('users') WHERE name = 'role'")
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some();
+    if !has_role {
+        conn.execute("ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'viewer'")
+            .await?;
+    }
+    Ok(())
+}
+
+/// Adds a local user who signs in with `password`.
+pub async fn create_user(
+    conn: &mut SqliteConnection,
+    username: &str,
+    password: &str,
+    role: Role,
+) -> Result<User, AuthError> {
+    let hash = password::hash(password)?;
+    let result = sqlx::query("INSERT INTO users (username, password_hash, role) VALUES (?, ?, ?)")
+        .bind(username)
+        .bind(hash)
+        .bind(role.as_str())
+        .execute(&mut *conn)
+        .await;
+    match result {
+        Ok(result) => Ok(User {
+            id: result.last_insert_rowid(),
+            username: username.to_string(),
+            role,
+        }),
+        Err(error) if is_unique_violation(&error) => {
+            Err(AuthError::UsernameTaken(username.to_string()))
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Checks a local user's password.
+pub async fn authenticate(
+    conn: &mut SqliteConnection,
+    username: &str,
+    password: &str,
+) -> Result<User, AuthError> {
+    let row = sqlx::query("SELECT id, username, password_hash, role FROM users WHERE username = ?")
+        .bind(username)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let user = row.and_then(|row| {
+        let hash = row.get::<Option<String>, _>("password_hash")?;
+        password::verify(password, &hash).then(|| user_from_row(&row))
+    });
+    user.ok_or(AuthError::InvalidCredentials)
+}
+
+/// The user for an identity from the OpenID Connect provider, added on
+/// their first sign-in. Their preferred username is used if it's free.
+pub async fn oidc_user(
+    conn: &mut SqliteConnection,
+    identity: &Identity,
+) -> Result<User, AuthError> {
+    let row = sqlx::query("SELECT id, username, role FROM users WHERE oidc_subject = ?")
+        .bind(&identity.subject)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if let Some(row) = row {
+        return Ok(user_from_row(&row));
+    }
+
+    let mut usernames = identity.username.iter().chain([&identity.subject]);
+    loop {
+        let Some(username) = usernames.next() else {
+            return Err(AuthError::UsernameTaken(identity.subject.clone()));
+        };
+        let result = sqlx::query("INSERT INTO users (username, oidc_subject) VALUES (?, ?)")
+            .bind(username)
+            .bind(&identity.subject)
+            .execute(&mut *conn)
+            .await;
+        match result {
+            Ok(result) => {
+                return Ok(User {
+                    id: result.last_insert_rowid(),
+                    username: username.clone(),
+                    role: Role::default(),
+                })
+            }
+            Err(error) if is_unique_violation(&error) => continue,
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// Starts a session for `user`, returning its token. Only the token's hash
+/// is kept.
+pub async fn create_session(conn: &mut SqliteConnection, user: &User) -> Result<String, AuthError> {
+    let token = new_token();
+    sqlx::query("INSERT INTO sessions (token, user_id, expires_at) VALUES (?, ?, ?)")
+        .bind(token_hash(&token))
+        .bind(user.id)
+        .bind(now() + SESSION_DAYS * 24 * 60 * 60)
+        .execute(&mut *conn)
+        .await?;
+    Ok(token)
+}
+
+/// The user a session belongs to, unless it has expired.
+pub async fn session_user(
+    conn: &mut SqliteConnection,
+    token: &str,
+) -> Result<Option<User>, AuthError> {
+    let row = sqlx::query(
+        "SELECT users.id, users.username, users.role \
+        FROM sessions JOIN users ON users.id = sessions.user_id \
+        WHERE sessions.token = ? AND sessions.expires_at > ?",
+    )
+    .bind(token_hash(token))
+    .bind(now())
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(row.as_ref().map(user_from_row))
+}
+
+pub async fn users(conn: &mut SqliteConnection) -> Result<Vec<User>, AuthError> {
+    let rows = sqlx::query("SELECT id, username, role FROM users ORDER BY username")
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(rows.iter().map(user_from_row).collect())
+}
+
+/// Changes a user's role, returning them if they exist.
+pub async fn set_role(
+    conn: &mut SqliteConnection,
+    username: &str,
+    role: Role,
+) -> Result<Option<User>, AuthError> {
+    let row =
+        sqlx::query("UPDATE users SET role = ? WHERE username = ? RETURNING id, username, role")
+            .bind(role.as_str())
+            .bind(username)
+            .fetch_optional(&mut *conn)
+            .await?;
+    Ok(row.as_ref().map(user_from_row))
+}
+
+pub async fn delete_session(conn: &mut SqliteConnection, token: &str) -> Result<(), AuthError> {
+    sqlx::query("DELETE FROM sessions WHERE token = ? OR expires_at <= ?")
+        .bind(token_hash(token))
+        .bind(now())
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Saves a query under `name`, replacing any of the user's with that name.
+pub async fn save_query(
+    conn: &mut SqliteConnection,
+    user: &User,
+    name: &str,
+    query: &[Input],
+    sort: &Option<Sort>,
+) -> Result<(), AuthError> {
+    sqlx::query(
+        "INSERT INTO saved_queries (user_id, name, query, sort) VALUES (?, ?, ?, ?) \
+        ON CONFLICT (user_id, name) DO UPDATE SET query = excluded.query, sort = excluded.sort",
+    )
+    .bind(user.id)
+    .bind(name)
+    .bind(to_json(&query))
+    .bind(sort.as_ref().map(to_json))
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn saved_queries(
+    conn: &mut SqliteConnection,
+    user: &User,
+) -> Result<Vec<SavedQuery>, AuthError> {
+    let rows = sqlx::query(
+        "SELECT id, name, query, sort FROM saved_queries WHERE user_id = ? ORDER BY name",
+    )
+    .bind(user.id)
+    .fetch_all(&mut *conn)
+    .await?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(SavedQuery {
+                id: row.get("id"),
+                name: row.get("name"),
+                query: from_json(row.get("query"))?,
+                sort: row
+                    .get::<Option<&str>, _>("sort")
+                    .map(from_json)
+                    .transpose()?,
+            })
+        })
+        .collect()
+}
+
+/// Deletes one of the user's saved queries; others' are left alone.
+pub async fn delete_saved_query(
+    conn: &mut SqliteConnection,
+    user: &User,
+    id: i64,
+) -> Result<(), AuthError> {
+    sqlx::query("DELETE FROM saved_queries WHERE id = ? AND user_id = ?")
+        .bind(id)
+        .bind(user.id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Adds a query to the user's history, dropping entries past
+/// `HISTORY_LIMIT`.
+pub async fn record_history(
+    conn: &mut SqliteConnection,
+    user: &User,
+    query: &[Input],
+    sort: &Option<Sort>,
+) -> Result<(), AuthError> {
+    sqlx::query("INSERT INTO query_history (user_id, query, sort, ran_at) VALUES (?, ?, ?, ?)")
+        .bind(user.id)
+        .bind(to_json(&query))
+        .bind(sort.as_ref().map(to_json))
+        .bind(now())
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "DELETE FROM query_history WHERE user_id = ? AND id NOT IN \
+        (SELECT id FROM query_history WHERE user_id = ? ORDER BY id DESC LIMIT ?)",
+    )
+    .bind(user.id)
+    .bind(user.id)
+    .bind(HISTORY_LIMIT as i64)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// The user's history, most recent first.
+pub async fn history(
+    conn: &mut SqliteConnection,
+    user: &User,
+) -> Result<Vec<HistoryEntry>, AuthError> {
+    let rows = sqlx::query(
+        "SELECT id, query, sort, ran_at FROM query_history WHERE user_id = ? ORDER BY id DESC",
+    )
+    .bind(user.id)
+    .fetch_all(&mut *conn)
+    .await?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(HistoryEntry {
+                id: row.get("id"),
+                query: from_json(row.get("query"))?,
+                sort: row
+                    .get::<Option<&str>, _>("sort")
+                    .map(from_json)
+                    .transpose()?,
+                ran_at: row.get("ran_at"),
+            })
+        })
+        .collect()
+}
+
+/// The ids of the columns the user shows in the output table, if they've
+/// chosen them.
+pub async fn column_layout(
+    conn: &mut SqliteConnection,
+    user: &User,
+) -> Result<Option<Vec<String>>, AuthError> {
+    let row = sqlx::query("SELECT columns FROM column_layouts WHERE user_id = ?")
+        .bind(user.id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(row.map(|row| from_json(row.get("columns"))).transpose()?)
+}
+
+pub async fn save_column_layout(
+    conn: &mut SqliteConnection,
+    user: &User,
+    columns: &[String],
+) -> Result<(), AuthError> {
+    sqlx::query(
+        "INSERT INTO column_layouts (user_id, columns) VALUES (?, ?) \
+        ON CONFLICT (user_id) DO UPDATE SET columns = excluded.columns",
+    )
+    .bind(user.id)
+    .bind(to_json(&columns))
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,334 @@
+//! An in-process LRU cache of `find_records` results, shared by every actix
+//! worker thread.
+//!
+//! Entries expire after a TTL and the whole cache is cleared after an import,
+//! so a cached page is never older than the data it was read from. Clearing
+//! starts a new generation, and a page read in an earlier one isn't stored,
+//! since it may have been read from the data that was just replaced.
+
+use crate::model::{
+    cache::CacheMetrics,
+    data::Data,
+    filter::Predicate,
+    input::{PageKind, Sort},
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// A query in canonical form: its filters as compiled, so inputs share a key
+/// exactly when they filter the same way, sorted as they're ANDed together.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryKey {
+    filters: Vec<String>,
+    sort: Option<(String, bool)>,
+    anchor_id: i64,
+    next: bool,
+}
+
+impl QueryKey {
+    pub fn new(
+        filters: &[Predicate],
+        sort: &Option<Sort>,
+        anchor_id: i64,
+        page: &PageKind,
+    ) -> QueryKey {
+        let mut filters = filters.iter().map(filter_key).collect::<Vec<_>>();
+        filters.sort();
+        filters.dedup();
+        QueryKey {
+            filters,
+            sort: sort
+                .as_ref()
+                .map(|sort| (sort.field.clone(), sort.descending)),
+            anchor_id,
+            next: matches!(page, PageKind::Next),
+        }
+    }
+}
+
+/// A compiled filter as text. Values are written with `Debug`, which quotes
+/// text and writes numbers exactly.
+fn filter_key(filter: &Predicate) -> String {
+    match filter {
+        Predicate::Compare { column, op, value } => {
+            format!("{} {} {value:?}", column.id, op.sql())
+        }
+        Predicate::In { column, values } => format!("{} in {values:?}", column.id),
+        Predicate::RelativeError { column, max } => format!("{} rel_err< {max:?}", column.id),
+        Predicate::Consistent {
+            column,
+            target,
+            sigmas,
+        } => format!("{} consistent {target:?} {sigmas:?}", column.id),
+    }
+}
+
+struct Entry {
+    value: Option<Data>,
+    inserted: Instant,
+    /// The tick of the last lookup, for finding the least recently used.
+    used: u64,
+}
+
+pub struct QueryCache {
+    entries: HashMap<QueryKey, Entry>,
+    capacity: usize,
+    ttl: Duration,
+    tick: u64,
+    generation: u64,
+}
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+static EVICTIONS: AtomicU64 = AtomicU64::new(0);
+
+impl QueryCache {
+    pub fn new(capacity: usize, ttl: Duration) -> QueryCache {
+        QueryCache {
+            entries: HashMap::new(),
+            capacity,
+            ttl,
+            tick: 0,
+            generation: 0,
+        }
+    }
+
+    /// The number of times the cache has been cleared.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn get(&mut self, key: &QueryKey) -> Option<Option<Data>> {
+        self.tick += 1;
+        let tick = self.tick;
+        let expired = match self.entries.get_mut(key) {
+            Some(entry) if entry.inserted.elapsed() < self.ttl => {
+                entry.used = tick;
+                return Some(entry.value.clone());
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.entries.remove(key);
+        }
+        None
+    }
+
+    /// Stores a result read in `generation`, unless the cache has been
+    /// cleared since.
+    pub fn insert(&mut self, key: QueryKey, value: Option<Data>, generation: u64) {
+        if self.capacity == 0 || generation != self.generation {
+            return;
+        }
+        self.tick += 1;
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+                EVICTIONS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                inserted: Instant::now(),
+                used: self.tick,
+            },
+        );
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.generation += 1;
+    }
+}
+
+/// The process-wide cache, sized and timed by the `cache` section of the
+/// configuration. A size of 0 disables caching.
+fn cache() -> &'static Mutex<QueryCache> {
+    static CACHE: OnceLock<Mutex<QueryCache>> = OnceLock::new();
+    CACHE.get_or_init(|| {
+        let settings = &crate::config::config().cache;
+        Mutex::new(QueryCache::new(
+            settings.size,
+            Duration::from_secs(settings.ttl_secs),
+        ))
+    })
+}
+
+/// The current generation, to read before running a query whose result
+/// will be stored.
+pub fn generation() -> u64 {
+    cache()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .generation()
+}
+
+/// Looks up a query, counting the hit or miss.
+pub fn lookup(key: &QueryKey) -> Option<Option<Data>> {
+    // A panic while holding the lock can't leave the map inconsistent, so
+    // keep using it.
+    let result = cache()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(key);
+    match result {
+        Some(_) => HITS.fetch_add(1, Ordering::Relaxed),
+        None => MISSES.fetch_add(1, Ordering::Relaxed),
+    };
+    result
+}
+
+/// Stores a result read in `generation`, unless the cache has been
+/// invalidated since.
+pub fn store(key: QueryKey, value: Option<Data>, generation: u64) {
+    cache()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(key, value, generation);
+}
+
+/// Drops every cached result, for after the data has changed.
+pub fn invalidate() {
+    cache()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clear();
+}
+
+pub fn metrics() -> CacheMetrics {
+    let entries = cache()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entries
+        .len() as u64;
+    CacheMetrics {
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        evictions: EVICTIONS.load(Ordering::Relaxed),
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::input::Input;
+
+    fn filters(inputs: &[(&str, &str, &str)]) -> Vec<Predicate> {
+        inputs
+            .iter()
+            .map(|(field, comparison_op, value)| {
+                Predicate::compile(Input::new(field, comparison_op, value)).unwrap()
+            })
+            .collect()
+    }
+
+    fn key(year: &str) -> QueryKey {
+        QueryKey::new(
+            &filters(&[("disc_year", "=", year)]),
+            &None,
+            0,
+            &PageKind::Next,
+        )
+    }
+
+    fn page(last_id: i64) -> Option<Data> {
+        Some(Data {
+            planet_data: Vec::new(),
+            last_id,
+            first_id: 0,
+            plan: None,
+        })
+    }
+
+    #[test]
+    fn equivalent_queries_share_a_key() {
+        let a = filters(&[("disc_year", ">", "2010"), ("pl_rade", ">", " 2 ")]);
+        let b = filters(&[("pl_rade", ">", "2"), ("disc_year", ">", "2010 ")]);
+        assert_eq!(
+            QueryKey::new(&a, &None, 0, &PageKind::Next),
+            QueryKey::new(&b, &None, 0, &PageKind::Next)
+        );
+        assert_ne!(
+            QueryKey::new(&a, &None, 0, &PageKind::Next),
+            QueryKey::new(&a, &None, 0, &PageKind::Prev)
+        );
+    }
+
+    #[test]
+    fn text_values_are_kept_as_bound() {
+        // Text is compared as written, spaces and all.
+        let spaced = filters(&[("hostname", "=", "X ")]);
+        let plain = filters(&[("hostname", "=", "X")]);
+        assert_ne!(
+            QueryKey::new(&spaced, &None, 0, &PageKind::Next),
+            QueryKey::new(&plain, &None, 0, &PageKind::Next)
+        );
+    }
+
+    #[test]
+    fn the_least_recently_used_entry_is_evicted() {
+        let mut cache = QueryCache::new(2, Duration::from_secs(60));
+        cache.insert(key("2001"), page(1), 0);
+        cache.insert(key("2002"), page(2), 0);
+        // Using 2001 leaves 2002 the least recently used.
+        assert_eq!(cache.get(&key("2001")), Some(page(1)));
+        cache.insert(key("2003"), page(3), 0);
+
+        assert_eq!(cache.get(&key("2002")), None);
+        assert_eq!(cache.get(&key("2001")), Some(page(1)));
+        assert_eq!(cache.get(&key("2003")), Some(page(3)));
+        assert_eq!(cache.entries.len(), 2);
+    }
+
+    #[test]
+    fn replacing_an_entry_evicts_nothing() {
+        let mut cache = QueryCache::new(2, Duration::from_secs(60));
+        cache.insert(key("2001"), page(1), 0);
+        cache.insert(key("2002"), page(2), 0);
+        cache.insert(key("2002"), page(20), 0);
+        assert_eq!(cache.get(&key("2001")), Some(page(1)));
+        assert_eq!(cache.get(&key("2002")), Some(page(20)));
+    }
+
+    #[test]
+    fn entries_expire() {
+        let mut cache = QueryCache::new(2, Duration::ZERO);
+        cache.insert(key("2001"), page(1), 0);
+        assert_eq!(cache.get(&key("2001")), None);
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn a_size_of_zero_caches_nothing() {
+        let mut cache = QueryCache::new(0, Duration::from_secs(60));
+        cache.insert(key("2001"), page(1), 0);
+        assert_eq!(cache.get(&key("2001")), None);
+    }
+
+    #[test]
+    fn results_read_before_clearing_are_not_stored() {
+        let mut cache = QueryCache::new(2, Duration::from_secs(60));
+        let generation = cache.generation();
+        cache.clear();
+        cache.insert(key("2001"), page(1), generation);
+        assert_eq!(cache.get(&key("2001")), None);
+
+        cache.insert(key("2001"), page(1), cache.generation());
+        assert_eq!(cache.get(&key("2001")), Some(page(1)));
+    }
+}
@@ -0,0 +1,185 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    api::{get_auth_status, Login, Logout},
+    model::user::{AuthStatus, Role, User},
+};
+use leptos::*;
+use leptos_router::{use_navigate, A};
+
+/// Whether sign-in is enabled and who is signed in, refetched after signing
+/// in or out.
+#[derive(Clone, Copy)]
+pub struct Account {
+    pub status: Resource<usize, Result<AuthStatus, ServerFnError>>,
+    pub set_version: WriteSignal<usize>,
+}
+
+impl Account {
+    pub fn user(&self) -> Option<User> {
+        self.status
+            .get()
+            .and_then(Result::ok)
+            .and_then(|status| status.user)
+    }
+
+    /// What the visitor may do, once the server has said.
+    pub fn role(&self) -> Option<Role> {
+        self.status
+            .get()
+            .and_then(Result::ok)
+            .map(|status| status.role)
+    }
+
+    /// Whether the visitor has at least `role`, and so should be offered what
+    /// needs it.
+    pub fn allows(&self, role: Role) -> bool {
+        self.role().is_some_and(|own| own >= role)
+    }
+
+    pub fn refresh(&self) {
+        self.set_version.update(|version| *version += 1);
+    }
+}
+
+pub fn provide_account() {
+    let (version, set_version) = create_signal(0);
+    let status = create_resource(version, |_| get_auth_status());
+    provide_context(Account {
+        status,
+        set_version,
+    });
+}
+
+/// The navbar's sign-in link, or the signed-in user with a way to sign out.
+/// Hidden when the server has sign-in turned off.
+#[component]
+pub fn AccountMenu() -> impl IntoView {
+    let account: Account = use_context().unwrap();
+    let logout = create_server_action::<Logout>();
+    create_effect(move |_| {
+        if logout.version().get() > 0 {
+            account.refresh();
+        }
+    });
+
+    view! {
+        <Suspense fallback=|| ()>
+            {move || {
+                account
+                    .status
+                    .get()
+                    .and_then(Result::ok)
+                    .filter(|status| status.enabled)
+                    .map(|status| match status.user {
+                        Some(user) => view! {
+                            <span class="mx-2">{user.username}</span>
+                            <button class="btn btn-ghost normal-case" on:click=move |_| logout.dispatch(Logout {})>
+                                "Log Out"
+                            </button>
+                        }
+                        .into_view(),
+                        None => view! {
+                            <A href="/login" class="btn btn-ghost normal-case">
+                                "Log In"
+                            </A>
+                        }
+                        .into_view(),
+                    })
+            }}
+        </Suspense>
+    }
+}
+
+#[component]
+pub fn LoginPage() -> impl IntoView {
+    let account: Account = use_context().unwrap();
+    let login = create_server_action::<Login>();
+    let pending = login.pending();
+    let (username, set_username) = create_signal(String::new());
+    let (password, set_password) = create_signal(String::new());
+
+    let navigate = use_navigate();
+    create_effect(move |_| {
+        if let Some(Ok(_)) = login.value().get() {
+            account.refresh();
+            navigate("/", Default::default());
+        }
+    });
+
+    let form = move |status: AuthStatus| {
+        view! {
+            <form
+                class="card-body"
+                on:submit=move |ev| {
+                    ev.prevent_default();
+                    login
+                        .dispatch(Login {
+                            username: username.get_untracked(),
+                            password: password.get_untracked(),
+                        });
+                }
+            >
+                <h2 class="card-title">"Log In"</h2>
+                <input
+                    class="input input-bordered"
+                    type="text"
+                    placeholder="Username"
+                    autocomplete="username"
+                    on:input=move |ev| set_username(event_target_value(&ev))
+                />
+                <input
+                    class="input input-bordered"
+                    type="password"
+                    placeholder="Password"
+                    autocomplete="current-password"
+                    on:input=move |ev| set_password(event_target_value(&ev))
+                />
+                {move || {
+                    login
+                        .value()
+                        .get()
+                        .and_then(Result::err)
+                        .map(|error| view! { <div class="alert alert-error">{error.to_string()}</div> })
+                }}
+                <div class="card-actions items-center">
+                    <button class="btn btn-outline btn-success" type="submit" disabled=pending>
+                        "Log In"
+                    </button>
+                    // The provider's pages are outside the app, so let the
+                    // browser follow the link.
+                    {status
+                        .oidc
+                        .then(|| {
+                            view! {
+                                <a href="/auth/oidc/login" rel="external" class="btn btn-outline">
+                                    "Log In with Single Sign-On"
+                                </a>
+                            }
+                        })}
+                </div>
+            </form>
+        }
+    };
+
+    view! {
+        <div class="m-2 flex justify-center">
+            <div class="card bg-base-200 w-96">
+                <Suspense fallback=|| view! { <span class="loading loading-spinner"></span> }>
+                    {move || {
+                        account
+                            .status
+                            .get()
+                            .map(|status| match status {
+                                Ok(status) if status.enabled => form(status).into_view(),
+                                _ => view! {
+                                    <div class="card-body">"Sign-in isn't enabled on this server."</div>
+                                }
+                                .into_view(),
+                            })
+                    }}
+                </Suspense>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,228 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    api::{cache_statistics, list_users, sql_console_enabled, FlushCache, ImportData, SetRole},
+    components::account::Account,
+    model::user::Role,
+};
+use leptos::*;
+use leptos_router::A;
+
+#[component]
+pub fn Admin() -> impl IntoView {
+    let import_action = create_server_action::<ImportData>();
+    let pending = import_action.pending();
+
+    let status = move || match import_action.value().get() {
+        Some(Ok(rows)) => format!("Imported {rows} rows."),
+        Some(Err(error)) => format!("Import failed: {error}"),
+        None => "".to_string(),
+    };
+
+    // Refetched after an import or flush, which empty the cache.
+    let flush_action = create_server_action::<FlushCache>();
+    let (refresh, set_refresh) = create_signal(0);
+    let metrics = create_resource(
+        move || {
+            (
+                refresh(),
+                import_action.version().get(),
+                flush_action.version().get(),
+            )
+        },
+        |_| cache_statistics(),
+    );
+    let console_enabled = create_resource(|| (), |_| sql_console_enabled());
+
+    view! {
+        <div class="admin-area m-2 flex flex-col gap-2">
+            <div class="card bg-base-200">
+                <div class="card-body">
+                    <h2 class="card-title">"Data Import"</h2>
+                    <p>
+                        "Replaces the dataset with the archive CSV export configured by IMPORT_PATH."
+                    </p>
+                    <div class="card-actions items-center">
+                        <button
+                            class="btn btn-outline btn-warning"
+                            disabled=pending
+                            on:click=move |_| import_action.dispatch(ImportData {})
+                        >
+                            "Import"
+                        </button>
+                        <span>{status}</span>
+                    </div>
+                </div>
+            </div>
+            <div class="card bg-base-200">
+                <div class="card-body">
+                    <h2 class="card-title">"Query Cache"</h2>
+                    <Suspense fallback=|| view! { <span class="loading loading-spinner"></span> }>
+                        {move || {
+                            metrics
+                                .get()
+                                .map(|result| match result {
+                                    Ok(metrics) => view! {
+                                        <div class="stats">
+                                            <div class="stat">
+                                                <div class="stat-title">"Hits"</div>
+                                                <div class="stat-value">{metrics.hits}</div>
+                                            </div>
+                                            <div class="stat">
+                                                <div class="stat-title">"Misses"</div>
+                                                <div class="stat-value">{metrics.misses}</div>
+                                            </div>
+                                            <div class="stat">
+                                                <div class="stat-title">"Hit Rate"</div>
+                                                <div class="stat-value">
+                                                    {metrics
+                                                        .hit_rate()
+                                                        .map(|rate| format!("{:.0}%", rate * 100.0))
+                                                        .unwrap_or_else(|| "-".to_string())}
+                                                </div>
+                                            </div>
+                                            <div class="stat">
+                                                <div class="stat-title">"Cached"</div>
+                                                <div class="stat-value">{metrics.entries}</div>
+                                                <div class="stat-desc">
+                                                    {format!("{} evicted", metrics.evictions)}
+                                                </div>
+                                            </div>
+                                        </div>
+                                    }
+                                    .into_view(),
+                                    Err(error) => view! {
+                                        <div class="alert alert-error">{error.to_string()}</div>
+                                    }
+                                    .into_view(),
+                                })
+                        }}
+                    </Suspense>
+                    <div class="card-actions">
+                        <button class="btn btn-sm" on:click=move |_| set_refresh.update(|n| *n += 1)>
+                            "Refresh"
+                        </button>
+                        <button
+                            class="btn btn-sm btn-outline btn-warning"
+                            disabled=flush_action.pending()
+                            on:click=move |_| flush_action.dispatch(FlushCache {})
+                        >
+                            "Flush"
+                        </button>
+                    </div>
+                </div>
+            </div>
+            // Only offered when the server has it turned on.
+            <Suspense fallback=|| ()>
+                {move || {
+                    console_enabled
+                        .get()
+                        .and_then(Result::ok)
+                        .filter(|enabled| *enabled)
+                        .map(|_| {
+                            view! {
+                                <div class="card bg-base-200">
+                                    <div class="card-body">
+                                        <h2 class="card-title">"SQL Console"</h2>
+                                        <p>"Run read-only SELECT statements against the dataset."</p>
+                                        <div class="card-actions">
+                                            <A href="/admin/sql" class="btn btn-outline">
+                                                "Open"
+                                            </A>
+                                        </div>
+                                    </div>
+                                </div>
+                            }
+                        })
+                }}
+            </Suspense>
+            <UserRoles/>
+        </div>
+    }
+}
+
+/// Lists the users with a choice of role for each, when sign-in is on.
+#[component]
+fn UserRoles() -> impl IntoView {
+    let account: Account = use_context().unwrap();
+    let set_role = create_server_action::<SetRole>();
+    let users = create_resource(
+        move || (account.user().map(|user| user.id), set_role.version().get()),
+        |(user, _)| async move {
+            match user {
+                Some(_) => list_users().await,
+                None => Ok(Vec::new()),
+            }
+        },
+    );
+
+    let rows = move || {
+        let own = account.user().map(|user| user.id);
+        users
+            .get()
+            .and_then(Result::ok)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|user| {
+                let username = user.username.clone();
+                view! {
+                    <tr>
+                        <td>{user.username}</td>
+                        <td>
+                            <select
+                                class="select select-bordered select-sm"
+                                disabled=own == Some(user.id)
+                                on:change=move |ev| {
+                                    if let Some(role) = Role::parse(&event_target_value(&ev)) {
+                                        set_role
+                                            .dispatch(SetRole {
+                                                username: username.clone(),
+                                                role,
+                                            });
+                                    }
+                                }
+                            >
+                                {Role::ALL
+                                    .into_iter()
+                                    .map(|role| {
+                                        view! {
+                                            <option value=role.as_str() selected=role == user.role>
+                                                {role.as_str()}
+                                            </option>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </select>
+                        </td>
+                    </tr>
+                }
+            })
+            .collect_view()
+    };
+
+    view! {
+        <Show when=move || account.user().is_some() fallback=|| ()>
+            <div class="card bg-base-200">
+                <div class="card-body">
+                    <h2 class="card-title">"Users"</h2>
+                    <table class="table">
+                        <thead>
+                            <tr>
+                                <th>"Username"</th>
+                                <th>"Role"</th>
+                            </tr>
+                        </thead>
+                        <tbody>{rows}</tbody>
+                    </table>
+                    {move || {
+                        set_role
+                            .value()
+                            .get()
+                            .and_then(Result::err)
+                            .map(|error| view! { <div class="alert alert-error">{error.to_string()}</div> })
+                    }}
+                </div>
+            </div>
+        </Show>
+    }
+}
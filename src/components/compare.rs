@@ -0,0 +1,267 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    components::{
+        input::DisplayUnits,
+        output::{column_label, display_value, has_value, measurement, SupSub},
+    },
+    model::{
+        columns::{Column, Group, COLUMNS},
+        data::{PlanetData, MAX_COMPARE},
+    },
+};
+use leptos::*;
+use leptos_router::A;
+use serde::{Deserialize, Serialize};
+
+// In the browser, answer from the snapshot when there's no server to ask.
+#[cfg(not(any(feature = "csr", feature = "hydrate")))]
+use crate::api::get_planets;
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+use crate::snapshot::get_planets;
+
+const STORAGE_KEY: &str = "compare_tray";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrayPlanet {
+    pub id: i64,
+    pub pl_name: String,
+}
+
+/// The planets picked for comparison, kept in local storage so the tray
+/// survives reloads.
+#[derive(Clone, Copy)]
+pub struct CompareTray {
+    pub planets: ReadSignal<Vec<TrayPlanet>>,
+    pub set_planets: WriteSignal<Vec<TrayPlanet>>,
+}
+
+impl CompareTray {
+    pub fn contains(&self, id: i64) -> bool {
+        self.planets
+            .with(|planets| planets.iter().any(|planet| planet.id == id))
+    }
+
+    /// Adds the planet, unless the tray is full, or removes it if present.
+    pub fn toggle(&self, planet: TrayPlanet) {
+        self.set_planets.update(|planets| {
+            match planets.iter().position(|tray| tray.id == planet.id) {
+                Some(index) => {
+                    planets.remove(index);
+                }
+                None if planets.len() < MAX_COMPARE => planets.push(planet),
+                None => {}
+            }
+        });
+    }
+}
+
+pub fn provide_compare_tray() {
+    let (planets, set_planets) = create_signal(Vec::<TrayPlanet>::new());
+    provide_context(CompareTray {
+        planets,
+        set_planets,
+    });
+
+    // Effects only run in the browser, so restore the tray once hydrated and
+    // save it on every change after.
+    create_effect(move |_| {
+        if let Some(saved) = load_tray() {
+            set_planets(saved);
+        }
+    });
+    create_effect(move |_| planets.with(|planets| save_tray(planets)));
+}
+
+fn storage() -> Option<web_sys::Storage> {
+    window().local_storage().ok().flatten()
+}
+
+fn load_tray() -> Option<Vec<TrayPlanet>> {
+    let saved = storage()?.get_item(STORAGE_KEY).ok().flatten()?;
+    serde_json::from_str(&saved).ok()
+}
+
+fn save_tray(planets: &[TrayPlanet]) {
+    if let (Some(storage), Ok(json)) = (storage(), serde_json::to_string(planets)) {
+        let _ = storage.set_item(STORAGE_KEY, &json);
+    }
+}
+
+#[component]
+pub fn CompareCheckbox(id: i64, pl_name: String) -> impl IntoView {
+    let tray: CompareTray = use_context().unwrap();
+    let pl_name = store_value(pl_name);
+
+    view! {
+        <input
+            type="checkbox"
+            class="checkbox checkbox-sm"
+            prop:checked=move || tray.contains(id)
+            disabled=move || !tray.contains(id) && tray.planets.with(Vec::len) >= MAX_COMPARE
+            on:change=move |_| {
+                tray.toggle(TrayPlanet {
+                    id,
+                    pl_name: pl_name.get_value(),
+                })
+            }
+        />
+    }
+}
+
+/// Lists the planets picked for comparison under the navbar.
+#[component]
+pub fn CompareTrayBar() -> impl IntoView {
+    let tray: CompareTray = use_context().unwrap();
+
+    view! {
+        <Show when=move || tray.planets.with(|planets| !planets.is_empty()) fallback=|| ()>
+            <div class="flex flex-wrap gap-2 items-center bg-base-200 p-2">
+                <span>
+                    {move || format!("Compare ({}/{MAX_COMPARE}):", tray.planets.with(Vec::len))}
+                </span>
+                <For each=tray.planets key=|planet| planet.id let:planet>
+                    <span class="badge badge-lg gap-1">
+                        {planet.pl_name.clone()}
+                        <button
+                            class="btn btn-ghost btn-xs"
+                            on:click=move |_| tray.toggle(planet.clone())
+                        >
+                            "✕"
+                        </button>
+                    </span>
+                </For>
+                <A href="/compare" class="btn btn-primary btn-sm">
+                    "Compare"
+                </A>
+                <button class="btn btn-ghost btn-sm" on:click=move |_| tray.set_planets.set(Vec::new())>
+                    "Clear"
+                </button>
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+pub fn Compare() -> impl IntoView {
+    let tray: CompareTray = use_context().unwrap();
+
+    let planets = create_resource(
+        move || {
+            tray.planets
+                .with(|planets| planets.iter().map(|planet| planet.id).collect::<Vec<_>>())
+        },
+        get_planets,
+    );
+
+    view! {
+        <div class="compare-area overflow-x-auto m-2">
+            <Suspense fallback=|| view! { <span class="loading loading-spinner m-2"></span> }>
+                {move || {
+                    planets
+                        .get()
+                        .map(|result| match result {
+                            Ok(planets) if planets.len() < 2 => view! {
+                                <p class="m-2">
+                                    "Tick the Compare box of at least two planets to compare them."
+                                </p>
+                            }
+                            .into_view(),
+                            Ok(planets) => view! { <CompareTable planets/> }.into_view(),
+                            Err(error) => view! {
+                                <div class="alert alert-error m-2">{error.to_string()}</div>
+                            }
+                            .into_view(),
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+/// Shows the planets as columns and every field with a value as a row,
+/// highlighting the rows where the planets differ.
+#[component]
+fn CompareTable(planets: Vec<PlanetData>) -> impl IntoView {
+    let DisplayUnits {
+        system,
+        set_system: _,
+    } = use_context().unwrap();
+
+    let planets = store_value(planets);
+    let colspan = planets.with_value(Vec::len) + 1;
+
+    let row = move |column: &'static Column| {
+        let differs = planets.with_value(|planets| {
+            let mut values =
+                planets
+                    .iter()
+                    .map(|planet| match column.errors || column.quantity.is_some() {
+                        true => format!("{:?}", measurement(column, planet).value),
+                        false => display_value(column, planet),
+                    });
+            let first = values.next();
+            values.any(|value| Some(value) != first)
+        });
+        let cells = planets
+            .get_value()
+            .into_iter()
+            .map(|planet| {
+                if column.errors || column.quantity.is_some() {
+                    let measurement = measurement(column, &planet);
+                    match column.quantity {
+                        Some(quantity) => view! { <td><SupSub measurement quantity/></td> },
+                        None => view! { <td><SupSub measurement/></td> },
+                    }
+                } else {
+                    view! { <td>{display_value(column, &planet)}</td> }
+                }
+            })
+            .collect_view();
+
+        view! {
+            <tr class:bg-warning=differs class:text-warning-content=differs>
+                <th>{move || column_label(column, system())}</th>
+                {cells}
+            </tr>
+        }
+    };
+
+    view! {
+        <table class="compare-table table table-sm">
+            <thead>
+                <tr>
+                    <th></th>
+                    {planets
+                        .get_value()
+                        .into_iter()
+                        .map(|planet| view! { <th>{planet.pl_name}</th> })
+                        .collect_view()}
+                </tr>
+            </thead>
+            <tbody>
+                {Group::ALL
+                    .into_iter()
+                    .map(|group| {
+                        let rows = COLUMNS
+                            .iter()
+                            .filter(|column| {
+                                column.group == group && !column.hidden
+                                    && planets.with_value(|planets| {
+                                        planets.iter().any(|planet| has_value(column, planet))
+                                    })
+                            })
+                            .map(row)
+                            .collect_view();
+                        view! {
+                            <tr>
+                                <td colspan=colspan class="bg-base-200 font-bold">{group.label()}</td>
+                            </tr>
+                            {rows}
+                        }
+                    })
+                    .collect_view()}
+            </tbody>
+        </table>
+    }
+}
@@ -0,0 +1,99 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    api::{sql_console_enabled, RunSql},
+    components::output::{ExportCsv, QueryError, ResultTable},
+    model::console::{ConsoleResult, CONSOLE_ROW_LIMIT},
+};
+use leptos::*;
+
+/// Runs read-only `SELECT` statements against the database, for analysis
+/// the query builder can't express.
+#[component]
+pub fn SqlConsole() -> impl IntoView {
+    let enabled = create_resource(|| (), |_| sql_console_enabled());
+    let run_action = create_server_action::<RunSql>();
+    let pending = run_action.pending();
+    let (statement, set_statement) = create_signal(String::new());
+
+    let disabled = || {
+        view! {
+            <div class="alert alert-info m-2">
+                "The SQL console is disabled. Set features.sql_console = true (or SQL_CONSOLE=true) on the server to enable it."
+            </div>
+        }
+    };
+
+    view! {
+        <div class="admin-area m-2 flex flex-col gap-2">
+            <Suspense fallback=|| view! { <span class="loading loading-spinner"></span> }>
+                {move || {
+                    enabled
+                        .get()
+                        .map(|enabled| match enabled {
+                            Ok(true) => view! {
+                                <div class="card bg-base-200">
+                                    <div class="card-body">
+                                        <h2 class="card-title">"SQL Console"</h2>
+                                        <p>
+                                            {format!(
+                                                "Runs a single SELECT in a read-only transaction, returning at most {CONSOLE_ROW_LIMIT} rows."
+                                            )}
+                                        </p>
+                                        <textarea
+                                            class="textarea textarea-bordered font-mono"
+                                            rows=6
+                                            placeholder="SELECT pl_name, pl_rade FROM exoplanet_data WHERE default_flag = 1"
+                                            prop:value=statement
+                                            on:input=move |ev| set_statement(event_target_value(&ev))
+                                        ></textarea>
+                                        <div class="card-actions">
+                                            <button
+                                                class="btn btn-outline btn-success"
+                                                disabled=pending
+                                                on:click=move |_| {
+                                                    run_action
+                                                        .dispatch(RunSql {
+                                                            statement: statement.get_untracked(),
+                                                        })
+                                                }
+                                            >
+                                                "Run"
+                                            </button>
+                                        </div>
+                                    </div>
+                                </div>
+                            }
+                            .into_view(),
+                            Ok(false) => disabled().into_view(),
+                            Err(error) => view! { <QueryError error/> }.into_view(),
+                        })
+                }}
+            </Suspense>
+            {move || match run_action.value().get() {
+                Some(Ok(result)) => view! { <ConsoleTable result/> }.into_view(),
+                Some(Err(error)) => view! { <QueryError error/> }.into_view(),
+                None => ().into_view(),
+            }}
+        </div>
+    }
+}
+
+#[component]
+fn ConsoleTable(result: ConsoleResult) -> impl IntoView {
+    let csv = result.to_csv();
+    let summary = match result.truncated {
+        true => format!("First {} rows", result.rows.len()),
+        false => format!("{} rows", result.rows.len()),
+    };
+
+    view! {
+        <div class="flex justify-between items-center m-2">
+            <h2 class="text-lg">{summary}</h2>
+            <ExportCsv csv filename="query.csv"/>
+        </div>
+        <div class="overflow-x-auto">
+            <ResultTable columns=result.columns rows=result.rows/>
+        </div>
+    }
+}
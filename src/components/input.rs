@@ -1,15 +1,30 @@
 #![allow(non_snake_case)]
 
 use crate::{
-    api::QueryDb,
-    components::output::OutputArea,
+    api::{
+        get_column_layout, list_saved_queries, DeleteSavedQuery, FindSimilar, QueryDb, QueryPlot,
+        QueryStats, QuerySystems, SaveQuery,
+    },
+    components::{account::Account, output::OutputArea},
     model::{
-        data::Data,
-        input::{Input, PageKind},
+        columns::{find_column, Column, ColumnKind, COLUMNS},
+        data::{Data, PlanetData},
+        input::{Input, PageKind, Query, Sort, CONSISTENT_OP, IN_OP, RELATIVE_ERROR_OP},
+        plot::PlotData,
+        similarity::{SimilarityParameter, MAX_SIMILAR},
+        stats::Statistics,
+        system::PlanetarySystem,
+        text_query::{self, ParseError, TextQuery},
+        units::{Unit, UnitSystem},
     },
 };
 use leptos::*;
 
+// In the browser, actions run against the snapshot when there's no server
+// to ask.
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+use crate::snapshot::create_server_action;
+
 type InputHolder = Vec<(usize, (ReadSignal<Input>, WriteSignal<Input>))>;
 
 #[derive(Clone, Copy)]
@@ -19,9 +34,44 @@ pub struct Item {
     pub fe_id: usize,
 }
 
+impl Item {
+    pub fn from_column(column: &Column, fe_id: usize) -> Item {
+        Item {
+            id: column.id,
+            value: column.label,
+            fe_id,
+        }
+    }
+}
+
+/// Items for the columns passing `keep`, keyed by their position in
+/// `COLUMNS` so a column keeps its key whichever others are shown.
+pub fn column_items(keep: impl Fn(&Column) -> bool) -> Vec<Item> {
+    COLUMNS
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| keep(column))
+        .map(|(fe_id, column)| Item::from_column(column, fe_id))
+        .collect()
+}
+
+/// The columns of the output table.
 #[derive(Clone, Copy)]
 pub struct Fields {
     pub fields: ReadSignal<Vec<Item>>,
+    pub set_fields: WriteSignal<Vec<Item>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct FilterFields {
+    pub fields: ReadSignal<Vec<Item>>,
+}
+
+/// The unit system measurements are shown in, shared by every page.
+#[derive(Clone, Copy)]
+pub struct DisplayUnits {
+    pub system: ReadSignal<UnitSystem>,
+    pub set_system: WriteSignal<UnitSystem>,
 }
 
 #[derive(Clone, Copy)]
@@ -46,60 +96,116 @@ pub struct QueryOutput {
     pub value: ReadSignal<Option<Result<Option<Data>, ServerFnError>>>,
 }
 
+/// The filters of the last query, shared with pages outside `Home`.
+#[derive(Clone, Copy)]
+pub struct ActiveFilters {
+    pub filters: ReadSignal<Vec<Input>>,
+    pub set_filters: WriteSignal<Vec<Input>>,
+}
+
+/// The parameters and size of "find similar" searches, and whether their
+/// results are shown.
+#[derive(Clone, Copy)]
+pub struct SimilarSearch {
+    pub parameters: ReadSignal<Vec<SimilarityParameter>>,
+    pub set_parameters: WriteSignal<Vec<SimilarityParameter>>,
+    pub count: ReadSignal<usize>,
+    pub set_count: WriteSignal<usize>,
+    pub action: Action<FindSimilar, Result<Option<Data>, ServerFnError>>,
+    pub showing: ReadSignal<bool>,
+    pub set_showing: WriteSignal<bool>,
+}
+
+impl SimilarSearch {
+    pub fn search(&self, id: i64) {
+        self.set_showing.set(true);
+        self.action.dispatch(FindSimilar {
+            id,
+            parameters: self.parameters.get_untracked(),
+            count: self.count.get_untracked(),
+        });
+    }
+
+    /// The planets found by the last search.
+    pub fn planets(&self) -> Vec<PlanetData> {
+        self.action.value().with(|value| match value {
+            Some(Ok(Some(data))) => data.planet_data.clone(),
+            _ => Vec::new(),
+        })
+    }
+}
+
+/// Provides a search with the default parameters and size to the current
+/// page.
+pub fn provide_similar_search() -> SimilarSearch {
+    let (parameters, set_parameters) = create_signal(SimilarityParameter::ALL.to_vec());
+    let (count, set_count) = create_signal(10);
+    let (showing, set_showing) = create_signal(false);
+    let similar = SimilarSearch {
+        parameters,
+        set_parameters,
+        count,
+        set_count,
+        action: create_server_action::<FindSimilar>(),
+        showing,
+        set_showing,
+    };
+    provide_context(similar);
+    similar
+}
+
+#[derive(Clone, Copy)]
+pub struct StatsOutput {
+    pub value: ReadSignal<Option<Result<Statistics, ServerFnError>>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct PlotOutput {
+    pub value: ReadSignal<Option<Result<PlotData, ServerFnError>>>,
+    /// The filters of the last plot, for linking to its rendered image.
+    pub query: ReadSignal<Vec<Input>>,
+    pub set_query: WriteSignal<Vec<Input>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct SystemsOutput {
+    pub value: ReadSignal<Option<Result<Vec<PlanetarySystem>, ServerFnError>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    Table,
+    Statistics,
+    Plot,
+    Systems,
+}
+
+#[derive(Clone, Copy)]
+pub struct Mode {
+    pub mode: ReadSignal<OutputMode>,
+    pub set_mode: WriteSignal<OutputMode>,
+}
+
 #[component]
 pub fn Home() -> impl IntoView {
-    let initial_fields = vec![
-        Item {
-            id: "pl_name",
-            value: "Planet Name",
-            fe_id: 0,
-        },
-        Item {
-            id: "hostname",
-            value: "Host Name",
-            fe_id: 1,
-        },
-        Item {
-            id: "sy_snum",
-            value: "Number of Stars",
-            fe_id: 2,
-        },
-        Item {
-            id: "sy_pnum",
-            value: "Number of Planets",
-            fe_id: 3,
-        },
-        Item {
-            id: "cb_flag",
-            value: "Circumbinary Flag",
-            fe_id: 5,
-        },
-        Item {
-            id: "discovery_method",
-            value: "Discovery Method",
-            fe_id: 6,
-        },
-        Item {
-            id: "disc_year",
-            value: "Discovery Year",
-            fe_id: 7,
-        },
-        Item {
-            id: "disc_refname",
-            value: "Discovery Reference",
-            fe_id: 8,
-        },
-        Item {
-            id: "disc_pubdate",
-            value: "Discovery Publication Date",
-            fe_id: 9,
-        },
-    ];
+    let initial_fields = column_items(|column| column.summary);
+    let initial_filter_fields = COLUMNS
+        .iter()
+        .filter(|column| !column.hidden)
+        .enumerate()
+        .map(|(fe_id, column)| Item::from_column(column, fe_id))
+        .collect::<Vec<_>>();
 
     let (last_id, set_last_id) = create_signal(0);
     let (first_id, set_first_id) = create_signal(0);
-    let (fields, _) = create_signal(initial_fields);
+    let (fields, set_fields) = create_signal(initial_fields);
+    let (filter_fields, _) = create_signal(initial_filter_fields);
+    let (mode, set_mode) = create_signal(OutputMode::Table);
     let query_action = create_server_action::<QueryDb>();
+    let stats_action = create_server_action::<QueryStats>();
+    let plot_action = create_server_action::<QueryPlot>();
+    let systems_action = create_server_action::<QuerySystems>();
+    let (plot_query, set_plot_query) = create_signal(Vec::<Input>::new());
     provide_context(LastId {
         last_id,
         set_last_id,
@@ -108,13 +214,56 @@ pub fn Home() -> impl IntoView {
         first_id,
         set_first_id,
     });
-    provide_context(Fields { fields });
+    provide_context(Fields { fields, set_fields });
+
+    // Signed-in users see the columns they last chose.
+    let account: Account = use_context().unwrap();
+    let layout = create_resource(
+        move || account.user().map(|user| user.id),
+        |user| async move {
+            match user {
+                Some(_) => get_column_layout().await,
+                None => Ok(None),
+            }
+        },
+    );
+    create_effect(move |_| {
+        if let Some(Ok(layout)) = layout.get() {
+            set_fields(match layout {
+                Some(ids) => column_items(|column| ids.iter().any(|id| id == column.id)),
+                None => column_items(|column| column.summary),
+            });
+        }
+    });
+    provide_context(FilterFields {
+        fields: filter_fields,
+    });
+    // Similar planets take the query results' place in the table until the
+    // next query comes back.
+    let similar = provide_similar_search();
+    create_effect(move |_| {
+        if query_action.version().get() > 0 {
+            similar.set_showing.set(false);
+        }
+    });
     provide_context(QueryOutput {
         value: query_action.value().read_only(),
     });
+    provide_context(StatsOutput {
+        value: stats_action.value().read_only(),
+    });
+    provide_context(PlotOutput {
+        value: plot_action.value().read_only(),
+        query: plot_query,
+        set_query: set_plot_query,
+    });
+    provide_context(SystemsOutput {
+        value: systems_action.value().read_only(),
+    });
+    provide_context(Mode { mode, set_mode });
     view! {
         <div>
-            <InputArea query_action/>
+            <InputArea query_action stats_action plot_action systems_action/>
             <OutputArea/>
         </div>
     }
@@ -123,13 +272,18 @@ pub fn Home() -> impl IntoView {
 #[component]
 pub fn InputArea(
     query_action: Action<QueryDb, Result<Option<Data>, ServerFnError>>,
+    stats_action: Action<QueryStats, Result<Statistics, ServerFnError>>,
+    plot_action: Action<QueryPlot, Result<PlotData, ServerFnError>>,
+    systems_action: Action<QuerySystems, Result<Vec<PlanetarySystem>, ServerFnError>>,
 ) -> impl IntoView {
     let initial_size = 1;
-    let mut next_counter_id = initial_size;
+    // Shared by adding inputs and applying text queries, so row ids stay
+    // unique.
+    let next_counter_id = store_value(initial_size);
     let mut next_history_id = 0;
 
     let initial_inputs = (0..initial_size)
-        .map(|id| (id, create_signal(Input::new())))
+        .map(|id| (id, create_signal(Input::default())))
         .collect::<Vec<_>>();
 
     let query_history = create_rw_signal(Vec::<(usize, QueryDb)>::new());
@@ -137,6 +291,41 @@ pub fn InputArea(
     let (open, set_open) = create_signal(false);
     let toggle = move |_| set_open(!open());
 
+    // Signed-in users' saved queries and history are kept on the server.
+    let account: Account = use_context().unwrap();
+    let save_action = leptos::create_server_action::<SaveQuery>();
+    let delete_action = leptos::create_server_action::<DeleteSavedQuery>();
+    let (query_name, set_query_name) = create_signal(String::new());
+    let saved = create_resource(
+        move || {
+            (
+                account.user().map(|user| user.id),
+                save_action.version().get(),
+                delete_action.version().get(),
+            )
+        },
+        |(user, _, _)| async move {
+            match user {
+                Some(_) => list_saved_queries().await,
+                None => Ok(Vec::new()),
+            }
+        },
+    );
+    let server_history = create_resource(
+        move || {
+            (
+                account.user().map(|user| user.id),
+                query_action.version().get(),
+            )
+        },
+        |(user, _)| async move {
+            match user {
+                Some(_) => crate::api::query_history().await,
+                None => Ok(Vec::new()),
+            }
+        },
+    );
+
     let (input_objects, set_input_objects) = create_signal(initial_inputs);
 
     provide_context(InputUpdater { set_input_objects });
@@ -151,12 +340,139 @@ pub fn InputArea(
         set_first_id: _,
     } = use_context().unwrap();
 
+    let Mode { mode: _, set_mode } = use_context().unwrap();
+    let PlotOutput {
+        value: _,
+        query: _,
+        set_query: set_plot_query,
+    } = use_context().unwrap();
+    let ActiveFilters {
+        filters: _,
+        set_filters,
+    } = use_context().unwrap();
+    let similar: SimilarSearch = use_context().unwrap();
+
     let add_input = move |_| {
-        let sig = create_signal(Input::new());
+        let sig = create_signal(Input::default());
+
+        set_input_objects.update(move |inputs| inputs.push((next_counter_id.get_value(), sig)));
+
+        next_counter_id.update_value(|id| *id += 1);
+    };
+
+    let FilterFields { fields } = use_context().unwrap();
+    let (sort_field, set_sort_field) = create_signal(Item {
+        id: "default",
+        value: "Sort By",
+        fe_id: 0,
+    });
+    let (sort_descending, set_sort_descending) = create_signal(false);
+    // Developer mode: return the database's plan and timing with each page.
+    let (explain, set_explain) = create_signal(false);
+    let sort = move || {
+        let field = sort_field.get().id;
+        (field != "default").then(|| Sort {
+            field: field.to_string(),
+            descending: sort_descending.get(),
+        })
+    };
 
-        set_input_objects.update(move |inputs| inputs.push((next_counter_id, sig)));
+    let numeric_fields = COLUMNS
+        .iter()
+        .filter(|column| {
+            !column.hidden && matches!(column.kind, ColumnKind::Integer | ColumnKind::Real)
+        })
+        .enumerate()
+        .map(|(fe_id, column)| Item::from_column(column, fe_id))
+        .collect::<Vec<_>>();
+    let (numeric_fields, _) = create_signal(numeric_fields);
+    let (group_by, set_group_by) = create_signal(Item {
+        id: "default",
+        value: "Group By",
+        fe_id: 0,
+    });
+    let (stats_column, set_stats_column) = create_signal(Item {
+        id: "default",
+        value: "Statistics Of",
+        fe_id: 0,
+    });
 
-        next_counter_id += 1;
+    let (plot_x, set_plot_x) = create_signal(Item {
+        id: "default",
+        value: "X Axis",
+        fe_id: 0,
+    });
+    let (plot_y, set_plot_y) = create_signal(Item {
+        id: "default",
+        value: "Y Axis",
+        fe_id: 0,
+    });
+
+    // The text syntax for the query, kept in step with the builder through
+    // the "Apply" and "From Builder" buttons.
+    let (query_text, set_query_text) = create_signal(String::new());
+    let (text_error, set_text_error) = create_signal(None::<ParseError>);
+    let load_query = move |TextQuery { query, sort }: TextQuery| {
+        let inputs = query
+            .inputs
+            .into_iter()
+            .map(|input| {
+                let id = next_counter_id.get_value();
+                next_counter_id.update_value(|id| *id += 1);
+                (id, create_signal(input))
+            })
+            .collect::<Vec<_>>();
+        set_input_objects(inputs);
+
+        let field = sort.as_ref().and_then(|sort| {
+            fields
+                .with_untracked(|fields| fields.iter().find(|item| item.id == sort.field).copied())
+        });
+        set_sort_field(field.unwrap_or(Item {
+            id: "default",
+            value: "Sort By",
+            fe_id: 0,
+        }));
+        set_sort_descending(sort.is_some_and(|sort| sort.descending));
+    };
+    let apply_text = move || match text_query::parse(&query_text.get_untracked()) {
+        Ok(query) => {
+            set_text_error(None);
+            load_query(query);
+        }
+        Err(error) => set_text_error(Some(error)),
+    };
+    // Shows a saved or past query as text and loads it into the builder.
+    let restore_query = move |query: TextQuery| {
+        set_text_error(None);
+        set_query_text(query.to_string());
+        load_query(query);
+    };
+    let builder_to_text = move |_| {
+        let query = TextQuery {
+            query: Query {
+                inputs: input_objects
+                    .get_untracked()
+                    .into_iter()
+                    .map(|(_id, (rs, _ws))| rs.get_untracked())
+                    .collect(),
+            },
+            sort: sort(),
+        };
+        set_text_error(None);
+        set_query_text(query.to_string());
+    };
+
+    let save_handler = move |_| {
+        save_action.dispatch(SaveQuery {
+            name: query_name.get_untracked(),
+            query: input_objects
+                .get_untracked()
+                .into_iter()
+                .map(|(_id, (rs, _ws))| rs.get_untracked())
+                .collect(),
+            sort: sort(),
+        });
     };
 
     let clear_input = move |_| {
@@ -169,27 +485,84 @@ pub fn InputArea(
             inputs.push(rs.get());
         }
 
+        set_filters(inputs.clone());
         let query = QueryDb {
             query: inputs,
+            sort: sort(),
             anchor_id: 0i64,
             page_direction: PageKind::Next,
+            explain: explain.get(),
         };
 
         query_history.update(|history| history.push((next_history_id, query.clone())));
         next_history_id += 1;
+        set_mode(OutputMode::Table);
         query_action.dispatch(query);
     };
 
+    let stats_handler = move |_| {
+        let mut inputs = Vec::<Input>::new();
+        for (_id, (rs, _ws)) in input_objects.get() {
+            inputs.push(rs.get());
+        }
+
+        set_mode(OutputMode::Statistics);
+        set_filters(inputs.clone());
+        stats_action.dispatch(QueryStats {
+            query: inputs,
+            group_by: group_by.get().id.to_string(),
+            column: stats_column.get().id.to_string(),
+        });
+    };
+
+    let plot_handler = move |_| {
+        let mut inputs = Vec::<Input>::new();
+        for (_id, (rs, _ws)) in input_objects.get() {
+            inputs.push(rs.get());
+        }
+
+        // A histogram only needs the x axis, so plot x against itself when
+        // no y axis is chosen.
+        let x = plot_x.get().id.to_string();
+        let y = match plot_y.get().id {
+            "default" => x.clone(),
+            y => y.to_string(),
+        };
+
+        set_mode(OutputMode::Plot);
+        set_plot_query(inputs.clone());
+        set_filters(inputs.clone());
+        plot_action.dispatch(QueryPlot {
+            query: inputs,
+            x,
+            y,
+        });
+    };
+
+    let systems_handler = move |_| {
+        let mut inputs = Vec::<Input>::new();
+        for (_id, (rs, _ws)) in input_objects.get() {
+            inputs.push(rs.get());
+        }
+
+        set_mode(OutputMode::Systems);
+        set_filters(inputs.clone());
+        systems_action.dispatch(QuerySystems { query: inputs });
+    };
+
     let next_page = move |_| {
         let mut inputs = Vec::<Input>::new();
         for (_id, (rs, _ws)) in input_objects.get() {
             inputs.push(rs.get());
         }
 
+        set_mode(OutputMode::Table);
         query_action.dispatch(QueryDb {
             query: inputs,
+            sort: sort(),
             anchor_id: last_id.get(),
             page_direction: PageKind::Next,
+            explain: explain.get(),
         });
     };
 
@@ -198,14 +571,56 @@ pub fn InputArea(
         for (_id, (rs, _ws)) in input_objects.get() {
             inputs.push(rs.get());
         }
+        set_mode(OutputMode::Table);
         query_action.dispatch(QueryDb {
             query: inputs,
+            sort: sort(),
             anchor_id: first_id.get(),
             page_direction: PageKind::Prev,
+            explain: explain.get(),
         });
     };
 
-    let fallback = move || view! { <div class="divider" on:click=toggle></div> };
+    let fallback =
+        move || view! { <div class="divider cursor-pointer" on:click=toggle>"History"</div> };
+
+    let history_button = move |query: TextQuery| {
+        let label = query.to_string();
+        view! {
+            <button class="btn btn-outline btn-primary btn-sm m-1 normal-case font-mono" on:click=move |_| restore_query(query.clone())>
+                {if label.is_empty() { "(everything)".to_string() } else { label }}
+            </button>
+        }
+    };
+    let history = move || match account.user() {
+        Some(_) => server_history
+            .get()
+            .and_then(Result::ok)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| {
+                history_button(TextQuery {
+                    query: Query {
+                        inputs: entry.query,
+                    },
+                    sort: entry.sort,
+                })
+            })
+            .collect_view(),
+        None => query_history
+            .get()
+            .into_iter()
+            .rev()
+            .map(|(_id, entry)| {
+                history_button(TextQuery {
+                    query: Query {
+                        inputs: entry.query,
+                    },
+                    sort: entry.sort,
+                })
+            })
+            .collect_view(),
+    };
 
     view! {
         <div class="input-area mt-2">
@@ -214,25 +629,199 @@ pub fn InputArea(
                     "Add Input"
                 </button>
             </div>
+            <div class="input-row">
+                <input
+                    class="input input-bordered input-sm w-full font-mono"
+                    type="text"
+                    placeholder="e.g. pl_rade between 1 and 2 and discovery_method in (\"Transit\") sort pl_bmasse desc"
+                    prop:value=query_text
+                    on:input=move |ev| set_query_text(event_target_value(&ev))
+                    on:keydown=move |ev| {
+                        if ev.key() == "Enter" {
+                            apply_text();
+                        }
+                    }
+                />
+
+                <button class="btn btn-outline btn-sm btn-info m-1" on:click=move |_| apply_text()>
+                    "Apply"
+                </button>
+                <button class="btn btn-outline btn-sm m-1" on:click=builder_to_text>
+                    "From Builder"
+                </button>
+            </div>
+            {move || {
+                text_error
+                    .get()
+                    .map(|error| {
+                        view! {
+                            <div class="alert alert-error flex-col items-start m-1">
+                                <span>{error.to_string()}</span>
+                                <pre class="font-mono text-sm">
+                                    {query_text.get_untracked()} "\n"
+                                    {format!("{}^", " ".repeat(error.position))}
+                                </pre>
+                            </div>
+                        }
+                    })
+            }}
+
             <For
                 each=input_objects
                 key=|input_objects| input_objects.0
-                children=move |(id, (_, ws))| {
-                    view! { <InputRow id=id writer=ws/> }
+                children=move |(id, (rs, ws))| {
+                    view! { <InputRow id=id reader=rs writer=ws/> }
                 }
             />
 
-            <Show when=open fallback=fallback>
-                <div class="divider"></div>
-                <For
-                    each=query_history
-                    key=|history| history.0
-                    let:query
+            <div class="input-row">
+                <Dropdown items=fields selected=sort_field set_selected=set_sort_field/>
+                <button
+                    class="btn btn-outline btn-sm btn-info m-1"
+                    on:click=move |_| set_sort_descending.update(|descending| *descending = !*descending)
                 >
-                    <button class="btn btn-outline btn-primary">
-                        "Something"
+                    {move || if sort_descending() { "Descending" } else { "Ascending" }}
+                </button>
+                <label class="label cursor-pointer gap-2 m-1">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=explain
+                        on:change=move |ev| set_explain(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Explain"</span>
+                </label>
+            </div>
+
+            <div class="input-row">
+                <Dropdown items=fields selected=group_by set_selected=set_group_by/>
+                <Dropdown items=numeric_fields selected=stats_column set_selected=set_stats_column/>
+                <button class="btn btn-outline btn-sm btn-accent m-1" on:click=stats_handler>
+                    "Statistics"
+                </button>
+            </div>
+
+            <div class="input-row">
+                <Dropdown items=numeric_fields selected=plot_x set_selected=set_plot_x/>
+                <Dropdown items=numeric_fields selected=plot_y set_selected=set_plot_y/>
+                <button class="btn btn-outline btn-sm btn-accent m-1" on:click=plot_handler>
+                    "Plot"
+                </button>
+                <button class="btn btn-outline btn-sm btn-accent m-1" on:click=systems_handler>
+                    "Systems"
+                </button>
+            </div>
+
+            <div class="input-row flex flex-wrap items-center gap-2 m-1">
+                <span>"Similar by"</span>
+                {SimilarityParameter::ALL
+                    .into_iter()
+                    .map(|parameter| {
+                        view! {
+                            <label class="label cursor-pointer gap-1">
+                                <input
+                                    type="checkbox"
+                                    class="checkbox checkbox-sm"
+                                    prop:checked=move || {
+                                        similar.parameters.with(|parameters| parameters.contains(&parameter))
+                                    }
+                                    on:change=move |ev| {
+                                        let checked = event_target_checked(&ev);
+                                        similar
+                                            .set_parameters
+                                            .update(|parameters| {
+                                                parameters.retain(|p| *p != parameter);
+                                                if checked {
+                                                    parameters.push(parameter);
+                                                }
+                                            });
+                                    }
+                                />
+                                <span class="label-text">{parameter.label()}</span>
+                            </label>
+                        }
+                    })
+                    .collect_view()}
+                <input
+                    type="number"
+                    class="input input-bordered input-sm w-20"
+                    min=1
+                    max=MAX_SIMILAR
+                    prop:value=similar.count
+                    on:change=move |ev| {
+                        if let Ok(count) = event_target_value(&ev).parse::<usize>() {
+                            similar.set_count.set(count.clamp(1, MAX_SIMILAR));
+                        }
+                    }
+                />
+                <span>"planets"</span>
+            </div>
+
+            <Show when=move || account.user().is_some() fallback=|| ()>
+                <div class="input-row flex flex-wrap items-center gap-2 m-1">
+                    <input
+                        class="input input-bordered input-sm"
+                        type="text"
+                        placeholder="Query name"
+                        prop:value=query_name
+                        on:input=move |ev| set_query_name(event_target_value(&ev))
+                    />
+                    <button
+                        class="btn btn-outline btn-sm btn-info"
+                        disabled=move || query_name.with(|name| name.trim().is_empty())
+                        on:click=save_handler
+                    >
+                        "Save Query"
                     </button>
-                </For>
+                    {move || {
+                        saved
+                            .get()
+                            .and_then(Result::ok)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|saved| {
+                                let id = saved.id;
+                                let name = saved.name;
+                                let label = name.clone();
+                                let query = TextQuery {
+                                    query: Query { inputs: saved.query },
+                                    sort: saved.sort,
+                                };
+                                view! {
+                                    <div class="join">
+                                        <button
+                                            class="join-item btn btn-outline btn-sm normal-case"
+                                            on:click=move |_| {
+                                                set_query_name(name.clone());
+                                                restore_query(query.clone());
+                                            }
+                                        >
+                                            {label}
+                                        </button>
+                                        <button
+                                            class="join-item btn btn-outline btn-sm btn-error"
+                                            on:click=move |_| delete_action.dispatch(DeleteSavedQuery { id })
+                                        >
+                                            "x"
+                                        </button>
+                                    </div>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                    {move || {
+                        save_action
+                            .value()
+                            .get()
+                            .and_then(Result::err)
+                            .map(|error| view! { <span class="text-error">{error.to_string()}</span> })
+                    }}
+                </div>
+            </Show>
+
+            <Show when=open fallback=fallback>
+                <div class="divider cursor-pointer" on:click=toggle>"History"</div>
+                <div class="flex flex-wrap">{history}</div>
                 <div class="divider"></div>
             </Show>
 
@@ -243,10 +832,11 @@ pub fn InputArea(
                 <button class="join-item btn btn-outline btn-error" on:click=clear_input>
                     "Clear Input"
                 </button>
-                <button class="join-item btn btn-outline" on:click=prev_page>
+                // Similar planets are one ranked list, with no pages to turn.
+                <button class="join-item btn btn-outline" on:click=prev_page disabled=move || similar.showing.get()>
                     "Previous Page"
                 </button>
-                <button class="join-item btn btn-outline" on:click=next_page>
+                <button class="join-item btn btn-outline" on:click=next_page disabled=move || similar.showing.get()>
                     "Next"
                 </button>
             </div>
@@ -255,7 +845,7 @@ pub fn InputArea(
 }
 
 #[component]
-pub fn InputRow(id: usize, writer: WriteSignal<Input>) -> impl IntoView {
+pub fn InputRow(id: usize, reader: ReadSignal<Input>, writer: WriteSignal<Input>) -> impl IntoView {
     let initial_comp_ops = vec![
         Item {
             id: "=",
@@ -287,22 +877,46 @@ pub fn InputRow(id: usize, writer: WriteSignal<Input>) -> impl IntoView {
             value: "<=",
             fe_id: 5,
         },
+        Item {
+            id: RELATIVE_ERROR_OP,
+            value: "Rel. Uncertainty < (%)",
+            fe_id: 6,
+        },
+        Item {
+            id: CONSISTENT_OP,
+            value: "Consistent With (value, Nσ)",
+            fe_id: 7,
+        },
+        Item {
+            id: IN_OP,
+            value: "In (list)",
+            fe_id: 8,
+        },
     ];
 
-    let Fields { fields } = use_context().unwrap();
+    let FilterFields { fields } = use_context().unwrap();
+
+    // Rows made from a text query start out filled in.
+    let initial = reader.get_untracked();
+    let initial_comp_op = initial_comp_ops
+        .iter()
+        .find(|item| item.id == initial.comparison_op)
+        .copied();
+    let initial_field = fields
+        .with_untracked(|fields| fields.iter().find(|item| item.id == initial.field).copied());
 
     let (comp_ops, _set_comp_ops) = create_signal(initial_comp_ops);
 
-    let (selected_comp_op, set_selected_comp_op) = create_signal(Item {
+    let (selected_comp_op, set_selected_comp_op) = create_signal(initial_comp_op.unwrap_or(Item {
         id: "default",
         value: "Select an Operator",
         fe_id: 0,
-    });
-    let (selected_field, set_selected_field) = create_signal(Item {
+    }));
+    let (selected_field, set_selected_field) = create_signal(initial_field.unwrap_or(Item {
         id: "default",
         value: "Select a Field",
         fe_id: 0,
-    });
+    }));
     let InputUpdater { set_input_objects } = use_context().unwrap();
 
     create_effect(move |_| {
@@ -310,6 +924,24 @@ pub fn InputRow(id: usize, writer: WriteSignal<Input>) -> impl IntoView {
         writer.update(move |input| input.field = selected_field.get().id.to_string());
     });
 
+    let placeholder = move || match selected_comp_op.get().id {
+        RELATIVE_ERROR_OP => "Percent, e.g. 20%".to_string(),
+        CONSISTENT_OP => "Value and standard deviations, e.g. 1.0 Rjup, 2σ".to_string(),
+        IN_OP => "Values separated by commas, e.g. Transit, \"Radial Velocity\"".to_string(),
+        _ => find_column(selected_field.get().id)
+            .and_then(|column| column.quantity)
+            .map(|quantity| {
+                let units = quantity
+                    .units()
+                    .iter()
+                    .map(Unit::symbol)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("Value, optionally with a unit: {units}")
+            })
+            .unwrap_or_default(),
+    };
+
     view! {
         <div class="input-row" id=id>
             <Dropdown items=fields selected=selected_field set_selected=set_selected_field/>
@@ -317,6 +949,8 @@ pub fn InputRow(id: usize, writer: WriteSignal<Input>) -> impl IntoView {
             <input
                 class="input input-bordered input-sm input-info w-full"
                 type="text"
+                placeholder=placeholder
+                value=initial.value
                 on:input=move |ev| {
                     writer.update(move |input| input.value = event_target_value(&ev))
                 }
@@ -1,2 +1,12 @@
+pub mod account;
+pub mod admin;
+pub mod compare;
+pub mod console;
 pub mod input;
+pub mod offline;
 pub mod output;
+pub mod planet;
+pub mod plot;
+pub mod stats;
+pub mod system;
+pub mod timeline;
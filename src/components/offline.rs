@@ -0,0 +1,74 @@
+#![allow(non_snake_case)]
+
+use leptos::*;
+
+/// Whether the browser is online and how fresh its copy of the dataset is,
+/// for the navbar's indicator.
+#[derive(Clone, Copy)]
+pub struct DataStatus {
+    pub online: ReadSignal<bool>,
+    pub set_online: WriteSignal<bool>,
+    /// The latest release date in the local copy, once loaded.
+    pub latest_release: ReadSignal<Option<String>>,
+    pub set_latest_release: WriteSignal<Option<String>>,
+}
+
+pub fn provide_data_status() {
+    let (online, set_online) = create_signal(true);
+    let (latest_release, set_latest_release) = create_signal(None::<String>);
+    provide_context(DataStatus {
+        online,
+        set_online,
+        latest_release,
+        set_latest_release,
+    });
+
+    // Effects only run in the browser, so start the service worker and sync
+    // the local copy once hydrated.
+    create_effect(move |_| {
+        set_online(window().navigator().on_line());
+        let _ = window().navigator().service_worker().register("/sw.js");
+
+        #[cfg(any(feature = "csr", feature = "hydrate"))]
+        spawn_local(async move {
+            match crate::snapshot::sync().await {
+                Ok(snapshot) => set_latest_release(snapshot.latest_release().map(str::to_string)),
+                Err(error) => logging::warn!("couldn't load the dataset for offline use: {error}"),
+            }
+        });
+    });
+    window_event_listener(ev::online, move |_| set_online(true));
+    window_event_listener(ev::offline, move |_| set_online(false));
+}
+
+/// Shows how fresh the local copy of the dataset is, and when queries are
+/// being answered from it because the browser is offline.
+#[component]
+pub fn DataFreshness() -> impl IntoView {
+    let status: DataStatus = use_context().unwrap();
+
+    view! {
+        <div class="flex gap-2 items-center ml-auto">
+            {move || {
+                status
+                    .latest_release
+                    .get()
+                    .map(|date| {
+                        view! {
+                            <span
+                                class="badge badge-outline"
+                                title="The latest release date in the copy of the dataset kept for offline use"
+                            >
+                                {format!("Data as of {date}")}
+                            </span>
+                        }
+                    })
+            }}
+            <Show when=move || !status.online.get() fallback=|| ()>
+                <span class="badge badge-warning" title="Queries are answered from the local copy">
+                    "Offline"
+                </span>
+            </Show>
+        </div>
+    }
+}
@@ -1,23 +1,249 @@
 #![allow(non_snake_case)]
 
 use crate::{
-    components::input::{Fields, FirstId, LastId, QueryOutput},
-    model::data::PlanetData,
+    api::SaveColumnLayout,
+    components::{
+        account::Account,
+        compare::CompareCheckbox,
+        input::{
+            column_items, DisplayUnits, Fields, FirstId, LastId, Mode, OutputMode, QueryOutput,
+            SimilarSearch,
+        },
+        plot::PlotView,
+        stats::{percent_encode, StatsTable},
+        system::SystemView,
+    },
+    model::{
+        columns::{find_column, Column, ColumnKind, Group, COLUMNS},
+        data::PlanetData,
+        explain::QueryPlan,
+        limits::{is_rate_limited, is_timeout, retry_after},
+        measurement::Measurement,
+        units::{Quantity, UnitSystem},
+    },
 };
 use leptos::*;
+use leptos_router::A;
 
 #[component]
 pub fn OutputArea() -> impl IntoView {
+    let Mode { mode, set_mode: _ } = use_context().unwrap();
+    let similar: SimilarSearch = use_context().unwrap();
+
     view! {
         <div class="output-area overflow-x-auto overflow-y-auto">
-            <OutputTable/>
+            <div class="flex justify-end items-center gap-2 m-2">
+                {move || (mode() == OutputMode::Table).then(|| view! { <ColumnPicker/> })}
+                <UnitSelect/>
+            </div>
+            {move || match mode() {
+                OutputMode::Table if similar.showing.get() => view! {
+                    <div class="flex items-center gap-2 m-2">
+                        <span class="font-medium">"Most similar planets"</span>
+                        <button class="btn btn-ghost btn-xs" on:click=move |_| similar.set_showing.set(false)>
+                            "Back to query results"
+                        </button>
+                    </div>
+                    <SimilarResults similar/>
+                }
+                .into_view(),
+                OutputMode::Table => view! {
+                    <TableError/>
+                    <QueryPlanPanel/>
+                    <OutputTable/>
+                }
+                .into_view(),
+                OutputMode::Statistics => view! { <StatsTable/> }.into_view(),
+                OutputMode::Plot => view! { <PlotView/> }.into_view(),
+                OutputMode::Systems => view! { <SystemView/> }.into_view(),
+            }}
         </div>
     }
 }
 
+#[component]
+pub fn UnitSelect() -> impl IntoView {
+    let DisplayUnits { system, set_system } = use_context().unwrap();
+
+    view! {
+        <select
+            class="select select-bordered select-sm"
+            on:change=move |ev| {
+                if let Some(selected) = UnitSystem::parse(&event_target_value(&ev)) {
+                    set_system(selected);
+                }
+            }
+        >
+            {UnitSystem::ALL
+                .into_iter()
+                .map(|option| {
+                    view! {
+                        <option value=option.as_str() selected=move || system() == option>
+                            {option.label()}
+                        </option>
+                    }
+                })
+                .collect_view()}
+        </select>
+    }
+}
+
+/// Chooses the output table's columns, remembering the choice for signed-in
+/// users.
+#[component]
+pub fn ColumnPicker() -> impl IntoView {
+    let Fields { fields, set_fields } = use_context().unwrap();
+    let account: Account = use_context().unwrap();
+    let save_layout = create_server_action::<SaveColumnLayout>();
+
+    let toggle_column = move |column: &'static Column, shown: bool| {
+        let mut ids = fields.with_untracked(|fields| {
+            fields
+                .iter()
+                .map(|item| item.id)
+                .filter(|id| *id != column.id)
+                .collect::<Vec<_>>()
+        });
+        if shown {
+            ids.push(column.id);
+        }
+        set_fields(column_items(|column| ids.contains(&column.id)));
+        if account.user().is_some() {
+            save_layout.dispatch(SaveColumnLayout {
+                columns: ids.iter().map(|id| id.to_string()).collect(),
+            });
+        }
+    };
+
+    view! {
+        <details class="dropdown dropdown-end">
+            <summary class="btn btn-outline btn-sm">"Columns"</summary>
+            <ul class="dropdown-content z-[1] menu p-2 shadow bg-base-200 rounded-box w-72 max-h-96 overflow-y-auto flex-nowrap">
+                {COLUMNS
+                    .iter()
+                    .filter(|column| !column.hidden)
+                    .map(|column| {
+                        view! {
+                            <li>
+                                <label class="label cursor-pointer justify-start gap-2">
+                                    <input
+                                        type="checkbox"
+                                        class="checkbox checkbox-sm"
+                                        prop:checked=move || {
+                                            fields.with(|fields| fields.iter().any(|item| item.id == column.id))
+                                        }
+                                        on:change=move |ev| toggle_column(column, event_target_checked(&ev))
+                                    />
+                                    <span class="label-text">{column.label}</span>
+                                </label>
+                            </li>
+                        }
+                    })
+                    .collect_view()}
+            </ul>
+        </details>
+    }
+}
+
+/// Shows a failed query's error, explaining timeouts and rate limits.
+#[component]
+pub fn QueryError(error: ServerFnError) -> impl IntoView {
+    let message = error.to_string();
+    if is_timeout(&message) {
+        view! {
+            <div class="alert alert-warning m-2">
+                <div>
+                    <p class="font-bold">"The query took too long and was stopped."</p>
+                    <p>"Add filters to narrow it down and try again."</p>
+                </div>
+            </div>
+        }
+    } else if is_rate_limited(&message) {
+        let wait = match retry_after(&message) {
+            Some(secs) => format!("Try again in {secs} s."),
+            None => "Wait a moment and try again.".to_string(),
+        };
+        view! {
+            <div class="alert alert-warning m-2">
+                <div>
+                    <p class="font-bold">"Too many requests in a short time."</p>
+                    <p>{wait}</p>
+                </div>
+            </div>
+        }
+    } else {
+        view! { <div class="alert alert-error m-2">{message}</div> }
+    }
+}
+
+/// Shows why the last table query failed, if it did.
+#[component]
+fn TableError() -> impl IntoView {
+    let QueryOutput { value } = use_context().unwrap();
+
+    move || match value.get() {
+        Some(Err(error)) => Some(view! { <QueryError error/> }),
+        _ => None,
+    }
+}
+
+/// Shows how the database ran the last explained query.
+#[component]
+pub fn QueryPlanPanel() -> impl IntoView {
+    let QueryOutput { value } = use_context().unwrap();
+
+    let plan = move || {
+        value.with(|value| match value {
+            Some(Ok(Some(data))) => data.plan.clone(),
+            _ => None,
+        })
+    };
+
+    move || {
+        plan().map(|plan| {
+            let title = format!("Query plan - {:.1} ms", plan.elapsed_ms);
+            let full_scan = plan.full_scan();
+            view! {
+                <div class="collapse collapse-arrow bg-base-200 m-2">
+                    <input type="checkbox"/>
+                    <div class="collapse-title font-medium">
+                        {title}
+                        {full_scan
+                            .then(|| view! {
+                                <span class="badge badge-warning ml-2">"Full table scan"</span>
+                            })}
+                    </div>
+                    <div class="collapse-content">
+                        <QueryPlanSteps plan/>
+                    </div>
+                </div>
+            }
+        })
+    }
+}
+
+#[component]
+fn QueryPlanSteps(plan: QueryPlan) -> impl IntoView {
+    let steps = plan
+        .tree()
+        .into_iter()
+        .map(|(depth, step)| {
+            view! {
+                <li style=format!("padding-left: {}rem", depth as f64 * 1.5)>
+                    {step.detail.clone()}
+                </li>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <pre class="whitespace-pre-wrap text-sm mb-2">{plan.sql.clone()}</pre>
+        <ul class="font-mono text-sm">{steps}</ul>
+    }
+}
+
 #[component]
 pub fn OutputTable() -> impl IntoView {
-    let Fields { fields } = use_context().unwrap();
     let QueryOutput { value } = use_context().unwrap();
 
     let LastId {
@@ -46,108 +272,227 @@ pub fn OutputTable() -> impl IntoView {
         None => Vec::new(),
     };
 
+    view! { <PlanetTable planets=Signal::derive(unwrap_data)/> }
+}
+
+/// The results of a "find similar" search, most similar first.
+#[component]
+pub fn SimilarResults(similar: SimilarSearch) -> impl IntoView {
+    let error = move || match similar.action.value().get() {
+        Some(Err(error)) => Some(view! { <QueryError error/> }),
+        _ => None,
+    };
+
+    view! {
+        {error}
+        <PlanetTable planets=Signal::derive(move || similar.planets())/>
+    }
+}
+
+/// Rows of text under the output table's headings, for results that aren't
+/// whole planets such as the SQL console's. Columns named after catalog
+/// columns are labelled and shown like them, and an `id` column links to
+/// each planet's page.
+#[component]
+pub fn ResultTable(columns: Vec<String>, rows: Vec<Vec<Option<String>>>) -> impl IntoView {
+    let columns = columns
+        .into_iter()
+        .map(|name| (find_column(&name.to_ascii_lowercase()), name))
+        .collect::<Vec<_>>();
+    let headings = columns
+        .iter()
+        .map(|(column, name)| {
+            let label = column.map_or(name.clone(), |column| column.label.to_string());
+            view! { <th>{label}</th> }
+        })
+        .collect_view();
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            let cells = row
+                .into_iter()
+                .zip(&columns)
+                .map(|(value, (column, name))| match (value, column) {
+                    (None, _) => view! { <td></td> },
+                    (Some(id), _) if name.eq_ignore_ascii_case("id") => view! {
+                        <td><A href=format!("/planet/{id}") class="link">{id}</A></td>
+                    },
+                    (Some(flag), Some(column)) if column.kind == ColumnKind::Flag => {
+                        let shown = match flag.as_str() {
+                            "0" | "false" => "No",
+                            _ => "Yes",
+                        };
+                        view! { <td>{shown}</td> }
+                    }
+                    (Some(value), _) => view! { <td>{value}</td> },
+                })
+                .collect_view();
+            view! { <tr class="hover">{cells}</tr> }
+        })
+        .collect_view();
+
+    view! {
+        <table class="output-table table">
+            <thead>
+                <tr>{headings}</tr>
+            </thead>
+            <tbody>{rows}</tbody>
+        </table>
+    }
+}
+
+/// A link saving `csv` as `filename`, for the tables that can be exported.
+#[component]
+pub fn ExportCsv(csv: String, filename: &'static str) -> impl IntoView {
+    let href = format!("data:text/csv;charset=utf-8,{}", percent_encode(&csv));
+
+    view! {
+        <a class="btn btn-outline btn-sm btn-primary" href=href download=filename>
+            "Export CSV"
+        </a>
+    }
+}
+
+/// A row for each planet under the output table's chosen columns, or the
+/// summary columns outside `Home`.
+#[component]
+pub fn PlanetTable(
+    #[prop(into)] planets: MaybeSignal<Vec<PlanetData>>,
+    /// Whether each planet's details start out shown.
+    #[prop(optional)]
+    open: bool,
+) -> impl IntoView {
+    let fields = use_context::<Fields>().map(|fields| fields.fields);
+    let labels = move || -> Vec<&'static str> {
+        match fields {
+            Some(fields) => fields.with(|fields| fields.iter().map(|item| item.value).collect()),
+            None => COLUMNS
+                .iter()
+                .filter(|column| column.summary)
+                .map(|column| column.label)
+                .collect(),
+        }
+    };
+
     view! {
         <table class="output-table table">
             <thead>
                 <tr>
-                    <For
-                        each=fields
-                        key=|field| field.fe_id
-                        let:field
-                    >
-                            <th>{move || { field.value }}</th>
-                    </For>
-
+                    <th>"Compare"</th>
+                    {move || labels().into_iter().map(|label| view! { <th>{label}</th> }).collect_view()}
                 </tr>
             </thead>
             <tbody>
                 <For
-                    each=unwrap_data
-                    key=|result| result.to_owned()
-                    let:result
+                    each=move || planets.get()
+                    key=|planet| planet.id
+                    let:planet
                 >
-                       <SummaryRow data=result/>
-
+                    <SummaryRow data=planet open/>
                 </For>
-
             </tbody>
         </table>
     }
 }
 
 #[component]
-pub fn SummaryRow(data: PlanetData) -> impl IntoView {
-    let (open, set_open) = create_signal(false);
+pub fn SummaryRow(
+    data: PlanetData,
+    /// Whether the details start out shown.
+    #[prop(optional)]
+    open: bool,
+) -> impl IntoView {
+    let data = store_value(data);
+    let (open, set_open) = create_signal(open);
     let toggle = move |_| set_open(!open());
+
+    // The table's chosen columns, or the summary columns outside it.
+    let fields = use_context::<Fields>().map(|fields| fields.fields);
+    let shown_columns = move || -> Vec<&'static Column> {
+        match fields {
+            Some(fields) => fields.with(|fields| {
+                fields
+                    .iter()
+                    .filter_map(|item| find_column(item.id))
+                    .collect()
+            }),
+            None => COLUMNS.iter().filter(|column| column.summary).collect(),
+        }
+    };
+    let colspan = move || shown_columns().len() + 1;
+
+    // Only offer searches on pages that show their results.
+    let id = data.with_value(|data| data.id);
+    let mode = use_context::<Mode>();
+    let find_similar = use_context::<SimilarSearch>().map(|similar| {
+        move |_| {
+            if let Some(Mode { mode: _, set_mode }) = mode {
+                set_mode(OutputMode::Table);
+            }
+            similar.search(id);
+        }
+    });
+    let similar_button = move |label: &'static str| {
+        find_similar.map(|find_similar| {
+            view! {
+                <button class="btn btn-ghost btn-xs" on:click=find_similar>
+                    {label}
+                </button>
+            }
+        })
+    };
+
     view! {
         <tr class="summary-row hover" on:click=toggle>
-            <td>
-                <a href=data.caltech_href>{data.pl_name}</a>
+            // Ticking the box shouldn't also toggle the details.
+            <td on:click=|ev| ev.stop_propagation()>
+                <CompareCheckbox id=data.with_value(|data| data.id) pl_name=data.with_value(|data| data.pl_name.clone())/>
+                {similar_button("Similar")}
             </td>
-            <td>{data.hostname}</td>
-            <td>{data.sy_snum}</td>
-            <td>{data.sy_pnum}</td>
-            <td>{data.cb_flag}</td>
-            <td>{data.discovery_method}</td>
-            <td>{data.disc_year}</td>
-            <td>
-                <a href=data.disc_refhref>{data.disc_refname}</a>
-            </td>
-            <td>{data.disc_pubdate}</td>
+            {move || {
+                shown_columns()
+                    .into_iter()
+                    .map(|column| {
+                        let text = data.with_value(|data| display_value(column, data));
+                        let href = column
+                            .link
+                            .and_then(|link| data.with_value(|data| data.value(link)))
+                            .filter(|href| !href.is_null())
+                            .map(|href| href.to_string());
+                        match href {
+                            Some(href) => view! { <td><a href=href>{text}</a></td> },
+                            None => view! { <td>{text}</td> },
+                        }
+                    })
+                    .collect_view()
+            }}
         </tr>
         <Show when=open fallback=|| ()>
             <tr class="bg-primary-focus">
-                <td colspan=10>
-                    <div class="grid grid-flow-col">
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Planet Letter"</div>
-                            <div>{data.pl_letter.to_owned()}</div>
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Orbital Period [days]"</div>
-                            <SupSub
-                                text=data.pl_orbper.to_owned()
-                                sup=data.pl_orbpererr1.to_owned()
-                                sub=data.pl_orbpererr2.to_owned()
-                            />
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Planet Radius [Earth Radius]"</div>
-                            <SupSub
-                                text=data.pl_rade.to_owned()
-                                sup=data.pl_radeerr1.to_owned()
-                                sub=data.pl_radeerr2.to_owned()
-                            />
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Planet Mass [Earth Mass]"</div>
-                            <SupSub
-                                text=data.pl_bmasse.to_owned()
-                                sup=data.pl_bmasseerr1.to_owned()
-                                sub=data.pl_bmasseerr2.to_owned()
-                            />
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Planet Mass Estimation Formula"</div>
-                            <div>{data.pl_bmassprov.to_owned()}</div>
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Discovery Facility"</div>
-                            <div>{data.disc_facility.to_owned()}</div>
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Spectral Type"</div>
-                            <div>{data.st_spectype.to_owned()}</div>
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Discovery Telescope"</div>
-                            <div>{data.disc_telescope.to_owned()}</div>
-                        </div>
-                        <div class="grid grid-cols-1 gap-[0.5rem]">
-                            <div>"Release Date"</div>
-                            <div>{data.release_date.to_owned()}</div>
-                        </div>
-                    </div>
+                <td colspan=colspan>
+                    <A href=format!("/planet/{id}") class="btn btn-ghost btn-xs">"Planet page"</A>
+                    {similar_button("Find similar planets")}
+                    {move || {
+                        let shown = shown_columns();
+                        Group::ALL
+                            .into_iter()
+                            .map(|group| {
+                                let cells = COLUMNS
+                                    .iter()
+                                    .filter(|column| {
+                                        column.group == group && !column.hidden
+                                            && !shown.iter().any(|shown| shown.id == column.id)
+                                            && data.with_value(|data| has_value(column, data))
+                                    })
+                                    .map(|column| view! { <DetailCell data column/> })
+                                    .collect_view();
+                                view! {
+                                    <div class="divider">{group.label()}</div>
+                                    <div class="grid grid-cols-4 gap-[0.5rem]">{cells}</div>
+                                }
+                            })
+                            .collect_view()
+                    }}
                 </td>
             </tr>
         </Show>
@@ -155,16 +500,86 @@ pub fn SummaryRow(data: PlanetData) -> impl IntoView {
 }
 
 #[component]
-pub fn SupSub(text: Option<String>, sup: Option<String>, sub: Option<String>) -> impl IntoView {
-    let unwrapped_text = text.unwrap_or("".to_string());
-    let unwrapped_sub = sub.unwrap_or("".to_string());
-    let unwrapped_sup = sup.unwrap_or("".to_string());
+pub fn DetailCell(data: StoredValue<PlanetData>, column: &'static Column) -> impl IntoView {
+    let DisplayUnits {
+        system,
+        set_system: _,
+    } = use_context().unwrap();
+
+    let label = move || column_label(column, system());
+
+    let value = if column.errors || column.quantity.is_some() {
+        let measurement = data.with_value(|data| measurement(column, data));
+        match column.quantity {
+            Some(quantity) => view! { <SupSub measurement quantity/> }.into_view(),
+            None => view! { <SupSub measurement/> }.into_view(),
+        }
+    } else {
+        let text = data.with_value(|data| display_value(column, data));
+        view! { <div>{text}</div> }.into_view()
+    };
+
+    view! {
+        <div class="grid grid-cols-1 gap-[0.5rem]">
+            <div>{label}</div>
+            {value}
+        </div>
+    }
+}
+
+/// The column's label, with the unit `system` shows it in.
+pub fn column_label(column: &Column, system: UnitSystem) -> String {
+    match column.quantity {
+        Some(quantity) => format!("{} [{}]", column.label, system.unit_for(quantity).label()),
+        None => column.label.to_string(),
+    }
+}
+
+pub fn measurement(column: &Column, data: &PlanetData) -> Measurement {
+    match column.derived {
+        Some(derived) => derived.compute(data),
+        None => data.measurement(column.id),
+    }
+}
+
+pub fn has_value(column: &Column, data: &PlanetData) -> bool {
+    match column.derived {
+        Some(_) => measurement(column, data).value.is_some(),
+        None => data.value(column.id).is_some_and(|value| !value.is_null()),
+    }
+}
+
+pub fn display_value(column: &Column, data: &PlanetData) -> String {
+    match (column.kind, data.value(column.id)) {
+        (ColumnKind::Flag, Some(value)) if !value.is_null() => match value.as_i64() {
+            Some(0) => "No".to_string(),
+            _ => "Yes".to_string(),
+        },
+        (_, Some(value)) => value.to_string(),
+        (_, None) => "".to_string(),
+    }
+}
+
+#[component]
+pub fn SupSub(
+    measurement: Measurement,
+    #[prop(optional)] quantity: Option<Quantity>,
+) -> impl IntoView {
+    let DisplayUnits {
+        system,
+        set_system: _,
+    } = use_context().unwrap();
+
+    let formatted = move || match quantity {
+        Some(quantity) => measurement.convert(quantity, system()).format(),
+        None => measurement.format(),
+    };
 
     view! {
         <div>
-            {unwrapped_text} <span class="supsub">
-                <sup>{unwrapped_sup}</sup>
-                <sub>{unwrapped_sub}</sub>
+            {move || formatted().value} <span class="supsub">
+                <sup>{move || formatted().upper}</sup>
+                <sub>{move || formatted().lower}</sub>
             </span>
         </div>
     }
@@ -0,0 +1,61 @@
+#![allow(non_snake_case)]
+
+use crate::components::{
+    input::provide_similar_search,
+    output::{PlanetTable, QueryError, SimilarResults},
+};
+use leptos::*;
+use leptos_router::use_params_map;
+
+// In the browser, answer from the snapshot when there's no server to ask.
+#[cfg(not(any(feature = "csr", feature = "hydrate")))]
+use crate::api::get_planet;
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+use crate::snapshot::get_planet;
+
+/// One planet's details at `/planet/:id`, with a search for the planets most
+/// like it.
+#[component]
+pub fn PlanetPage() -> impl IntoView {
+    let params = use_params_map();
+    let id = move || params.with(|params| params.get("id").and_then(|id| id.parse::<i64>().ok()));
+    let planet = create_resource(id, |id| async move {
+        match id {
+            Some(id) => get_planet(id).await,
+            None => Ok(None),
+        }
+    });
+    let similar = provide_similar_search();
+
+    let details = move || {
+        planet.get().map(|planet| match planet {
+            Ok(Some(planet)) => view! {
+                <h1 class="text-2xl font-bold m-2">{planet.pl_name.clone()}</h1>
+                <PlanetTable planets=vec![planet] open=true/>
+            }
+            .into_view(),
+            Ok(None) => view! {
+                <div class="alert alert-warning m-2">"There's no planet with that id."</div>
+            }
+            .into_view(),
+            Err(error) => view! { <QueryError error/> }.into_view(),
+        })
+    };
+
+    view! {
+        <div class="overflow-x-auto">
+            <Suspense fallback=|| ()>{details}</Suspense>
+            {move || {
+                similar
+                    .showing
+                    .get()
+                    .then(|| {
+                        view! {
+                            <h2 class="text-xl font-bold m-2">"Most similar planets"</h2>
+                            <SimilarResults similar/>
+                        }
+                    })
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,338 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    components::{
+        input::{DisplayUnits, PlotOutput},
+        output::{PlanetTable, QueryError},
+    },
+    model::{
+        data::PlanetData,
+        plot::{
+            axis_label, format_tick, HistogramLayout, PlotData, PlotKind, PlotParams,
+            ScatterLayout, Tick, BOTTOM, HEIGHT, LEFT, RIGHT, TOP, WIDTH,
+        },
+    },
+};
+use leptos::*;
+
+// In the browser, answer from the snapshot when there's no server to ask.
+#[cfg(not(any(feature = "csr", feature = "hydrate")))]
+use crate::api::get_planet;
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+use crate::snapshot::get_planet;
+
+#[component]
+pub fn PlotView() -> impl IntoView {
+    let PlotOutput {
+        value,
+        query,
+        set_query: _,
+    } = use_context().unwrap();
+    let DisplayUnits {
+        system,
+        set_system: _,
+    } = use_context().unwrap();
+
+    let (kind, set_kind) = create_signal(PlotKind::Scatter);
+    let (log_x, set_log_x) = create_signal(false);
+    let (log_y, set_log_y) = create_signal(false);
+    let (selected, set_selected) = create_signal(None::<i64>);
+
+    let planet = create_resource(selected, |id| async move {
+        match id {
+            Some(id) => get_planet(id).await.ok().flatten(),
+            None => None,
+        }
+    });
+
+    let plot = move || match value.get() {
+        Some(Ok(data)) => {
+            let data = data.convert(system());
+            let truncated = data.truncated.then(|| {
+                view! {
+                    <div class="alert alert-warning m-2">
+                        "Only the first " {data.points.len()} " matching planets are plotted."
+                    </div>
+                }
+            });
+            let params = PlotParams {
+                query: query.get_untracked(),
+                x: data.x.clone(),
+                y: Some(data.y.clone()),
+                kind: kind(),
+                log_x: log_x(),
+                log_y: log_y(),
+                units: system(),
+            };
+            let links = view! { <ImageLinks params/> };
+            let chart = match kind() {
+                PlotKind::Scatter => view! {
+                    <Scatter data log_x=log_x() log_y=log_y() set_selected/>
+                }
+                .into_view(),
+                PlotKind::Histogram => view! { <Histogram data log_x=log_x()/> }.into_view(),
+            };
+            view! { {truncated} {chart} {links} }.into_view()
+        }
+        Some(Err(error)) => view! { <QueryError error/> }.into_view(),
+        None => ().into_view(),
+    };
+
+    view! {
+        <div class="flex gap-2 items-center m-2">
+            <div class="join">
+                {[PlotKind::Scatter, PlotKind::Histogram]
+                    .into_iter()
+                    .map(|option| {
+                        view! {
+                            <button
+                                class="btn btn-sm join-item"
+                                class:btn-active=move || kind() == option
+                                on:click=move |_| set_kind(option)
+                            >
+                                {match option {
+                                    PlotKind::Scatter => "Scatter",
+                                    PlotKind::Histogram => "Histogram",
+                                }}
+                            </button>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+            <label class="label cursor-pointer gap-1">
+                <input
+                    type="checkbox"
+                    class="checkbox checkbox-sm"
+                    prop:checked=log_x
+                    on:change=move |ev| set_log_x(event_target_checked(&ev))
+                />
+                <span class="label-text">"Log X"</span>
+            </label>
+            <Show when=move || kind() == PlotKind::Scatter fallback=|| ()>
+                <label class="label cursor-pointer gap-1">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=log_y
+                        on:change=move |ev| set_log_y(event_target_checked(&ev))
+                    />
+                    <span class="label-text">"Log Y"</span>
+                </label>
+            </Show>
+        </div>
+        {plot}
+        <Suspense fallback=|| ()>
+            {move || planet.get().flatten().map(|planet| view! { <SelectedPlanet planet/> })}
+        </Suspense>
+    }
+}
+
+#[component]
+fn Scatter(
+    data: PlotData,
+    log_x: bool,
+    log_y: bool,
+    set_selected: WriteSignal<Option<i64>>,
+) -> impl IntoView {
+    let DisplayUnits {
+        system,
+        set_system: _,
+    } = use_context().unwrap();
+
+    let Some(ScatterLayout {
+        marks,
+        x_ticks,
+        y_ticks,
+        legend,
+    }) = data.scatter(log_x, log_y)
+    else {
+        return view! { <div class="m-2">"No planets have values on both axes."</div> }.into_view();
+    };
+
+    let marks = marks
+        .into_iter()
+        .map(|mark| {
+            let id = mark.id;
+            let x_bar = mark.x_bar.map(|(low, high)| {
+                view! { <line x1=low y1=mark.cy x2=high y2=mark.cy stroke=mark.color/> }
+            });
+            let y_bar = mark.y_bar.map(|(low, high)| {
+                view! { <line x1=mark.cx y1=low x2=mark.cx y2=high stroke=mark.color/> }
+            });
+            view! {
+                <g class="cursor-pointer" on:click=move |_| set_selected(Some(id))>
+                    {x_bar}
+                    {y_bar}
+                    <circle cx=mark.cx cy=mark.cy r=3 fill=mark.color fill-opacity=0.7/>
+                </g>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <svg viewBox=format!("0 0 {WIDTH} {HEIGHT}") class="plot w-full max-w-4xl">
+            <Axes
+                x_ticks
+                y_ticks
+                x_label=axis_label(&data.x, system.get_untracked())
+                y_label=axis_label(&data.y, system.get_untracked())
+            />
+            {marks}
+        </svg>
+        <div class="flex flex-wrap gap-3 m-2">
+            {legend
+                .into_iter()
+                .map(|(method, color)| {
+                    let method = if method.is_empty() { "Unknown".to_string() } else { method };
+                    view! {
+                        <span class="flex items-center gap-1">
+                            <span
+                                class="inline-block w-3 h-3 rounded-full"
+                                style=format!("background-color: {color}")
+                            ></span>
+                            {method}
+                        </span>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+    .into_view()
+}
+
+#[component]
+fn Histogram(data: PlotData, log_x: bool) -> impl IntoView {
+    let DisplayUnits {
+        system,
+        set_system: _,
+    } = use_context().unwrap();
+
+    let Some(HistogramLayout {
+        bars,
+        x_ticks,
+        y_ticks,
+    }) = data.histogram(log_x)
+    else {
+        return view! { <div class="m-2">"No planets have a value to plot."</div> }.into_view();
+    };
+
+    let bars = bars
+        .into_iter()
+        .map(|bar| {
+            let range = format!(
+                "{} to {}: {}",
+                format_tick(bar.start),
+                format_tick(bar.end),
+                bar.count
+            );
+            view! {
+                <rect
+                    x=bar.x
+                    y=bar.y
+                    width=bar.width
+                    height=bar.height
+                    fill="#1f77b4"
+                    fill-opacity=0.8
+                    stroke="white"
+                    aria-label=range
+                />
+            }
+        })
+        .collect_view();
+
+    view! {
+        <svg viewBox=format!("0 0 {WIDTH} {HEIGHT}") class="plot w-full max-w-4xl">
+            <Axes
+                x_ticks
+                y_ticks
+                x_label=axis_label(&data.x, system.get_untracked())
+                y_label="Planets".to_string()
+            />
+            {bars}
+        </svg>
+    }
+    .into_view()
+}
+
+#[component]
+pub fn Axes(
+    x_ticks: Vec<Tick>,
+    y_ticks: Vec<Tick>,
+    x_label: String,
+    y_label: String,
+) -> impl IntoView {
+    let x_ticks = x_ticks
+        .into_iter()
+        .map(|tick| {
+            view! {
+                <line x1=tick.position y1=BOTTOM x2=tick.position y2=BOTTOM + 5.0 stroke="currentColor"/>
+                <text x=tick.position y=BOTTOM + 18.0 text-anchor="middle" font-size=11 fill="currentColor">
+                    {tick.label}
+                </text>
+            }
+        })
+        .collect_view();
+    let y_ticks = y_ticks
+        .into_iter()
+        .map(|tick| {
+            view! {
+                <line x1=LEFT - 5.0 y1=tick.position x2=LEFT y2=tick.position stroke="currentColor"/>
+                <text
+                    x=LEFT - 8.0
+                    y=tick.position + 4.0
+                    text-anchor="end"
+                    font-size=11
+                    fill="currentColor"
+                >
+                    {tick.label}
+                </text>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <line x1=LEFT y1=BOTTOM x2=RIGHT y2=BOTTOM stroke="currentColor"/>
+        <line x1=LEFT y1=BOTTOM x2=LEFT y2=TOP stroke="currentColor"/>
+        {x_ticks}
+        {y_ticks}
+        <text
+            x=(LEFT + RIGHT) / 2.0
+            y=HEIGHT - 8.0
+            text-anchor="middle"
+            font-size=12
+            fill="currentColor"
+        >
+            {x_label}
+        </text>
+        <text
+            transform=format!("translate(14 {}) rotate(-90)", (TOP + BOTTOM) / 2.0)
+            text-anchor="middle"
+            font-size=12
+            fill="currentColor"
+        >
+            {y_label}
+        </text>
+    }
+}
+
+/// Links to the server-rendered image of the plot, for embedding elsewhere.
+#[component]
+fn ImageLinks(params: PlotParams) -> impl IntoView {
+    let query = serde_qs::to_string(&params).unwrap_or_default();
+
+    view! {
+        <div class="flex gap-2 m-2">
+            <a class="btn btn-outline btn-sm" href=format!("/plot.svg?{query}") target="_blank">
+                "Open as SVG"
+            </a>
+            <a class="btn btn-outline btn-sm" href=format!("/plot.png?{query}") target="_blank">
+                "Open as PNG"
+            </a>
+        </div>
+    }
+}
+
+#[component]
+pub fn SelectedPlanet(planet: PlanetData) -> impl IntoView {
+    view! { <PlanetTable planets=vec![planet] open=true/> }
+}
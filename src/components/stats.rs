@@ -0,0 +1,96 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    components::{
+        input::StatsOutput,
+        output::{ExportCsv, QueryError},
+    },
+    model::{
+        columns::find_column,
+        measurement::{Limit, Measurement},
+        stats::Statistics,
+    },
+};
+use leptos::*;
+
+#[component]
+pub fn StatsTable() -> impl IntoView {
+    let StatsOutput { value } = use_context().unwrap();
+
+    move || match value.get() {
+        Some(Ok(statistics)) => view! { <StatsResult statistics/> }.into_view(),
+        Some(Err(error)) => view! { <QueryError error/> }.into_view(),
+        None => ().into_view(),
+    }
+}
+
+#[component]
+fn StatsResult(statistics: Statistics) -> impl IntoView {
+    let label = |id: &str| {
+        find_column(id)
+            .map(|column| column.label.to_string())
+            .unwrap_or_else(|| id.to_string())
+    };
+    let title = format!(
+        "{} by {}",
+        label(&statistics.column),
+        label(&statistics.group_by)
+    );
+    let csv = statistics.to_csv();
+
+    view! {
+        <div class="flex justify-between items-center m-2">
+            <h2 class="text-lg">{title}</h2>
+            <ExportCsv csv filename="statistics.csv"/>
+        </div>
+        <table class="stats-table table">
+            <thead>
+                <tr>
+                    {Statistics::HEADERS
+                        .iter()
+                        .map(|header| view! { <th>{*header}</th> })
+                        .collect_view()}
+                </tr>
+            </thead>
+            <tbody>
+                {statistics
+                    .groups
+                    .into_iter()
+                    .map(|group| {
+                        view! {
+                            <tr class="hover">
+                                <td>{group.group_value.unwrap_or_else(|| "(none)".to_string())}</td>
+                                <td>{group.count}</td>
+                                <td>{format_number(group.min)}</td>
+                                <td>{format_number(group.max)}</td>
+                                <td>{format_number(group.mean)}</td>
+                                <td>{format_number(group.median)}</td>
+                            </tr>
+                        }
+                    })
+                    .collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
+fn format_number(value: Option<f64>) -> String {
+    Measurement::new(value, None, None, Limit::None)
+        .format()
+        .value
+}
+
+/// Percent-encodes everything but unreserved characters so `text` can be
+/// used in a `data:` URL.
+pub fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,194 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    components::{input::SystemsOutput, output::QueryError, plot::SelectedPlanet},
+    model::{
+        plot::{LEFT, RIGHT, WIDTH},
+        system::{layout, PlanetarySystem, SystemAxis, SystemLayout, SystemRow, AXIS_HEIGHT},
+    },
+};
+use leptos::*;
+
+// In the browser, answer from the snapshot when there's no server to ask.
+#[cfg(not(any(feature = "csr", feature = "hydrate")))]
+use crate::api::get_planet;
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+use crate::snapshot::get_planet;
+
+const STAR_COLOR: &str = "#f5b700";
+const PLANET_COLOR: &str = "#1f77b4";
+
+#[component]
+pub fn SystemView() -> impl IntoView {
+    let SystemsOutput { value } = use_context().unwrap();
+
+    let (axis, set_axis) = create_signal(SystemAxis::Period);
+    let (selected, set_selected) = create_signal(None::<i64>);
+
+    let planet = create_resource(selected, |id| async move {
+        match id {
+            Some(id) => get_planet(id).await.ok().flatten(),
+            None => None,
+        }
+    });
+
+    let diagram = move || match value.get() {
+        Some(Ok(systems)) => {
+            view! { <SystemDiagram systems axis=axis() set_selected/> }.into_view()
+        }
+        Some(Err(error)) => view! { <QueryError error/> }.into_view(),
+        None => ().into_view(),
+    };
+
+    view! {
+        <div class="flex gap-2 items-center m-2">
+            <div class="join">
+                {SystemAxis::ALL
+                    .into_iter()
+                    .map(|option| {
+                        view! {
+                            <button
+                                class="btn btn-sm join-item"
+                                class:btn-active=move || axis() == option
+                                on:click=move |_| set_axis(option)
+                            >
+                                {match option {
+                                    SystemAxis::Period => "Period",
+                                    SystemAxis::SemiMajorAxis => "Semi-Major Axis",
+                                }}
+                            </button>
+                        }
+                    })
+                    .collect_view()}
+            </div>
+            <span class="text-sm">
+                "Markers are sized by planet radius; hollow markers have no known radius."
+            </span>
+        </div>
+        {diagram}
+        <Suspense fallback=|| ()>
+            {move || planet.get().flatten().map(|planet| view! { <SelectedPlanet planet/> })}
+        </Suspense>
+    }
+}
+
+#[component]
+fn SystemDiagram(
+    systems: Vec<PlanetarySystem>,
+    axis: SystemAxis,
+    set_selected: WriteSignal<Option<i64>>,
+) -> impl IntoView {
+    let Some(SystemLayout {
+        rows,
+        ticks,
+        height,
+    }) = layout(&systems, axis)
+    else {
+        return view! { <div class="m-2">"No multi-planet systems match the filters."</div> }
+            .into_view();
+    };
+
+    let axis_y = height - AXIS_HEIGHT;
+    let ticks = ticks
+        .into_iter()
+        .map(|tick| {
+            view! {
+                <line
+                    x1=tick.position
+                    y1=axis_y
+                    x2=tick.position
+                    y2=axis_y + 5.0
+                    stroke="currentColor"
+                />
+                <text
+                    x=tick.position
+                    y=axis_y + 18.0
+                    text-anchor="middle"
+                    font-size=11
+                    fill="currentColor"
+                >
+                    {tick.label}
+                </text>
+            }
+        })
+        .collect_view();
+
+    let rows = rows
+        .into_iter()
+        .map(|row| view! { <SystemRowView row set_selected/> })
+        .collect_view();
+
+    view! {
+        <svg viewBox=format!("0 0 {WIDTH} {height}") class="plot w-full max-w-4xl">
+            {rows}
+            <line x1=LEFT y1=axis_y x2=RIGHT y2=axis_y stroke="currentColor"/>
+            {ticks}
+            <text
+                x=(LEFT + RIGHT) / 2.0
+                y=height - 6.0
+                text-anchor="middle"
+                font-size=12
+                fill="currentColor"
+            >
+                {axis.label()}
+            </text>
+        </svg>
+    }
+    .into_view()
+}
+
+#[component]
+fn SystemRowView(row: SystemRow, set_selected: WriteSignal<Option<i64>>) -> impl IntoView {
+    let mut label = row.hostname.clone();
+    if row.star_count > 1 {
+        label.push_str(&format!(" ({} stars)", row.star_count));
+    }
+    if row.circumbinary {
+        label.push_str(" - circumbinary");
+    }
+    if !row.unplaced.is_empty() {
+        label.push_str(&format!(" - not shown: {}", row.unplaced.join(", ")));
+    }
+
+    // Circumbinary planets orbit both stars, so draw the host as a pair.
+    let star = if row.circumbinary {
+        view! {
+            <circle cx=LEFT - 24.0 cy=row.y r=6 fill=STAR_COLOR/>
+            <circle cx=LEFT - 10.0 cy=row.y r=6 fill=STAR_COLOR/>
+        }
+        .into_view()
+    } else {
+        view! { <circle cx=LEFT - 16.0 cy=row.y r=8 fill=STAR_COLOR/> }.into_view()
+    };
+
+    let planets = row
+        .planets
+        .into_iter()
+        .map(|planet| {
+            let id = planet.id;
+            let fill = if planet.sized { PLANET_COLOR } else { "none" };
+            view! {
+                <circle
+                    class="cursor-pointer"
+                    cx=planet.cx
+                    cy=planet.cy
+                    r=planet.r
+                    fill=fill
+                    fill-opacity=0.8
+                    stroke=PLANET_COLOR
+                    aria-label=planet.pl_name
+                    on:click=move |_| set_selected(Some(id))
+                />
+            }
+        })
+        .collect_view();
+
+    view! {
+        <text x=4 y=row.y - 20.0 font-size=11 fill="currentColor">
+            {label}
+        </text>
+        {star}
+        <line x1=LEFT y1=row.y x2=RIGHT y2=row.y stroke="currentColor" stroke-opacity=0.3/>
+        {planets}
+    }
+}
@@ -0,0 +1,161 @@
+#![allow(non_snake_case)]
+
+use crate::{
+    components::{input::ActiveFilters, output::QueryError, plot::Axes},
+    model::{
+        plot::{HEIGHT, WIDTH},
+        timeline::{StackBy, Timeline, TimelineLayout},
+    },
+};
+use leptos::*;
+
+// In the browser, answer from the snapshot when there's no server to ask.
+#[cfg(not(any(feature = "csr", feature = "hydrate")))]
+use crate::api::query_timeline;
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+use crate::snapshot::query_timeline;
+
+#[component]
+pub fn DiscoveryTimeline() -> impl IntoView {
+    let ActiveFilters {
+        filters,
+        set_filters: _,
+    } = use_context().unwrap();
+
+    let (stack_by, set_stack_by) = create_signal(StackBy::Method);
+    let (cumulative, set_cumulative) = create_signal(false);
+
+    let timeline = create_resource(
+        move || (filters.get(), stack_by.get()),
+        |(query, stack_by)| query_timeline(query, stack_by),
+    );
+
+    let filter_summary = move || {
+        let filters = filters.get();
+        match filters.len() {
+            0 => "Showing every planet. Filters submitted on the Home page apply here.".to_string(),
+            _ => format!(
+                "Filtered by {}.",
+                filters
+                    .iter()
+                    .map(|input| format!("{} {} {}", input.field, input.comparison_op, input.value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    };
+
+    view! {
+        <div class="timeline-area m-2">
+            <p class="m-2">{filter_summary}</p>
+            <div class="flex gap-2 items-center m-2">
+                <div class="join">
+                    {StackBy::ALL
+                        .into_iter()
+                        .map(|option| {
+                            view! {
+                                <button
+                                    class="btn btn-sm join-item"
+                                    class:btn-active=move || stack_by() == option
+                                    on:click=move |_| set_stack_by(option)
+                                >
+                                    {option.label()}
+                                </button>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                <div class="join">
+                    <button
+                        class="btn btn-sm join-item"
+                        class:btn-active=move || !cumulative()
+                        on:click=move |_| set_cumulative(false)
+                    >
+                        "Per Year"
+                    </button>
+                    <button
+                        class="btn btn-sm join-item"
+                        class:btn-active=cumulative
+                        on:click=move |_| set_cumulative(true)
+                    >
+                        "Cumulative"
+                    </button>
+                </div>
+            </div>
+            <Suspense fallback=|| view! { <span class="loading loading-spinner m-2"></span> }>
+                {move || {
+                    timeline
+                        .get()
+                        .map(|result| match result {
+                            Ok(timeline) => view! {
+                                <TimelineChart timeline cumulative=cumulative()/>
+                            }
+                            .into_view(),
+                            Err(error) => view! { <QueryError error/> }.into_view(),
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+#[component]
+fn TimelineChart(timeline: Timeline, cumulative: bool) -> impl IntoView {
+    let Some(TimelineLayout {
+        bars,
+        x_ticks,
+        y_ticks,
+        legend,
+    }) = timeline.layout(cumulative)
+    else {
+        return view! { <div class="m-2">"No planets have a discovery year."</div> }.into_view();
+    };
+
+    let bars = bars
+        .into_iter()
+        .map(|bar| {
+            let label = format!("{} {}: {}", bar.year, bar.category, bar.count);
+            view! {
+                <rect
+                    x=bar.x
+                    y=bar.y
+                    width=bar.width
+                    height=bar.height
+                    fill=bar.color
+                    aria-label=label
+                />
+            }
+        })
+        .collect_view();
+
+    view! {
+        <p class="m-2">{timeline.total()} " planets discovered."</p>
+        <svg viewBox=format!("0 0 {WIDTH} {HEIGHT}") class="plot w-full max-w-4xl">
+            <Axes
+                x_ticks
+                y_ticks
+                x_label="Discovery Year".to_string()
+                y_label=if cumulative { "Planets Discovered (cumulative)" } else { "Planets Discovered" }
+                    .to_string()
+            />
+            {bars}
+        </svg>
+        <div class="flex flex-wrap gap-3 m-2">
+            {legend
+                .into_iter()
+                .map(|(category, color)| {
+                    view! {
+                        <span class="flex items-center gap-1">
+                            <span
+                                class="inline-block w-3 h-3"
+                                style=format!("background-color: {color}")
+                            ></span>
+                            {category}
+                        </span>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+    .into_view()
+}
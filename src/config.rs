@@ -0,0 +1,497 @@
+//! The server's configuration, loaded once at startup from a TOML file with
+//! environment variables overriding it.
+//!
+//! The file is `CONFIG_FILE` if that's set, otherwise `config.toml` in the
+//! working directory if there is one; every setting has a default but the
+//! database URL. `config.example.toml` lists them all with the variable that
+//! overrides each. Leptos's own settings (the site address and root) stay in
+//! `Cargo.toml` and the `LEPTOS_*` variables.
+
+use crate::storage::Dialect;
+use serde::Deserialize;
+use std::{env, fmt, fs, io, net::IpAddr, path::PathBuf, sync::OnceLock};
+
+const DEFAULT_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub limits: LimitsConfig,
+    pub cache: CacheConfig,
+    pub rate_limit: RateLimitConfig,
+    pub import: ImportConfig,
+    pub auth: AuthConfig,
+    pub features: FeaturesConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// The dataset's SQLite or Postgres database. `DATABASE_URL`.
+    pub url: String,
+    /// Connections kept open for requests. `DATABASE_MAX_CONNECTIONS`.
+    pub max_connections: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            max_connections: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// `QUERY_TIMEOUT_MS`.
+    pub timeout_ms: u64,
+    /// `QUERY_MAX_FILTERS`.
+    pub max_filters: usize,
+    /// `QUERY_MAX_VALUE_LENGTH`.
+    pub max_value_length: usize,
+    /// Records in a page of the output table. `QUERY_PAGE_SIZE`.
+    pub page_size: i64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            timeout_ms: 5000,
+            max_filters: 20,
+            max_value_length: 200,
+            page_size: crate::model::data::PAGE_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Pages of results kept; 0 turns the cache off. `QUERY_CACHE_SIZE`.
+    pub size: usize,
+    /// `QUERY_CACHE_TTL_SECS`.
+    pub ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            size: 256,
+            ttl_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Queries a minute; 0 turns the limit off. `RATE_LIMIT_PER_MINUTE`.
+    pub per_minute: f64,
+    /// `RATE_LIMIT_BURST`.
+    pub burst: f64,
+    /// Statistics, plots and exports a minute. `RATE_LIMIT_EXPORT_PER_MINUTE`.
+    pub export_per_minute: f64,
+    /// `RATE_LIMIT_EXPORT_BURST`.
+    pub export_burst: f64,
+    /// Reverse proxies whose `X-Forwarded-For` names the client.
+    /// `RATE_LIMIT_TRUSTED_PROXIES`, separated by commas.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            per_minute: 120.0,
+            burst: 30.0,
+            export_per_minute: 12.0,
+            export_burst: 4.0,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImportConfig {
+    /// The archive CSV export the Admin page imports. `IMPORT_PATH`.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// The SQLite database of users; sign-in is off without it.
+    /// `AUTH_DATABASE_URL`.
+    pub database_url: Option<String>,
+    pub oidc: Option<OidcConfig>,
+}
+
+/// An OpenID Connect provider to sign in through. `OIDC_ISSUER`,
+/// `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET` and `OIDC_REDIRECT_URL`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeaturesConfig {
+    /// The admin SQL console. `SQL_CONSOLE`.
+    pub sql_console: bool,
+    /// Gives every visitor every role while sign-in is off. `OPEN_ADMIN`.
+    pub open_admin: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// An environment variable that doesn't parse as its setting.
+    Env {
+        name: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    /// A setting out of range, named by its path in the file.
+    Invalid {
+        setting: &'static str,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, error) => {
+                write!(f, "couldn't read {}: {error}", path.display())
+            }
+            ConfigError::Parse(path, error) => write!(f, "in {}: {error}", path.display()),
+            ConfigError::Env {
+                name,
+                value,
+                expected,
+            } => write!(f, "{name} is '{value}', which isn't {expected}"),
+            ConfigError::Invalid { setting, message } => write!(f, "{setting} {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(setting: &'static str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        setting,
+        message: message.into(),
+    }
+}
+
+/// Replaces `setting` with the variable `name` when it's set.
+fn override_with<T: std::str::FromStr>(
+    env: &impl Fn(&str) -> Option<String>,
+    name: &'static str,
+    expected: &'static str,
+    setting: &mut T,
+) -> Result<(), ConfigError> {
+    if let Some(value) = env(name) {
+        *setting = value.trim().parse().map_err(|_| ConfigError::Env {
+            name,
+            value,
+            expected,
+        })?;
+    }
+    Ok(())
+}
+
+fn override_bool(
+    env: &impl Fn(&str) -> Option<String>,
+    name: &'static str,
+    setting: &mut bool,
+) -> Result<(), ConfigError> {
+    if let Some(value) = env(name) {
+        *setting = match value.trim() {
+            "1" | "true" => true,
+            "0" | "false" | "" => false,
+            _ => {
+                return Err(ConfigError::Env {
+                    name,
+                    value,
+                    expected: "true, false, 1 or 0",
+                })
+            }
+        };
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Parses a configuration file's contents, leaving settings it doesn't
+    /// mention at their defaults.
+    pub fn parse(path: PathBuf, toml: &str) -> Result<Config, ConfigError> {
+        toml::from_str(toml).map_err(|error| ConfigError::Parse(path, error))
+    }
+
+    /// Applies the environment variables that override settings, reading
+    /// them with `env`.
+    pub fn override_from(
+        &mut self,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        const NUMBER: &str = "a whole number";
+        const RATE: &str = "a number";
+
+        if let Some(url) = env("DATABASE_URL") {
+            self.database.url = url;
+        }
+        override_with(
+            &env,
+            "DATABASE_MAX_CONNECTIONS",
+            NUMBER,
+            &mut self.database.max_connections,
+        )?;
+        let limits = &mut self.limits;
+        override_with(&env, "QUERY_TIMEOUT_MS", NUMBER, &mut limits.timeout_ms)?;
+        override_with(&env, "QUERY_MAX_FILTERS", NUMBER, &mut limits.max_filters)?;
+        override_with(
+            &env,
+            "QUERY_MAX_VALUE_LENGTH",
+            NUMBER,
+            &mut limits.max_value_length,
+        )?;
+        override_with(&env, "QUERY_PAGE_SIZE", NUMBER, &mut limits.page_size)?;
+        override_with(&env, "QUERY_CACHE_SIZE", NUMBER, &mut self.cache.size)?;
+        override_with(
+            &env,
+            "QUERY_CACHE_TTL_SECS",
+            NUMBER,
+            &mut self.cache.ttl_secs,
+        )?;
+        let rate_limit = &mut self.rate_limit;
+        override_with(
+            &env,
+            "RATE_LIMIT_PER_MINUTE",
+            RATE,
+            &mut rate_limit.per_minute,
+        )?;
+        override_with(&env, "RATE_LIMIT_BURST", RATE, &mut rate_limit.burst)?;
+        override_with(
+            &env,
+            "RATE_LIMIT_EXPORT_PER_MINUTE",
+            RATE,
+            &mut rate_limit.export_per_minute,
+        )?;
+        override_with(
+            &env,
+            "RATE_LIMIT_EXPORT_BURST",
+            RATE,
+            &mut rate_limit.export_burst,
+        )?;
+        if let Some(value) = env("RATE_LIMIT_TRUSTED_PROXIES") {
+            rate_limit.trusted_proxies = value
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| ConfigError::Env {
+                    name: "RATE_LIMIT_TRUSTED_PROXIES",
+                    value,
+                    expected: "IP addresses separated by commas",
+                })?;
+        }
+        if let Some(path) = env("IMPORT_PATH") {
+            self.import.path = Some(path.into());
+        }
+        if let Some(url) = env("AUTH_DATABASE_URL") {
+            self.auth.database_url = Some(url);
+        }
+        if let Some(issuer) = env("OIDC_ISSUER") {
+            self.auth.oidc.get_or_insert_with(Default::default).issuer = issuer;
+        }
+        if let Some(oidc) = &mut self.auth.oidc {
+            let vars = [
+                ("OIDC_CLIENT_ID", &mut oidc.client_id),
+                ("OIDC_CLIENT_SECRET", &mut oidc.client_secret),
+                ("OIDC_REDIRECT_URL", &mut oidc.redirect_url),
+            ];
+            for (name, setting) in vars {
+                if let Some(value) = env(name) {
+                    *setting = value;
+                }
+            }
+        }
+        override_bool(&env, "SQL_CONSOLE", &mut self.features.sql_console)?;
+        override_bool(&env, "OPEN_ADMIN", &mut self.features.open_admin)?;
+        Ok(())
+    }
+
+    /// Checks the settings make sense together.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.database.url.is_empty() {
+            return Err(invalid(
+                "database.url",
+                "must be set, in the configuration file or DATABASE_URL",
+            ));
+        }
+        if Dialect::from_url(&self.database.url).is_none() {
+            return Err(invalid(
+                "database.url",
+                "must start with sqlite:, postgres: or postgresql:",
+            ));
+        }
+        if self.database.max_connections == 0 {
+            return Err(invalid("database.max_connections", "must be at least 1"));
+        }
+
+        let limits = &self.limits;
+        if limits.timeout_ms == 0 {
+            return Err(invalid("limits.timeout_ms", "must be more than 0"));
+        }
+        if limits.max_filters == 0 {
+            return Err(invalid("limits.max_filters", "must be at least 1"));
+        }
+        if limits.max_value_length == 0 {
+            return Err(invalid("limits.max_value_length", "must be at least 1"));
+        }
+        if !(1..=1000).contains(&limits.page_size) {
+            return Err(invalid("limits.page_size", "must be from 1 to 1000"));
+        }
+        if self.cache.ttl_secs == 0 {
+            return Err(invalid("cache.ttl_secs", "must be more than 0"));
+        }
+
+        let rate_limit = &self.rate_limit;
+        let rates = [
+            ("rate_limit.per_minute", rate_limit.per_minute),
+            ("rate_limit.export_per_minute", rate_limit.export_per_minute),
+        ];
+        for (setting, rate) in rates {
+            if !(rate >= 0.0 && rate.is_finite()) {
+                return Err(invalid(setting, "must be 0 or more"));
+            }
+        }
+        let bursts = [
+            ("rate_limit.burst", rate_limit.burst),
+            ("rate_limit.export_burst", rate_limit.export_burst),
+        ];
+        for (setting, burst) in bursts {
+            if !(burst >= 1.0 && burst.is_finite()) {
+                return Err(invalid(setting, "must be at least 1"));
+            }
+        }
+
+        if let Some(url) = &self.auth.database_url {
+            if Dialect::from_url(url) != Some(Dialect::Sqlite) {
+                return Err(invalid("auth.database_url", "must start with sqlite:"));
+            }
+            // Users and their sessions stay out of the dataset, which the SQL
+            // console can read, however its file is named.
+            let file = sqlite_file(url);
+            if url == &self.database.url
+                || file.is_some() && file == sqlite_file(&self.database.url)
+            {
+                return Err(invalid(
+                    "auth.database_url",
+                    "must be a different database from database.url",
+                ));
+            }
+        }
+        if self.features.open_admin && self.auth.database_url.is_some() {
+            return Err(invalid(
+                "features.open_admin",
+                "only applies with sign-in off, without auth.database_url",
+            ));
+        }
+        if let Some(oidc) = &self.auth.oidc {
+            if self.auth.database_url.is_none() {
+                return Err(invalid(
+                    "auth.oidc",
+                    "needs auth.database_url to keep its users in",
+                ));
+            }
+            let settings = [
+                ("auth.oidc.issuer", &oidc.issuer),
+                ("auth.oidc.client_id", &oidc.client_id),
+                ("auth.oidc.client_secret", &oidc.client_secret),
+                ("auth.oidc.redirect_url", &oidc.redirect_url),
+            ];
+            for (setting, value) in settings {
+                if value.is_empty() {
+                    return Err(invalid(
+                        setting,
+                        "must be set to sign in through a provider",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the configuration file, if there is one, applies the
+    /// environment and validates the result.
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = env::var_os("CONFIG_FILE").map(PathBuf::from);
+        let mut config = match path {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(toml) => Config::parse(path, &toml)?,
+                Err(error) => return Err(ConfigError::Read(path, error)),
+            },
+            // The default file is optional; everything can come from the
+            // environment.
+            None => match fs::read_to_string(DEFAULT_FILE) {
+                Ok(toml) => Config::parse(DEFAULT_FILE.into(), &toml)?,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Config::default(),
+                Err(error) => return Err(ConfigError::Read(DEFAULT_FILE.into(), error)),
+            },
+        };
+        config.override_from(|name| env::var(name).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// The file a `sqlite:` URL opens, absolute and with links resolved as far
+/// as it exists, so every spelling of one file compares equal. `None` for
+/// other schemes and in-memory databases.
+fn sqlite_file(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("sqlite:")?;
+    let path = path.strip_prefix("//").unwrap_or(path);
+    let path = path.split('?').next().unwrap_or_default();
+    if path.is_empty() || path == ":memory:" {
+        return None;
+    }
+    let path = std::path::absolute(path).ok()?;
+    if let Ok(file) = path.canonicalize() {
+        return Some(file);
+    }
+    // A file that doesn't exist yet, in a directory that may.
+    let directory = path.parent()?.canonicalize().ok()?;
+    Some(directory.join(path.file_name()?))
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Loads the configuration for the rest of the process. `main` calls this
+/// first, so a bad configuration stops the server before it starts.
+pub fn init() -> Result<&'static Config, ConfigError> {
+    if let Some(config) = CONFIG.get() {
+        return Ok(config);
+    }
+    let config = Config::load()?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// The configuration, loaded by [`init`].
+///
+/// # Panics
+///
+/// If the configuration hasn't been loaded and doesn't load now.
+pub fn config() -> &'static Config {
+    init().unwrap_or_else(|error| panic!("invalid configuration: {error}"))
+}
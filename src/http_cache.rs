@@ -0,0 +1,279 @@
+//! HTTP caching: conditional requests for query results, and cache headers
+//! for the bundle and assets.
+//!
+//! A query's result only changes when the dataset does, so its ETag is the
+//! dataset version and the request URL, and its Last-Modified the time the
+//! dataset last changed. A browser revalidating a result it already has gets
+//! `304 Not Modified` without the query running again.
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{
+            CacheControl, CacheDirective, ETag, EntityTag, HeaderMap, IfModifiedSince, IfNoneMatch,
+            LastModified, TryIntoHeaderPair,
+        },
+        Method,
+    },
+    HttpMessage, HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+use std::{
+    collections::hash_map::DefaultHasher,
+    future::{ready, Ready},
+    hash::{Hash, Hasher},
+    path::Path,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Read-only query server functions, by prefix. Server functions are routed
+/// by name, so a prefix covers any suffix leptos adds.
+const QUERY_PATHS: [&str; 7] = [
+    "/api/query_db",
+    "/api/query_stats",
+    "/api/query_plot",
+    "/api/query_timeline",
+    "/api/query_systems",
+    "/api/find_similar_planets",
+    "/api/get_planet",
+];
+
+/// The query whose new searches go in the signed-in user's history.
+const HISTORY_PATH: &str = "/api/query_db";
+
+/// How long browsers keep bundle files under a versioned path.
+const IMMUTABLE_MAX_AGE: u32 = 365 * 24 * 60 * 60;
+
+/// A hash of the bundle's contents, set at startup by [`version_bundle`].
+static BUNDLE_VERSION: OnceLock<String> = OnceLock::new();
+
+/// Bumped whenever the dataset changes.
+static CHANGES: AtomicU64 = AtomicU64::new(0);
+/// When the dataset last changed, in seconds since the Unix epoch. Zero
+/// until the first change, which stands for the server starting.
+static CHANGED_AT: AtomicU64 = AtomicU64::new(0);
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// When the server started, taken the first time it's needed: at startup,
+/// from `main`.
+pub fn started_at() -> u64 {
+    static STARTED_AT: OnceLock<u64> = OnceLock::new();
+    *STARTED_AT.get_or_init(now_secs)
+}
+
+/// Marks the dataset as changed, so results cached by browsers are fetched
+/// again.
+pub fn data_changed() {
+    CHANGES.fetch_add(1, Ordering::Relaxed);
+    CHANGED_AT.store(now_secs(), Ordering::Relaxed);
+}
+
+/// The dataset version: the server's start and the changes since, as the
+/// data may have changed while it was down.
+pub fn data_version() -> (u64, u64) {
+    (started_at(), CHANGES.load(Ordering::Relaxed))
+}
+
+/// When the dataset last changed, to the second.
+pub fn data_modified() -> SystemTime {
+    let secs = match CHANGED_AT.load(Ordering::Relaxed) {
+        0 => started_at(),
+        secs => secs,
+    };
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// The ETag of the result for a request to `uri` with the current data. Weak,
+/// as compression changes the bytes but not what they mean.
+pub fn query_etag(uri: &str) -> EntityTag {
+    let mut hasher = DefaultHasher::new();
+    data_version().hash(&mut hasher);
+    uri.hash(&mut hasher);
+    EntityTag::new_weak(format!("{:016x}", hasher.finish()))
+}
+
+/// Hashes the names and contents of the bundle files in `pkg_dir`, so the
+/// bundle's URLs change whenever a build does. Leptos 0.5's `cargo leptos`
+/// can't hash the file names itself.
+pub fn version_bundle(pkg_dir: &Path) -> std::io::Result<&'static str> {
+    let mut files = std::fs::read_dir(pkg_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.sort();
+    let mut hasher = DefaultHasher::new();
+    for file in files.iter().filter(|file| file.is_file()) {
+        file.file_name().hash(&mut hasher);
+        std::fs::read(file)?.hash(&mut hasher);
+    }
+    Ok(BUNDLE_VERSION.get_or_init(|| format!("{:016x}", hasher.finish())))
+}
+
+/// The site-relative directory the bundle is served from: `pkg` under its
+/// version once [`version_bundle`] has run, or plain `pkg` before.
+pub fn bundle_dir() -> String {
+    match BUNDLE_VERSION.get() {
+        Some(version) => format!("pkg/{version}"),
+        None => "pkg".to_string(),
+    }
+}
+
+/// How long browsers may keep a bundle or asset file: for good under the
+/// bundle's versioned path, whose contents never change, and otherwise only
+/// after checking it's current.
+pub fn static_cache_control(path: &str) -> CacheControl {
+    let versioned = BUNDLE_VERSION.get().is_some_and(|_| {
+        path.strip_prefix('/')
+            .and_then(|path| path.strip_prefix(&bundle_dir()))
+            .is_some_and(|rest| rest.starts_with('/'))
+    });
+    CacheControl(if versioned {
+        vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(IMMUTABLE_MAX_AGE),
+            CacheDirective::Extension("immutable".to_string(), None),
+        ]
+    } else {
+        vec![CacheDirective::NoCache]
+    })
+}
+
+/// Sets a header on a response that's already been built.
+pub fn insert_header(headers: &mut HeaderMap, header: impl TryIntoHeaderPair) {
+    if let Ok((name, value)) = header.try_into_pair() {
+        headers.insert(name, value);
+    }
+}
+
+/// Middleware answering conditional `GET`s of query results, and tagging
+/// fresh results so browsers can make them.
+#[derive(Clone, Default)]
+pub struct ConditionalQueries;
+
+impl<S, B> Transform<S, ServiceRequest> for ConditionalQueries
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = ConditionalQueriesMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ConditionalQueriesMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct ConditionalQueriesMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ConditionalQueriesMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let conditional = req.method() == Method::GET
+            && QUERY_PATHS
+                .iter()
+                .any(|prefix| req.path().starts_with(prefix));
+
+        Box::pin(async move {
+            if !conditional {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body);
+            }
+
+            let etag = query_etag(&req.uri().to_string());
+            let modified = data_modified();
+            // An ETag, when sent, decides on its own.
+            let current = match req.request().get_header::<IfNoneMatch>() {
+                Some(IfNoneMatch::Any) => true,
+                Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+                None => req
+                    .request()
+                    .get_header::<IfModifiedSince>()
+                    .is_some_and(|IfModifiedSince(since)| SystemTime::from(since) >= modified),
+            };
+            if current {
+                // The visitor still searched, so a new query goes in their
+                // history as if it had run.
+                if req.path().starts_with(HISTORY_PATH) {
+                    crate::api::remember_request(req.request()).await;
+                }
+                let response = HttpResponse::NotModified()
+                    .insert_header(ETag(etag))
+                    .insert_header(LastModified(modified.into()))
+                    .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+                    .finish();
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+
+            let mut response = service.call(req).await?;
+            // Errors, such as timeouts, are worth trying again.
+            if response.status().is_success() {
+                let headers = response.headers_mut();
+                insert_header(headers, ETag(etag));
+                insert_header(headers, LastModified(modified.into()));
+                insert_header(headers, CacheControl(vec![CacheDirective::NoCache]));
+            }
+            Ok(response.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_versioned_bundle_is_immutable() {
+        assert_eq!(
+            static_cache_control("/pkg/exoplanet_query_app.js").to_string(),
+            "no-cache"
+        );
+
+        let pkg = std::env::temp_dir().join(format!("bundle-{}", std::process::id()));
+        std::fs::create_dir_all(&pkg).unwrap();
+        std::fs::write(pkg.join("exoplanet_query_app.js"), "export {}").unwrap();
+        let version = version_bundle(&pkg).unwrap();
+        std::fs::remove_dir_all(&pkg).unwrap();
+
+        assert_eq!(bundle_dir(), format!("pkg/{version}"));
+        assert_eq!(
+            static_cache_control(&format!("/pkg/{version}/exoplanet_query_app.js")).to_string(),
+            "public, max-age=31536000, immutable"
+        );
+        for path in [
+            "/pkg/exoplanet_query_app.js",
+            &format!("/pkg/{version}x/exoplanet_query_app.js"),
+            "/assets/favicon.ico",
+        ] {
+            assert_eq!(static_cache_control(path).to_string(), "no-cache", "{path}");
+        }
+    }
+}
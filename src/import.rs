@@ -0,0 +1,233 @@
+//! Imports a CSV export of the NASA Exoplanet Archive's PS or PSCompPars
+//! table into `exoplanet_data`.
+//!
+//! The table is rebuilt from `columns::COLUMNS` on every import, so adding a
+//! column to the catalog is enough for it to be imported.
+
+use crate::{
+    migrations::migrate,
+    model::{
+        columns::{ColumnKind, COLUMNS},
+        derived::DerivedQuantity,
+        measurement::{Limit, Measurement},
+    },
+    storage::{Bind, Connection, Sql, Storage},
+};
+use leptos::ServerFnError;
+use std::{collections::HashMap, path::Path};
+
+/// Rows inserted per statement, kept low enough to stay under SQLite's and
+/// Postgres' limits on bound parameters.
+const BATCH_SIZE: usize = 100;
+
+const OVERVIEW_URL: &str = "https://exoplanetarchive.ipac.caltech.edu/overview/";
+
+enum Cell {
+    Null,
+    Text(String),
+    Integer(i64),
+    Real(f64),
+}
+
+/// Replaces `exoplanet_data` with the rows of the CSV file at `path`,
+/// returning the number of rows imported. Queries keep reading the old table
+/// until the new one is complete.
+pub async fn import_csv(conn: &mut Connection, path: &Path) -> Result<u64, ServerFnError> {
+    let columns = COLUMNS
+        .iter()
+        .flat_map(|column| column.stored_columns())
+        .collect::<Vec<_>>();
+    let rows = read_rows(path, &columns)?;
+
+    // Rolled back if the import fails or is dropped before it commits.
+    let mut transaction = conn.begin().await?;
+    replace_table(&mut transaction, &columns, &rows).await?;
+    transaction.commit().await?;
+
+    Ok(rows.len() as u64)
+}
+
+/// Builds the new table under another name and swaps it in, inside the
+/// transaction `import_csv` has begun.
+async fn replace_table(
+    conn: &mut impl Storage,
+    columns: &[(String, ColumnKind)],
+    rows: &[Vec<Cell>],
+) -> Result<(), ServerFnError> {
+    let dialect = conn.dialect();
+    conn.execute_script("DROP TABLE IF EXISTS exoplanet_data_import")
+        .await?;
+    conn.execute_script(&dialect.create_table("exoplanet_data_import"))
+        .await?;
+
+    let names = columns
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    for batch in rows.chunks(BATCH_SIZE) {
+        let mut sql = Sql::new(dialect);
+        sql.push(format!(
+            "INSERT INTO exoplanet_data_import ({names}) VALUES "
+        ));
+        for (i, row) in batch.iter().enumerate() {
+            sql.push(if i == 0 { "(" } else { ", (" });
+            for (j, cell) in row.iter().enumerate() {
+                if j > 0 {
+                    sql.push(", ");
+                }
+                push_value(
+                    &mut sql,
+                    match cell {
+                        Cell::Null => None,
+                        Cell::Text(value) => Some(Bind::Text(value.clone())),
+                        Cell::Integer(value) => Some(Bind::Integer(*value)),
+                        Cell::Real(value) => Some(Bind::Real(*value)),
+                    },
+                );
+            }
+            sql.push(")");
+        }
+        conn.execute(&sql).await?;
+    }
+
+    conn.execute_script("DROP TABLE IF EXISTS exoplanet_data")
+        .await?;
+    conn.execute_script("ALTER TABLE exoplanet_data_import RENAME TO exoplanet_data")
+        .await?;
+    // Dropping the old table dropped its indexes too.
+    migrate(conn).await
+}
+
+/// Pushes a value, or a literal `NULL`, which unlike a bound one needs no
+/// type for Postgres to accept it in any column.
+fn push_value(sql: &mut Sql, value: Option<Bind>) {
+    match value {
+        Some(value) => sql.push_bind(value),
+        None => sql.push("NULL"),
+    };
+}
+
+/// Computes every derived quantity from a row's measured columns and fills
+/// in its value, uncertainties and limit flag.
+fn derive(row: &mut [Cell], index: &HashMap<&str, usize>) {
+    for derived in DerivedQuantity::ALL {
+        let measurement = derived.compute_from(|parameter| {
+            let real = |name: &str| match index.get(name).map(|i| &row[*i]) {
+                Some(Cell::Real(value)) => Some(*value),
+                Some(Cell::Integer(value)) => Some(*value as f64),
+                _ => None,
+            };
+            let name = parameter.sql();
+            Measurement::new(
+                real(name),
+                real(&format!("{name}err1")),
+                real(&format!("{name}err2")),
+                Limit::from_flag(real(&format!("{name}lim")).map(|flag| flag as i64)),
+            )
+        });
+        let id = derived.id();
+        let mut set = |name: &str, cell: Cell| {
+            if let Some(i) = index.get(name) {
+                row[*i] = cell;
+            }
+        };
+        let cell = |value: Option<f64>| value.map_or(Cell::Null, Cell::Real);
+        set(id, cell(measurement.value));
+        set(&format!("{id}err1"), cell(measurement.err_upper));
+        set(
+            &format!("{id}err2"),
+            cell(measurement.err_lower.map(|error| -error)),
+        );
+        set(&format!("{id}lim"), Cell::Integer(measurement.limit.flag()));
+    }
+}
+
+/// Reads the CSV file into rows of cells ordered like `columns`.
+fn read_rows(
+    path: &Path,
+    columns: &[(String, ColumnKind)],
+) -> Result<Vec<Vec<Cell>>, ServerFnError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(path)?;
+    let headers = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.to_string(), index))
+        .collect::<HashMap<_, _>>();
+    let index = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    let mut rows = Vec::new();
+    for (line, record) in reader.records().enumerate() {
+        let record = record?;
+        let field = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|index| record.get(*index))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        // The archive links discovery references as HTML anchors.
+        let (disc_refname, disc_refhref) = match field("disc_refname") {
+            Some(reference) => split_reference(reference),
+            None => (None, None),
+        };
+
+        let mut row = Vec::with_capacity(columns.len());
+        for (name, kind) in columns {
+            let raw = match name.as_str() {
+                "disc_refname" => disc_refname.clone(),
+                "disc_refhref" => disc_refhref.clone(),
+                "caltech_href" => field("hostname")
+                    .map(|host| format!("{OVERVIEW_URL}{}", host.replace(' ', "%20"))),
+                // PSCompPars has one row per planet and no default flag.
+                "default_flag" => Some(field(name).unwrap_or("1").to_string()),
+                _ => field(name).map(str::to_string),
+            };
+            row.push(parse_cell(raw, *kind).map_err(|value| {
+                ServerFnError::ServerError(format!(
+                    "row {}: column {name}: '{value}' is not a valid {}",
+                    line + 1,
+                    kind.sql_type().to_lowercase()
+                ))
+            })?);
+        }
+        derive(&mut row, &index);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+fn parse_cell(raw: Option<String>, kind: ColumnKind) -> Result<Cell, String> {
+    let Some(raw) = raw else {
+        return Ok(Cell::Null);
+    };
+    match kind {
+        ColumnKind::Text => Ok(Cell::Text(raw)),
+        ColumnKind::Integer | ColumnKind::Flag => raw.parse().map(Cell::Integer).map_err(|_| raw),
+        ColumnKind::Real => raw.parse().map(Cell::Real).map_err(|_| raw),
+    }
+}
+
+/// Splits an anchor such as `<a refstr=X href=https://... target=ref>Smith et
+/// al. 2020</a>` into its text and link. Plain text is returned unchanged.
+fn split_reference(reference: &str) -> (Option<String>, Option<String>) {
+    let href = reference.split_once("href=").and_then(|(_, rest)| {
+        rest.split(|c: char| c.is_whitespace() || c == '>')
+            .next()
+            .map(|href| href.trim_matches('"').to_string())
+    });
+    let text = match (reference.find('>'), reference.rfind("</a>")) {
+        (Some(start), Some(end)) if start < end => reference[start + 1..end].trim(),
+        _ => reference,
+    };
+    (Some(text.to_string()), href)
+}
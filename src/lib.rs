@@ -1,7 +1,27 @@
 pub mod api;
 pub mod app;
+#[cfg(feature = "ssr")]
+pub mod auth;
+#[cfg(feature = "ssr")]
+pub mod cache;
 pub mod components;
+#[cfg(feature = "ssr")]
+pub mod config;
+#[cfg(feature = "ssr")]
+pub mod http_cache;
+#[cfg(feature = "ssr")]
+pub mod import;
+#[cfg(feature = "ssr")]
+pub mod migrations;
 pub mod model;
+#[cfg(feature = "ssr")]
+pub mod plot_image;
+#[cfg(feature = "ssr")]
+pub mod rate_limit;
+#[cfg(any(feature = "ssr", feature = "csr", feature = "hydrate"))]
+pub mod snapshot;
+#[cfg(feature = "ssr")]
+pub mod storage;
 use cfg_if::cfg_if;
 
 cfg_if! {
@@ -2,12 +2,117 @@
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     use actix_files::Files;
-    use actix_web::*;
+    use actix_web::{dev::Service, *};
+    use exoplanet_query_app::api::{open_storage, storage};
     use exoplanet_query_app::app::*;
+    use exoplanet_query_app::auth::{identity_provider, oidc_callback, oidc_login, RoleGuard};
+    use exoplanet_query_app::http_cache::{
+        bundle_dir, insert_header, started_at, static_cache_control, version_bundle,
+        ConditionalQueries,
+    };
+    use exoplanet_query_app::migrations::migrate;
+    use exoplanet_query_app::model::user::Role;
+    use exoplanet_query_app::plot_image::plot_image;
+    use exoplanet_query_app::rate_limit::{RateLimit, RateLimiter};
+    use exoplanet_query_app::snapshot::snapshot_file;
     use leptos::*;
     use leptos_actix::{generate_route_list, LeptosRoutes};
 
-    let conf = get_configuration(None).await.unwrap();
+    // Everything else reads the configuration, so a bad one stops the server
+    // here with the reason.
+    let config = match exoplanet_query_app::config::init() {
+        Ok(config) => config,
+        Err(error) => {
+            logging::error!("Invalid configuration: {error}");
+            std::process::exit(1);
+        }
+    };
+    let mut conf = get_configuration(None).await.unwrap();
+    // The bundle is served under a hash of its contents so browsers can keep
+    // it for good; the hydration scripts' URLs follow `site_pkg_dir`.
+    let site_pkg = format!("{}/pkg", conf.leptos_options.site_root);
+    match version_bundle(site_pkg.as_ref()) {
+        Ok(_) => conf.leptos_options.site_pkg_dir = bundle_dir(),
+        Err(error) => logging::warn!("Couldn't version the bundle in {site_pkg}: {error}"),
+    }
+    exoplanet_query_app::auth::open_database()
+        .await
+        .expect("couldn't open auth.database_url");
+
+    open_storage(config)
+        .await
+        .expect("couldn't connect to the database");
+    let mut conn = storage().await.expect("couldn't connect to the database");
+    migrate(&mut conn)
+        .await
+        .expect("couldn't migrate the database");
+
+    match &std::env::args().skip(1).collect::<Vec<_>>()[..] {
+        // `snapshot <path>` writes the dataset for the static build and exits.
+        [command, path] if command == "snapshot" => {
+            let rows = exoplanet_query_app::snapshot::write(&mut conn, path.as_ref())
+                .await
+                .expect("couldn't write the snapshot");
+            logging::log!("Wrote {rows} rows to {path}");
+            return Ok(());
+        }
+        // `add-user <username> [role]` adds a local user with the password
+        // read from standard input, and exits. They're a viewer unless given
+        // another role.
+        [command, username, role @ ..] if command == "add-user" && role.len() <= 1 => {
+            use exoplanet_query_app::auth::{db, store::create_user};
+
+            let role = match role.first() {
+                Some(role) => Role::parse(role).expect("the role must be viewer, analyst or admin"),
+                None => Role::Viewer,
+            };
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password)?;
+            let mut users = db().await.expect("couldn't open auth.database_url");
+            create_user(
+                &mut users,
+                username,
+                password.trim_end_matches(['\r', '\n']),
+                role,
+            )
+            .await
+            .expect("couldn't add the user");
+            logging::log!("Added {username} as {role}");
+            return Ok(());
+        }
+        // `set-role <username> <role>` changes a user's role and exits.
+        [command, username, role] if command == "set-role" => {
+            use exoplanet_query_app::auth::{db, store::set_role};
+
+            let role = Role::parse(role).expect("the role must be viewer, analyst or admin");
+            let mut users = db().await.expect("couldn't open auth.database_url");
+            match set_role(&mut users, username, role).await {
+                Ok(Some(_)) => logging::log!("{username} is now {role}"),
+                Ok(None) => logging::error!("There's no user {username}"),
+                Err(error) => logging::error!("{error}"),
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+    #[cfg(feature = "columnar")]
+    {
+        let rows = exoplanet_query_app::storage::columnar::reload(&mut conn)
+            .await
+            .expect("couldn't load the column store");
+        logging::log!("Loaded {rows} rows into the column store");
+    }
+    // Back to the pool for the requests.
+    drop(conn);
+    let identity_provider = identity_provider()
+        .await
+        .expect("couldn't set up the OpenID Connect provider");
+    // Handlers read the configuration from the app data.
+    let config = web::Data::new(config.clone());
+    // One limiter for every worker, so they share the buckets.
+    let rate_limiter = std::sync::Arc::new(RateLimiter::from_config(&config.rate_limit));
+    // Query results' Last-Modified starts from here.
+    started_at();
     let addr = conf.leptos_options.site_addr;
     // Generate the list of routes in your Leptos App
     let routes = generate_route_list(|| view! { <App/> });
@@ -18,7 +123,24 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .route("/api/{tail:.*}", leptos_actix::handle_server_fns())
-            // serve JS/WASM/CSS from `pkg`
+            .service(plot_image)
+            .configure(|config| {
+                // Sign-in through the provider is only routed when it's set up.
+                if let Some(provider) = identity_provider.clone() {
+                    config
+                        .app_data(web::Data::from(provider))
+                        .service(oidc_login)
+                        .service(oidc_callback);
+                }
+            })
+            // registered before `/assets` so the snapshot comes from the database
+            .service(snapshot_file)
+            .service(service_worker)
+            // serve JS/WASM/CSS from `pkg`, under its version and as is
+            .service(Files::new(
+                &format!("/{}", leptos_options.site_pkg_dir),
+                format!("{site_root}/pkg"),
+            ))
             .service(Files::new("/pkg", format!("{site_root}/pkg")))
             // serve other assets from the `assets` directory
             .service(Files::new("/assets", site_root))
@@ -30,7 +152,36 @@ async fn main() -> std::io::Result<()> {
                 || view! { <App/> },
             )
             .app_data(web::Data::new(leptos_options.to_owned()))
-        //.wrap(middleware::Compress::default())
+            .app_data(config.clone())
+            // The pages need these roles; their server functions check too.
+            .wrap(
+                RoleGuard::new()
+                    .path("/admin", Role::Admin)
+                    .path("/admin/sql", Role::Analyst),
+            )
+            // Browsers keep the versioned bundle for good, and check other
+            // bundle files and assets are current before using them.
+            .wrap_fn(|req, srv| {
+                let path = req.path();
+                let cache_control = (path.starts_with("/pkg/") || path.starts_with("/assets/"))
+                    .then(|| static_cache_control(path));
+                let response = srv.call(req);
+                async move {
+                    let mut response = response.await?;
+                    if let Some(cache_control) = cache_control {
+                        let headers = response.headers_mut();
+                        if !headers.contains_key(actix_web::http::header::CACHE_CONTROL) {
+                            insert_header(headers, cache_control);
+                        }
+                    }
+                    Ok(response)
+                }
+            })
+            .wrap(ConditionalQueries)
+            .wrap(RateLimit::new(rate_limiter.clone()))
+            // Outermost, so every response is compressed when the browser
+            // accepts it.
+            .wrap(middleware::Compress::default())
     })
     .bind(&addr)?
     .run()
@@ -49,6 +200,18 @@ async fn favicon(
     ))?)
 }
 
+/// Serves the service worker from the root, as it only controls pages under
+/// its own path.
+#[cfg(feature = "ssr")]
+#[actix_web::get("sw.js")]
+async fn service_worker(
+    leptos_options: actix_web::web::Data<leptos::LeptosOptions>,
+) -> actix_web::Result<actix_files::NamedFile> {
+    let leptos_options = leptos_options.into_inner();
+    let site_root = &leptos_options.site_root;
+    Ok(actix_files::NamedFile::open(format!("{site_root}/sw.js"))?)
+}
+
 #[cfg(not(any(feature = "ssr", feature = "csr")))]
 pub fn main() {
     // no client-side main function
@@ -61,9 +224,10 @@ pub fn main() {
 pub fn main() {
     // a client-side main function is required for using `trunk serve`
     // prefer using `cargo leptos serve` instead
-    // to run: `trunk serve --open --features ssg`
+    // to run: `trunk serve --open`, which builds with the `csr` feature as
+    // configured in index.html
+    use exoplanet_query_app::app::*;
     use leptos::*;
-    use leptos_start::app::*;
     use wasm_bindgen::prelude::wasm_bindgen;
 
     console_error_panic_hook::set_once();
@@ -0,0 +1,112 @@
+//! Schema changes to `exoplanet_data` that aren't part of the column catalog,
+//! such as indexes, and columns the catalog gained since the table was
+//! imported.
+//!
+//! The importer rebuilds the table from scratch, which drops them, so every
+//! migration must be safe to re-run and they're all applied both at startup
+//! and after each import.
+
+use crate::{model::columns::COLUMNS, storage::Storage};
+use leptos::ServerFnError;
+
+/// The migrations in the order they're applied.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "0001_filter_indexes",
+        include_str!("../migrations/0001_filter_indexes.sql"),
+    ),
+    (
+        "0002_measurement_indexes",
+        include_str!("../migrations/0002_measurement_indexes.sql"),
+    ),
+];
+
+/// Applies every migration, or none if the data hasn't been imported yet.
+pub async fn migrate(conn: &mut impl Storage) -> Result<(), ServerFnError> {
+    if !conn.table_exists("exoplanet_data").await? {
+        return Ok(());
+    }
+
+    retype_columns(conn).await?;
+    add_missing_columns(conn).await?;
+    for (name, sql) in MIGRATIONS {
+        conn.execute_script(sql).await.map_err(|error| {
+            ServerFnError::ServerError(format!("migration {name} failed: {error}"))
+        })?;
+    }
+    // Let the planner pick between the indexes with real statistics.
+    conn.execute_script("ANALYZE exoplanet_data").await?;
+
+    Ok(())
+}
+
+/// Rebuilds a table whose columns were declared with other types than the
+/// catalog's, as ones loaded from CSV by the original app are, with every
+/// value as text. `PlanetData` can't decode those, and they sort as strings.
+/// The values are cast into a copy with the catalog's types, empty strings
+/// becoming null, which then replaces the table; columns the catalog doesn't
+/// know are dropped, as an import would.
+async fn retype_columns(conn: &mut impl Storage) -> Result<(), ServerFnError> {
+    let existing = conn.column_types("exoplanet_data").await?;
+    let dialect = conn.dialect();
+    let columns = COLUMNS
+        .iter()
+        .flat_map(|column| column.stored_columns())
+        .filter_map(|(name, kind)| {
+            let (_, declared) = existing.iter().find(|(column, _)| *column == name)?;
+            Some((name, dialect.column_type(kind), declared.as_str()))
+        })
+        .collect::<Vec<_>>();
+    if columns
+        .iter()
+        .all(|(_, column_type, declared)| column_type.eq_ignore_ascii_case(declared))
+    {
+        return Ok(());
+    }
+
+    let names = columns
+        .iter()
+        .map(|(name, _, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let values = columns
+        .iter()
+        .map(|(name, column_type, declared)| match *declared {
+            "text" if *column_type != "TEXT" => {
+                format!("CAST(NULLIF(trim({name}), '') AS {column_type})")
+            }
+            _ => format!("CAST({name} AS {column_type})"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    // The original table stays until its rows have all been copied.
+    conn.execute_script(&format!(
+        "DROP TABLE IF EXISTS exoplanet_data_typed; \
+         {}; \
+         INSERT INTO exoplanet_data_typed (id, {names}) SELECT id, {values} FROM exoplanet_data; \
+         DROP TABLE exoplanet_data; \
+         ALTER TABLE exoplanet_data_typed RENAME TO exoplanet_data",
+        dialect.create_table("exoplanet_data_typed")
+    ))
+    .await
+    .map_err(|error| ServerFnError::ServerError(format!("retyping columns failed: {error}")))?;
+    Ok(())
+}
+
+/// Adds the columns in `COLUMNS` that a table imported by an older version
+/// lacks, so rows still decode as `PlanetData`. They stay null, derived
+/// quantities included, until the next import fills them in.
+async fn add_missing_columns(conn: &mut impl Storage) -> Result<(), ServerFnError> {
+    let existing = conn.table_columns("exoplanet_data").await?;
+    let dialect = conn.dialect();
+    for (name, kind) in COLUMNS.iter().flat_map(|column| column.stored_columns()) {
+        if !existing.contains(&name) {
+            let column_type = dialect.column_type(kind);
+            conn.execute_script(&format!(
+                "ALTER TABLE exoplanet_data ADD COLUMN {name} {column_type}"
+            ))
+            .await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// Counters for the server's record query cache since it started.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Results currently cached.
+    pub entries: u64,
+}
+
+impl CacheMetrics {
+    /// The share of lookups answered from the cache, or `None` before any.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
@@ -0,0 +1,412 @@
+use crate::model::{derived::DerivedQuantity, units::Quantity};
+
+/// How a column's values are stored and compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Text,
+    Integer,
+    Real,
+    /// A 0/1 integer flag.
+    Flag,
+}
+
+impl ColumnKind {
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            ColumnKind::Text => "TEXT",
+            ColumnKind::Integer | ColumnKind::Flag => "INTEGER",
+            ColumnKind::Real => "REAL",
+        }
+    }
+}
+
+/// The section of the detail view a column is shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    Planet,
+    Orbit,
+    Star,
+    System,
+    Discovery,
+    Derived,
+}
+
+impl Group {
+    pub const ALL: [Group; 6] = [
+        Group::Planet,
+        Group::Orbit,
+        Group::Star,
+        Group::System,
+        Group::Discovery,
+        Group::Derived,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Group::Planet => "Planet",
+            Group::Orbit => "Orbit",
+            Group::Star => "Host Star",
+            Group::System => "System",
+            Group::Discovery => "Discovery",
+            Group::Derived => "Derived",
+        }
+    }
+}
+
+/// A column of `exoplanet_data` that can be shown or filtered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    /// The name of the `PlanetData` field, also used by `Input::field`.
+    pub id: &'static str,
+    pub label: &'static str,
+    /// The SQL column the value is read from.
+    pub sql: &'static str,
+    pub kind: ColumnKind,
+    pub group: Group,
+    /// Set for columns the importer computes from other columns.
+    pub derived: Option<DerivedQuantity>,
+    /// The physical quantity of a measurement column, if it has one.
+    pub quantity: Option<Quantity>,
+    /// Whether the column has `err1`/`err2` uncertainty columns alongside it.
+    pub errors: bool,
+    /// Whether the column has a `lim` flag column alongside it.
+    pub limit: bool,
+    /// The id of a column holding a URL the value links to.
+    pub link: Option<&'static str>,
+    /// Whether the column is shown in the summary row of the output table.
+    pub summary: bool,
+    /// Whether the column is only used to support other columns, and so is
+    /// neither shown nor filterable.
+    pub hidden: bool,
+}
+
+impl Column {
+    const fn new(id: &'static str, label: &'static str, group: Group) -> Column {
+        Column {
+            id,
+            label,
+            sql: id,
+            kind: ColumnKind::Text,
+            group,
+            derived: None,
+            quantity: None,
+            errors: false,
+            limit: false,
+            link: None,
+            summary: false,
+            hidden: false,
+        }
+    }
+
+    const fn derived(derived: DerivedQuantity) -> Column {
+        let mut column = Column::new(derived.id(), derived.label(), Group::Derived)
+            .kind(ColumnKind::Real)
+            .quantity(derived.quantity())
+            .measured();
+        column.derived = Some(derived);
+        column
+    }
+
+    const fn sql(mut self, sql: &'static str) -> Column {
+        self.sql = sql;
+        self
+    }
+
+    const fn kind(mut self, kind: ColumnKind) -> Column {
+        self.kind = kind;
+        self
+    }
+
+    const fn quantity(mut self, quantity: Quantity) -> Column {
+        self.quantity = Some(quantity);
+        self
+    }
+
+    const fn errors(mut self) -> Column {
+        self.errors = true;
+        self
+    }
+
+    /// Marks a column as having both uncertainties and a limit flag.
+    const fn measured(mut self) -> Column {
+        self.errors = true;
+        self.limit = true;
+        self
+    }
+
+    const fn link(mut self, link: &'static str) -> Column {
+        self.link = Some(link);
+        self
+    }
+
+    const fn summary(mut self) -> Column {
+        self.summary = true;
+        self
+    }
+
+    const fn hidden(mut self) -> Column {
+        self.hidden = true;
+        self
+    }
+
+    /// The names of the columns stored for this column in `exoplanet_data`:
+    /// the column itself followed by its uncertainty and limit columns.
+    pub fn stored_columns(&self) -> Vec<(String, ColumnKind)> {
+        let mut columns = vec![(self.sql.to_string(), self.kind)];
+        if self.errors {
+            columns.push((format!("{}err1", self.sql), ColumnKind::Real));
+            columns.push((format!("{}err2", self.sql), ColumnKind::Real));
+        }
+        if self.limit {
+            columns.push((format!("{}lim", self.sql), ColumnKind::Integer));
+        }
+        columns
+    }
+}
+
+/// Every column of the NASA Exoplanet Archive's PS/PSCompPars tables the app
+/// knows about, followed by the derived columns. Adding an entry here is
+/// enough for a `PlanetData` field to be imported, filtered on and shown.
+pub const COLUMNS: &[Column] = &[
+    Column::new("pl_name", "Planet Name", Group::Planet)
+        .link("caltech_href")
+        .summary(),
+    Column::new("hostname", "Host Name", Group::Star).summary(),
+    Column::new("pl_letter", "Planet Letter", Group::Planet),
+    Column::new("hd_name", "HD ID", Group::Star),
+    Column::new("hip_name", "HIP ID", Group::Star),
+    Column::new("tic_id", "TIC ID", Group::Star),
+    Column::new("gaia_id", "Gaia ID", Group::Star),
+    Column::new("default_flag", "Default Parameter Set", Group::Planet)
+        .kind(ColumnKind::Flag)
+        .hidden(),
+    Column::new("sy_snum", "Number of Stars", Group::System)
+        .kind(ColumnKind::Integer)
+        .summary(),
+    Column::new("sy_pnum", "Number of Planets", Group::System)
+        .kind(ColumnKind::Integer)
+        .summary(),
+    Column::new("sy_mnum", "Number of Moons", Group::System).kind(ColumnKind::Integer),
+    Column::new("cb_flag", "Circumbinary Flag", Group::System)
+        .kind(ColumnKind::Flag)
+        .summary(),
+    Column::new("discovery_method", "Discovery Method", Group::Discovery)
+        .sql("discoverymethod")
+        .summary(),
+    Column::new("disc_year", "Discovery Year", Group::Discovery)
+        .kind(ColumnKind::Integer)
+        .summary(),
+    Column::new("disc_refname", "Discovery Reference", Group::Discovery)
+        .link("disc_refhref")
+        .summary(),
+    Column::new("disc_refhref", "Discovery Reference Link", Group::Discovery).hidden(),
+    Column::new(
+        "disc_pubdate",
+        "Discovery Publication Date",
+        Group::Discovery,
+    )
+    .summary(),
+    Column::new("disc_locale", "Discovery Locale", Group::Discovery),
+    Column::new("disc_facility", "Discovery Facility", Group::Discovery),
+    Column::new("disc_telescope", "Discovery Telescope", Group::Discovery),
+    Column::new("disc_instrument", "Discovery Instrument", Group::Discovery),
+    Column::new("caltech_href", "Archive Overview Link", Group::Planet).hidden(),
+    Column::new("rv_flag", "Detected by Radial Velocity", Group::Discovery).kind(ColumnKind::Flag),
+    Column::new("pul_flag", "Detected by Pulsar Timing", Group::Discovery).kind(ColumnKind::Flag),
+    Column::new(
+        "ptv_flag",
+        "Detected by Pulsation Timing Variations",
+        Group::Discovery,
+    )
+    .kind(ColumnKind::Flag),
+    Column::new("tran_flag", "Detected by Transits", Group::Discovery).kind(ColumnKind::Flag),
+    Column::new(
+        "ast_flag",
+        "Detected by Astrometric Variations",
+        Group::Discovery,
+    )
+    .kind(ColumnKind::Flag),
+    Column::new(
+        "obm_flag",
+        "Detected by Orbital Brightness Modulations",
+        Group::Discovery,
+    )
+    .kind(ColumnKind::Flag),
+    Column::new("micro_flag", "Detected by Microlensing", Group::Discovery).kind(ColumnKind::Flag),
+    Column::new(
+        "etv_flag",
+        "Detected by Eclipse Timing Variations",
+        Group::Discovery,
+    )
+    .kind(ColumnKind::Flag),
+    Column::new("ima_flag", "Detected by Imaging", Group::Discovery).kind(ColumnKind::Flag),
+    Column::new("dkin_flag", "Detected by Disk Kinematics", Group::Discovery)
+        .kind(ColumnKind::Flag),
+    Column::new("pl_controv_flag", "Controversial Flag", Group::Planet).kind(ColumnKind::Flag),
+    Column::new("pl_orbper", "Orbital Period", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::Period)
+        .measured(),
+    Column::new("pl_orbsmax", "Orbit Semi-Major Axis", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::OrbitalDistance)
+        .measured(),
+    Column::new("pl_rade", "Planet Radius", Group::Planet)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::PlanetRadius)
+        .measured(),
+    Column::new("pl_radj", "Planet Radius [Jupiter Radius]", Group::Planet)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_bmasse", "Planet Mass", Group::Planet)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::PlanetMass)
+        .measured(),
+    Column::new("pl_bmassj", "Planet Mass [Jupiter Mass]", Group::Planet)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new(
+        "pl_bmassprov",
+        "Planet Mass Estimation Formula",
+        Group::Planet,
+    ),
+    Column::new("pl_dens", "Planet Density", Group::Planet)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::Density)
+        .measured(),
+    Column::new("pl_orbeccen", "Eccentricity", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_insol", "Insolation Flux [Earth Flux]", Group::Planet)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_eqt", "Equilibrium Temperature", Group::Planet)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::Temperature)
+        .measured(),
+    Column::new("pl_orbincl", "Inclination [deg]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_tranmid", "Transit Midpoint [BJD]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_imppar", "Impact Parameter", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_trandep", "Transit Depth [%]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_trandur", "Transit Duration [hours]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new(
+        "pl_ratdor",
+        "Ratio of Semi-Major Axis to Stellar Radius",
+        Group::Orbit,
+    )
+    .kind(ColumnKind::Real)
+    .measured(),
+    Column::new(
+        "pl_ratror",
+        "Ratio of Planet to Stellar Radius",
+        Group::Orbit,
+    )
+    .kind(ColumnKind::Real)
+    .measured(),
+    Column::new("pl_orblper", "Argument of Periastron [deg]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_rvamp", "Radial Velocity Amplitude [m/s]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_projobliq", "Projected Obliquity [deg]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("pl_trueobliq", "True Obliquity [deg]", Group::Orbit)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new(
+        "ttv_flag",
+        "Data Show Transit Timing Variations",
+        Group::Orbit,
+    )
+    .kind(ColumnKind::Flag),
+    Column::new("st_spectype", "Spectral Type", Group::Star),
+    Column::new("st_teff", "Stellar Effective Temperature", Group::Star)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::Temperature)
+        .measured(),
+    Column::new("st_rad", "Stellar Radius", Group::Star)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::StellarRadius)
+        .measured(),
+    Column::new("st_mass", "Stellar Mass", Group::Star)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::StellarMass)
+        .measured(),
+    Column::new("st_met", "Stellar Metallicity [dex]", Group::Star)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("st_metratio", "Stellar Metallicity Ratio", Group::Star),
+    Column::new("st_lum", "Stellar Luminosity [log(Solar)]", Group::Star)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new(
+        "st_logg",
+        "Stellar Surface Gravity [log10(cm/s**2)]",
+        Group::Star,
+    )
+    .kind(ColumnKind::Real)
+    .measured(),
+    Column::new("st_age", "Stellar Age [Gyr]", Group::Star)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("st_dens", "Stellar Density", Group::Star)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::Density)
+        .measured(),
+    Column::new("st_vsin", "Stellar Rotational Velocity [km/s]", Group::Star)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("st_rotp", "Stellar Rotational Period [days]", Group::Star)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("st_radv", "Systemic Radial Velocity [km/s]", Group::Star)
+        .kind(ColumnKind::Real)
+        .measured(),
+    Column::new("rastr", "RA [sexagesimal]", Group::System),
+    Column::new("ra", "RA [deg]", Group::System).kind(ColumnKind::Real),
+    Column::new("decstr", "Dec [sexagesimal]", Group::System),
+    Column::new("dec", "Dec [deg]", Group::System).kind(ColumnKind::Real),
+    Column::new("sy_dist", "Distance", Group::System)
+        .kind(ColumnKind::Real)
+        .quantity(Quantity::Distance)
+        .errors(),
+    Column::new("sy_plx", "Parallax [mas]", Group::System)
+        .kind(ColumnKind::Real)
+        .errors(),
+    Column::new("sy_vmag", "V (Johnson) Magnitude", Group::System)
+        .kind(ColumnKind::Real)
+        .errors(),
+    Column::new("sy_kmag", "Ks (2MASS) Magnitude", Group::System)
+        .kind(ColumnKind::Real)
+        .errors(),
+    Column::new("sy_gaiamag", "Gaia Magnitude", Group::System)
+        .kind(ColumnKind::Real)
+        .errors(),
+    Column::new("release_date", "Release Date", Group::Discovery).sql("releasedate"),
+    Column::new(
+        "pl_pubdate",
+        "Planetary Parameter Reference Publication Date",
+        Group::Discovery,
+    ),
+    Column::new("rowupdate", "Date of Last Update", Group::Discovery),
+    Column::derived(DerivedQuantity::Density),
+    Column::derived(DerivedQuantity::SurfaceGravity),
+    Column::derived(DerivedQuantity::SemiMajorAxis),
+    Column::derived(DerivedQuantity::EquilibriumTemperature),
+];
+
+pub fn find_column(id: &str) -> Option<&'static Column> {
+    COLUMNS.iter().find(|column| column.id == id)
+}
@@ -0,0 +1,277 @@
+//! The admin SQL console: checking that a statement is a single read query,
+//! and its results as text.
+
+use crate::model::stats::csv_field;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The most rows a console query returns.
+pub const CONSOLE_ROW_LIMIT: usize = 1000;
+
+/// Keywords that write, change settings or reach outside the database,
+/// rejected anywhere in a console statement. `INTO` catches Postgres'
+/// `SELECT ... INTO`, which creates a table.
+const FORBIDDEN: [&str; 24] = [
+    "ALTER", "ANALYZE", "ATTACH", "CALL", "COPY", "CREATE", "DELETE", "DETACH", "DO", "DROP",
+    "EXECUTE", "GRANT", "INSERT", "INTO", "LISTEN", "LOCK", "NOTIFY", "PRAGMA", "REINDEX",
+    "REPLACE", "REVOKE", "SET", "TRUNCATE", "UPDATE",
+];
+
+/// The columns and rows a console query returned, with every value as text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ConsoleResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Option<String>>>,
+    /// Whether rows past `CONSOLE_ROW_LIMIT` were left out.
+    pub truncated: bool,
+}
+
+impl ConsoleResult {
+    /// Renders the result as CSV, with missing values left empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = self
+            .columns
+            .iter()
+            .map(|column| csv_field(column))
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push('\n');
+        for row in &self.rows {
+            let row = row
+                .iter()
+                .map(|value| csv_field(value.as_deref().unwrap_or("")))
+                .collect::<Vec<_>>();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsoleError {
+    Disabled,
+    Empty,
+    NotSelect,
+    MultipleStatements,
+    Forbidden(String),
+    Unterminated,
+}
+
+impl fmt::Display for ConsoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsoleError::Disabled => write!(f, "the SQL console is disabled"),
+            ConsoleError::Empty => write!(f, "enter a SELECT statement"),
+            ConsoleError::NotSelect => write!(f, "only SELECT statements can be run"),
+            ConsoleError::MultipleStatements => {
+                write!(f, "only one statement can be run at a time")
+            }
+            ConsoleError::Forbidden(keyword) => {
+                write!(f, "'{keyword}' isn't allowed in a read query")
+            }
+            ConsoleError::Unterminated => write!(f, "unterminated string, identifier or comment"),
+        }
+    }
+}
+
+impl std::error::Error for ConsoleError {}
+
+/// Checks that `statement` is a single `SELECT` (or `WITH ... SELECT`)
+/// without keywords that could write, returning it without its trailing
+/// semicolon. Words in strings, quoted identifiers and comments are skipped.
+///
+/// This is a first line of defence, giving clearer errors: the statement
+/// also runs alone in a transaction that's rolled back, on a read-only
+/// connection or in a read-only transaction.
+pub fn check_statement(statement: &str) -> Result<&str, ConsoleError> {
+    let chars = statement.char_indices().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut end = statement.len();
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        match c {
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                // Postgres' `E'...'` strings escape quotes with backslashes.
+                let escapes = c == '\''
+                    && i > 0
+                    && chars[i - 1].1.eq_ignore_ascii_case(&'e')
+                    && !(i > 1 && (chars[i - 2].1.is_alphanumeric() || chars[i - 2].1 == '_'));
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(ConsoleError::Unterminated),
+                        Some((_, '\\')) if escapes => i += 2,
+                        // A doubled quote is an escaped one.
+                        Some((_, q))
+                            if *q == close
+                                && chars.get(i + 1).map(|(_, c)| *c) == Some(close)
+                                && close != ']' =>
+                        {
+                            i += 2
+                        }
+                        Some((_, q)) if *q == close => break,
+                        Some(_) => i += 1,
+                    }
+                }
+                i += 1;
+            }
+            '-' if next == Some('-') => {
+                while i < chars.len() && chars[i].1 != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while !(chars.get(i).map(|(_, c)| *c) == Some('*')
+                    && chars.get(i + 1).map(|(_, c)| *c) == Some('/'))
+                {
+                    if i >= chars.len() {
+                        return Err(ConsoleError::Unterminated);
+                    }
+                    i += 1;
+                }
+                i += 2;
+            }
+            ';' => {
+                // Only whitespace and comments may follow the one statement.
+                let rest = &statement[offset + 1..];
+                if !check_statement(rest).is_err_and(|error| error == ConsoleError::Empty) {
+                    return Err(ConsoleError::MultipleStatements);
+                }
+                end = offset;
+                break;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_') {
+                    i += 1;
+                }
+                let word = chars[start..i].iter().map(|(_, c)| c).collect::<String>();
+                words.push(word.to_ascii_uppercase());
+            }
+            _ => i += 1,
+        }
+    }
+
+    match words.first().map(String::as_str) {
+        None => return Err(ConsoleError::Empty),
+        Some("SELECT" | "WITH") => {}
+        Some(_) => return Err(ConsoleError::NotSelect),
+    }
+    if !words.iter().any(|word| word == "SELECT") {
+        return Err(ConsoleError::NotSelect);
+    }
+    if let Some(word) = words.iter().find(|word| FORBIDDEN.contains(&word.as_str())) {
+        return Err(ConsoleError::Forbidden(word.clone()));
+    }
+    Ok(statement[..end].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_selects_are_accepted() {
+        assert_eq!(check_statement("SELECT 1"), Ok("SELECT 1"));
+        assert_eq!(
+            check_statement("  select pl_name from exoplanet_data ;  "),
+            Ok("select pl_name from exoplanet_data")
+        );
+        assert_eq!(
+            check_statement("WITH t AS (SELECT 1) SELECT * FROM t"),
+            Ok("WITH t AS (SELECT 1) SELECT * FROM t")
+        );
+    }
+
+    #[test]
+    fn other_statements_are_rejected() {
+        assert_eq!(check_statement(""), Err(ConsoleError::Empty));
+        assert_eq!(check_statement("-- nothing"), Err(ConsoleError::Empty));
+        assert_eq!(
+            check_statement("UPDATE exoplanet_data SET pl_rade = 1"),
+            Err(ConsoleError::NotSelect)
+        );
+        assert_eq!(
+            check_statement("EXPLAIN SELECT 1"),
+            Err(ConsoleError::NotSelect)
+        );
+        assert_eq!(
+            check_statement("WITH t AS (VALUES (1)) TABLE t"),
+            Err(ConsoleError::NotSelect)
+        );
+    }
+
+    #[test]
+    fn forbidden_words_are_rejected_anywhere() {
+        assert_eq!(
+            check_statement("SELECT * INTO backup FROM exoplanet_data"),
+            Err(ConsoleError::Forbidden("INTO".to_string()))
+        );
+        assert_eq!(
+            check_statement(
+                "WITH gone AS (delete FROM exoplanet_data RETURNING id) SELECT * FROM gone"
+            ),
+            Err(ConsoleError::Forbidden("DELETE".to_string()))
+        );
+        assert_eq!(
+            check_statement("SELECT 1 AS set"),
+            Err(ConsoleError::Forbidden("SET".to_string()))
+        );
+        // Only whole words count.
+        assert!(check_statement("SELECT updated_at, reset_count FROM t").is_ok());
+    }
+
+    #[test]
+    fn words_in_strings_and_identifiers_are_skipped() {
+        assert!(check_statement("SELECT 'DROP TABLE t; --' AS text").is_ok());
+        assert!(check_statement("SELECT 'it''s; DROP' AS text").is_ok());
+        assert!(check_statement("SELECT \"delete\" FROM t").is_ok());
+        assert!(check_statement("SELECT [insert], `update` FROM t").is_ok());
+        assert!(check_statement("SELECT E'\\'; DROP' AS text").is_ok());
+        assert_eq!(
+            check_statement("SELECT 'open"),
+            Err(ConsoleError::Unterminated)
+        );
+    }
+
+    #[test]
+    fn escape_strings_cannot_hide_a_second_statement() {
+        // In Postgres this is the string `'` followed by a DELETE.
+        assert_eq!(
+            check_statement("SELECT E'\\'' ; DELETE FROM exoplanet_data --'"),
+            Err(ConsoleError::MultipleStatements)
+        );
+        // Without the prefix, a backslash is an ordinary character.
+        assert!(check_statement("SELECT '\\' AS backslash").is_ok());
+        assert!(check_statement("SELECT name'\\' AS backslash FROM t").is_ok());
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        assert!(check_statement("SELECT 1 /* DROP TABLE t; */").is_ok());
+        assert!(check_statement("SELECT 1 -- ; DROP TABLE t").is_ok());
+        assert_eq!(
+            check_statement("SELECT 1 /* open"),
+            Err(ConsoleError::Unterminated)
+        );
+    }
+
+    #[test]
+    fn only_comments_may_follow_the_semicolon() {
+        assert_eq!(check_statement("SELECT 1; -- done"), Ok("SELECT 1"));
+        assert_eq!(check_statement("SELECT 1; /* done */ ;"), Ok("SELECT 1"));
+        assert_eq!(
+            check_statement("SELECT 1; SELECT 2"),
+            Err(ConsoleError::MultipleStatements)
+        );
+        assert_eq!(
+            check_statement("SELECT 1; DELETE FROM exoplanet_data"),
+            Err(ConsoleError::MultipleStatements)
+        );
+    }
+}
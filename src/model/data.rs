@@ -1,10 +1,23 @@
+use crate::model::{
+    explain::QueryPlan,
+    measurement::{Limit, Measurement},
+};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash, Eq)]
+/// The most planets that can be compared side by side.
+pub const MAX_COMPARE: usize = 6;
+/// The most records on one page.
+pub const PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Data {
     pub planet_data: Vec<PlanetData>,
     pub last_id: i64,
     pub first_id: i64,
+    /// How the page was queried, when asked to explain it.
+    #[serde(default)]
+    pub plan: Option<QueryPlan>,
 }
 
 impl Data {
@@ -20,6 +33,7 @@ impl Data {
                     planet_data: data_vec,
                     last_id,
                     first_id,
+                    plan: None,
                 };
                 Some(data)
             } else {
@@ -29,41 +43,345 @@ impl Data {
             None
         }
     }
+
+    /// Builds a page of sorted results starting at row `offset` of the
+    /// result set. Sorted pages can't be anchored on `id`, so `first_id` and
+    /// `last_id` hold the offsets of the page's first row and of the row
+    /// after its last instead.
+    pub fn build_sorted(data_vec: Vec<PlanetData>, offset: i64) -> Option<Data> {
+        if data_vec.is_empty() {
+            return None;
+        }
+        let last_id = offset + data_vec.len() as i64;
+        Some(Data {
+            planet_data: data_vec,
+            last_id,
+            first_id: offset,
+            plan: None,
+        })
+    }
+}
+
+/// A single value of a `PlanetData` field, looked up by name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Value {
+    Null,
+    Text(String),
+    Integer(i64),
+    Real(f64),
+    Bool(bool),
+}
+
+impl Value {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            Value::Bool(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Text(value) => f.write_str(value),
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Real(value) => write!(f, "{value}"),
+            Value::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+trait ToValue {
+    fn to_value(&self) -> Value;
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        Value::Text(self.to_owned())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash, Eq)]
-#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
-pub struct PlanetData {
-    pub pl_name: String,
-    pub hostname: String,
-    pub pl_letter: String,
-    pub sy_snum: String,
-    pub sy_pnum: String,
-    pub cb_flag: u8,
-    pub default_flag: bool,
-
-    #[serde(rename = "discoverymethod")]
-    pub discovery_method: Option<String>,
-
-    pub disc_year: String,
-    pub disc_refname: String,
-    pub disc_pubdate: String,
-    pub disc_refhref: String,
-    pub caltech_href: String,
-    pub disc_telescope: String,
-    pub pl_orbper: Option<String>,
-    pub pl_orbpererr1: Option<String>,
-    pub pl_orbpererr2: Option<String>,
-    pub pl_rade: Option<String>,
-    pub pl_radeerr1: Option<String>,
-    pub pl_radeerr2: Option<String>,
-    pub pl_bmasse: Option<String>,
-    pub pl_bmasseerr1: Option<String>,
-    pub pl_bmasseerr2: Option<String>,
-    pub pl_bmassprov: Option<String>,
-    #[serde(rename = "releasedate")]
-    pub release_date: String,
-    pub st_spectype: Option<String>,
-    pub disc_facility: String,
-    pub id: i64,
+impl ToValue for i64 {
+    fn to_value(&self) -> Value {
+        Value::Integer(*self)
+    }
+}
+
+impl ToValue for f64 {
+    fn to_value(&self) -> Value {
+        Value::Real(*self)
+    }
+}
+
+impl ToValue for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        self.as_ref().map(ToValue::to_value).unwrap_or(Value::Null)
+    }
+}
+
+/// Declares `PlanetData` along with `PlanetData::value`, which looks fields up
+/// by name so views can be driven by the column catalog instead of naming
+/// every field.
+macro_rules! planet_data {
+    (
+        $(#[$struct_meta:meta])*
+        pub struct PlanetData {
+            $(
+                $(#[$field_meta:meta])*
+                pub $field:ident: $ty:ty,
+            )*
+        }
+    ) => {
+        $(#[$struct_meta])*
+        pub struct PlanetData {
+            $(
+                $(#[$field_meta])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl PlanetData {
+            pub fn value(&self, field: &str) -> Option<Value> {
+                match field {
+                    $(stringify!($field) => Some(self.$field.to_value()),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+// Columns follow the NASA Exoplanet Archive's PS and PSCompPars tables. Every
+// measured column `x` comes with `xerr1`/`xerr2` and, for most, an `xlim`
+// flag; new columns also need an entry in `columns::COLUMNS`.
+planet_data! {
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+    pub struct PlanetData {
+        pub pl_name: String,
+        pub hostname: String,
+        pub pl_letter: Option<String>,
+        pub hd_name: Option<String>,
+        pub hip_name: Option<String>,
+        pub tic_id: Option<String>,
+        pub gaia_id: Option<String>,
+        pub default_flag: bool,
+        pub sy_snum: Option<i64>,
+        pub sy_pnum: Option<i64>,
+        pub sy_mnum: Option<i64>,
+        pub cb_flag: Option<i64>,
+        #[serde(rename = "discoverymethod")]
+        pub discovery_method: Option<String>,
+        pub disc_year: Option<i64>,
+        pub disc_refname: Option<String>,
+        pub disc_refhref: Option<String>,
+        pub disc_pubdate: Option<String>,
+        pub disc_locale: Option<String>,
+        pub disc_facility: Option<String>,
+        pub disc_telescope: Option<String>,
+        pub disc_instrument: Option<String>,
+        pub caltech_href: Option<String>,
+        pub rv_flag: Option<i64>,
+        pub pul_flag: Option<i64>,
+        pub ptv_flag: Option<i64>,
+        pub tran_flag: Option<i64>,
+        pub ast_flag: Option<i64>,
+        pub obm_flag: Option<i64>,
+        pub micro_flag: Option<i64>,
+        pub etv_flag: Option<i64>,
+        pub ima_flag: Option<i64>,
+        pub dkin_flag: Option<i64>,
+        pub pl_controv_flag: Option<i64>,
+        pub pl_orbper: Option<f64>,
+        pub pl_orbpererr1: Option<f64>,
+        pub pl_orbpererr2: Option<f64>,
+        pub pl_orbperlim: Option<i64>,
+        pub pl_orbsmax: Option<f64>,
+        pub pl_orbsmaxerr1: Option<f64>,
+        pub pl_orbsmaxerr2: Option<f64>,
+        pub pl_orbsmaxlim: Option<i64>,
+        pub pl_rade: Option<f64>,
+        pub pl_radeerr1: Option<f64>,
+        pub pl_radeerr2: Option<f64>,
+        pub pl_radelim: Option<i64>,
+        pub pl_radj: Option<f64>,
+        pub pl_radjerr1: Option<f64>,
+        pub pl_radjerr2: Option<f64>,
+        pub pl_radjlim: Option<i64>,
+        pub pl_bmasse: Option<f64>,
+        pub pl_bmasseerr1: Option<f64>,
+        pub pl_bmasseerr2: Option<f64>,
+        pub pl_bmasselim: Option<i64>,
+        pub pl_bmassj: Option<f64>,
+        pub pl_bmassjerr1: Option<f64>,
+        pub pl_bmassjerr2: Option<f64>,
+        pub pl_bmassjlim: Option<i64>,
+        pub pl_bmassprov: Option<String>,
+        pub pl_dens: Option<f64>,
+        pub pl_denserr1: Option<f64>,
+        pub pl_denserr2: Option<f64>,
+        pub pl_denslim: Option<i64>,
+        pub pl_orbeccen: Option<f64>,
+        pub pl_orbeccenerr1: Option<f64>,
+        pub pl_orbeccenerr2: Option<f64>,
+        pub pl_orbeccenlim: Option<i64>,
+        pub pl_insol: Option<f64>,
+        pub pl_insolerr1: Option<f64>,
+        pub pl_insolerr2: Option<f64>,
+        pub pl_insollim: Option<i64>,
+        pub pl_eqt: Option<f64>,
+        pub pl_eqterr1: Option<f64>,
+        pub pl_eqterr2: Option<f64>,
+        pub pl_eqtlim: Option<i64>,
+        pub pl_orbincl: Option<f64>,
+        pub pl_orbinclerr1: Option<f64>,
+        pub pl_orbinclerr2: Option<f64>,
+        pub pl_orbincllim: Option<i64>,
+        pub pl_tranmid: Option<f64>,
+        pub pl_tranmiderr1: Option<f64>,
+        pub pl_tranmiderr2: Option<f64>,
+        pub pl_tranmidlim: Option<i64>,
+        pub pl_imppar: Option<f64>,
+        pub pl_impparerr1: Option<f64>,
+        pub pl_impparerr2: Option<f64>,
+        pub pl_impparlim: Option<i64>,
+        pub pl_trandep: Option<f64>,
+        pub pl_trandeperr1: Option<f64>,
+        pub pl_trandeperr2: Option<f64>,
+        pub pl_trandeplim: Option<i64>,
+        pub pl_trandur: Option<f64>,
+        pub pl_trandurerr1: Option<f64>,
+        pub pl_trandurerr2: Option<f64>,
+        pub pl_trandurlim: Option<i64>,
+        pub pl_ratdor: Option<f64>,
+        pub pl_ratdorerr1: Option<f64>,
+        pub pl_ratdorerr2: Option<f64>,
+        pub pl_ratdorlim: Option<i64>,
+        pub pl_ratror: Option<f64>,
+        pub pl_ratrorerr1: Option<f64>,
+        pub pl_ratrorerr2: Option<f64>,
+        pub pl_ratrorlim: Option<i64>,
+        pub pl_orblper: Option<f64>,
+        pub pl_orblpererr1: Option<f64>,
+        pub pl_orblpererr2: Option<f64>,
+        pub pl_orblperlim: Option<i64>,
+        pub pl_rvamp: Option<f64>,
+        pub pl_rvamperr1: Option<f64>,
+        pub pl_rvamperr2: Option<f64>,
+        pub pl_rvamplim: Option<i64>,
+        pub pl_projobliq: Option<f64>,
+        pub pl_projobliqerr1: Option<f64>,
+        pub pl_projobliqerr2: Option<f64>,
+        pub pl_projobliqlim: Option<i64>,
+        pub pl_trueobliq: Option<f64>,
+        pub pl_trueobliqerr1: Option<f64>,
+        pub pl_trueobliqerr2: Option<f64>,
+        pub pl_trueobliqlim: Option<i64>,
+        pub ttv_flag: Option<i64>,
+        pub st_spectype: Option<String>,
+        pub st_teff: Option<f64>,
+        pub st_tefferr1: Option<f64>,
+        pub st_tefferr2: Option<f64>,
+        pub st_tefflim: Option<i64>,
+        pub st_rad: Option<f64>,
+        pub st_raderr1: Option<f64>,
+        pub st_raderr2: Option<f64>,
+        pub st_radlim: Option<i64>,
+        pub st_mass: Option<f64>,
+        pub st_masserr1: Option<f64>,
+        pub st_masserr2: Option<f64>,
+        pub st_masslim: Option<i64>,
+        pub st_met: Option<f64>,
+        pub st_meterr1: Option<f64>,
+        pub st_meterr2: Option<f64>,
+        pub st_metlim: Option<i64>,
+        pub st_metratio: Option<String>,
+        pub st_lum: Option<f64>,
+        pub st_lumerr1: Option<f64>,
+        pub st_lumerr2: Option<f64>,
+        pub st_lumlim: Option<i64>,
+        pub st_logg: Option<f64>,
+        pub st_loggerr1: Option<f64>,
+        pub st_loggerr2: Option<f64>,
+        pub st_logglim: Option<i64>,
+        pub st_age: Option<f64>,
+        pub st_ageerr1: Option<f64>,
+        pub st_ageerr2: Option<f64>,
+        pub st_agelim: Option<i64>,
+        pub st_dens: Option<f64>,
+        pub st_denserr1: Option<f64>,
+        pub st_denserr2: Option<f64>,
+        pub st_denslim: Option<i64>,
+        pub st_vsin: Option<f64>,
+        pub st_vsinerr1: Option<f64>,
+        pub st_vsinerr2: Option<f64>,
+        pub st_vsinlim: Option<i64>,
+        pub st_rotp: Option<f64>,
+        pub st_rotperr1: Option<f64>,
+        pub st_rotperr2: Option<f64>,
+        pub st_rotplim: Option<i64>,
+        pub st_radv: Option<f64>,
+        pub st_radverr1: Option<f64>,
+        pub st_radverr2: Option<f64>,
+        pub st_radvlim: Option<i64>,
+        pub rastr: Option<String>,
+        pub ra: Option<f64>,
+        pub decstr: Option<String>,
+        pub dec: Option<f64>,
+        pub sy_dist: Option<f64>,
+        pub sy_disterr1: Option<f64>,
+        pub sy_disterr2: Option<f64>,
+        pub sy_plx: Option<f64>,
+        pub sy_plxerr1: Option<f64>,
+        pub sy_plxerr2: Option<f64>,
+        pub sy_vmag: Option<f64>,
+        pub sy_vmagerr1: Option<f64>,
+        pub sy_vmagerr2: Option<f64>,
+        pub sy_kmag: Option<f64>,
+        pub sy_kmagerr1: Option<f64>,
+        pub sy_kmagerr2: Option<f64>,
+        pub sy_gaiamag: Option<f64>,
+        pub sy_gaiamagerr1: Option<f64>,
+        pub sy_gaiamagerr2: Option<f64>,
+        #[serde(rename = "releasedate")]
+        pub release_date: Option<String>,
+        pub pl_pubdate: Option<String>,
+        pub rowupdate: Option<String>,
+        pub id: i64,
+    }
+}
+
+impl PlanetData {
+    /// The measurement stored in column `field` and its `err1`, `err2` and
+    /// `lim` companions.
+    pub fn measurement(&self, field: &str) -> Measurement {
+        let companion = |suffix: &str| self.value(&format!("{field}{suffix}"));
+        Measurement::new(
+            self.value(field).and_then(|value| value.as_f64()),
+            companion("err1").and_then(|value| value.as_f64()),
+            companion("err2").and_then(|value| value.as_f64()),
+            Limit::from_flag(companion("lim").and_then(|value| value.as_i64())),
+        )
+    }
 }
@@ -0,0 +1,317 @@
+use crate::model::{
+    data::PlanetData,
+    measurement::{Limit, Measurement},
+    units::{Quantity, AU_PER_SOLAR_RADIUS, DAYS_PER_YEAR},
+};
+use serde::{Deserialize, Serialize};
+
+/// Mean density of the Earth in g/cm³.
+const EARTH_DENSITY: f64 = 5.514;
+/// Surface gravity of the Earth in m/s².
+const EARTH_GRAVITY: f64 = 9.807;
+
+/// A measured column a derived quantity is computed from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    PlanetRadius,
+    PlanetMass,
+    OrbitalPeriod,
+    StellarTemperature,
+    StellarRadius,
+    StellarMass,
+}
+
+impl Parameter {
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Parameter::PlanetRadius => "pl_rade",
+            Parameter::PlanetMass => "pl_bmasse",
+            Parameter::OrbitalPeriod => "pl_orbper",
+            Parameter::StellarTemperature => "st_teff",
+            Parameter::StellarRadius => "st_rad",
+            Parameter::StellarMass => "st_mass",
+        }
+    }
+
+    pub fn measurement(&self, data: &PlanetData) -> Measurement {
+        data.measurement(self.sql())
+    }
+}
+
+/// A quantity computed from measured columns rather than stored in
+/// `exoplanet_data`.
+///
+/// Every derived quantity is a power law `constant * Π parameter^exponent` of
+/// independent measurements, so uncertainties propagate in quadrature on the
+/// relative errors. The importer stores each quantity in a column named by
+/// its id so it can be filtered and sorted on like any other.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum DerivedQuantity {
+    Density,
+    SurfaceGravity,
+    SemiMajorAxis,
+    EquilibriumTemperature,
+}
+
+impl DerivedQuantity {
+    pub const ALL: [DerivedQuantity; 4] = [
+        DerivedQuantity::Density,
+        DerivedQuantity::SurfaceGravity,
+        DerivedQuantity::SemiMajorAxis,
+        DerivedQuantity::EquilibriumTemperature,
+    ];
+
+    pub const fn id(&self) -> &'static str {
+        match self {
+            DerivedQuantity::Density => "derived_density",
+            DerivedQuantity::SurfaceGravity => "derived_gravity",
+            DerivedQuantity::SemiMajorAxis => "derived_orbsmax",
+            DerivedQuantity::EquilibriumTemperature => "derived_eqt",
+        }
+    }
+
+    pub const fn label(&self) -> &'static str {
+        match self {
+            DerivedQuantity::Density => "Planet Density (derived)",
+            DerivedQuantity::SurfaceGravity => "Surface Gravity (derived)",
+            DerivedQuantity::SemiMajorAxis => "Orbit Semi-Major Axis (derived)",
+            DerivedQuantity::EquilibriumTemperature => "Equilibrium Temperature (derived)",
+        }
+    }
+
+    pub const fn quantity(&self) -> Quantity {
+        match self {
+            DerivedQuantity::Density => Quantity::Density,
+            DerivedQuantity::SurfaceGravity => Quantity::SurfaceGravity,
+            DerivedQuantity::SemiMajorAxis => Quantity::OrbitalDistance,
+            DerivedQuantity::EquilibriumTemperature => Quantity::Temperature,
+        }
+    }
+
+    /// The constant and the `(parameter, exponent)` terms of the power law.
+    fn law(&self) -> (f64, &'static [(Parameter, f64)]) {
+        match self {
+            // ρ = ρ⊕ M / R³
+            DerivedQuantity::Density => (
+                EARTH_DENSITY,
+                &[
+                    (Parameter::PlanetMass, 1.0),
+                    (Parameter::PlanetRadius, -3.0),
+                ],
+            ),
+            // g = g⊕ M / R²
+            DerivedQuantity::SurfaceGravity => (
+                EARTH_GRAVITY,
+                &[
+                    (Parameter::PlanetMass, 1.0),
+                    (Parameter::PlanetRadius, -2.0),
+                ],
+            ),
+            // Kepler's third law: a³ = M* P², with a in AU, M* in solar masses
+            // and P in years.
+            DerivedQuantity::SemiMajorAxis => (
+                DAYS_PER_YEAR.powf(-2.0 / 3.0),
+                &[
+                    (Parameter::StellarMass, 1.0 / 3.0),
+                    (Parameter::OrbitalPeriod, 2.0 / 3.0),
+                ],
+            ),
+            // Teq = Teff √(R* / 2a) for zero albedo and full redistribution,
+            // with a substituted from Kepler's third law.
+            DerivedQuantity::EquilibriumTemperature => (
+                (AU_PER_SOLAR_RADIUS / 2.0).sqrt() * DAYS_PER_YEAR.powf(1.0 / 3.0),
+                &[
+                    (Parameter::StellarTemperature, 1.0),
+                    (Parameter::StellarRadius, 0.5),
+                    (Parameter::StellarMass, -1.0 / 6.0),
+                    (Parameter::OrbitalPeriod, -1.0 / 3.0),
+                ],
+            ),
+        }
+    }
+
+    /// Computes the quantity for a planet, propagating the `err1`/`err2`
+    /// uncertainties of its inputs to first order.
+    ///
+    /// The result is a limit when every limited input pushes it the same way,
+    /// and has no value when inputs are missing or limits conflict.
+    pub fn compute(&self, data: &PlanetData) -> Measurement {
+        self.compute_from(|parameter| parameter.measurement(data))
+    }
+
+    /// Computes the quantity from the measurements `measurement` gives for
+    /// its parameters, as [`DerivedQuantity::compute`] does for a planet.
+    pub fn compute_from(&self, measurement: impl Fn(Parameter) -> Measurement) -> Measurement {
+        let (constant, terms) = self.law();
+
+        let mut value = constant;
+        let mut upper_sq = Some(0.0);
+        let mut lower_sq = Some(0.0);
+        let mut limit = Limit::None;
+
+        for (parameter, exponent) in terms {
+            let measurement = measurement(*parameter);
+            let Some(input) = measurement.value.filter(|v| *v > 0.0) else {
+                return Measurement::default();
+            };
+            value *= input.powf(*exponent);
+
+            // A positive exponent carries the input's upper error to the
+            // result's upper error; a negative one swaps them.
+            let (raises, lowers) = if *exponent > 0.0 {
+                (measurement.err_upper, measurement.err_lower)
+            } else {
+                (measurement.err_lower, measurement.err_upper)
+            };
+            let add = |sum: Option<f64>, error: Option<f64>| {
+                Some(sum? + (exponent * error? / input).powi(2))
+            };
+            upper_sq = add(upper_sq, raises);
+            lower_sq = add(lower_sq, lowers);
+
+            let term_limit = match (measurement.limit, *exponent > 0.0) {
+                (Limit::None, _) => Limit::None,
+                (Limit::Upper, true) | (Limit::Lower, false) => Limit::Upper,
+                (Limit::Lower, true) | (Limit::Upper, false) => Limit::Lower,
+            };
+            limit = match (limit, term_limit) {
+                (limit, Limit::None) => limit,
+                (Limit::None, term_limit) => term_limit,
+                (limit, term_limit) if limit == term_limit => limit,
+                _ => return Measurement::default(),
+            };
+        }
+
+        Measurement {
+            value: Some(value),
+            err_upper: upper_sq.map(|sq| value * sq.sqrt()),
+            err_lower: lower_sq.map(|sq| value * sq.sqrt()),
+            limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A planet with just the given columns set.
+    fn planet(columns: serde_json::Value) -> PlanetData {
+        let mut planet = json!({
+            "pl_name": "Test b",
+            "hostname": "Test",
+            "default_flag": true,
+            "id": 1,
+        });
+        planet
+            .as_object_mut()
+            .unwrap()
+            .extend(columns.as_object().unwrap().clone());
+        serde_json::from_value(planet).unwrap()
+    }
+
+    fn value(quantity: DerivedQuantity, columns: serde_json::Value) -> f64 {
+        quantity.compute(&planet(columns)).value.unwrap()
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance
+    }
+
+    #[test]
+    fn the_earth_has_earth_density_and_gravity() {
+        let earth = json!({ "pl_bmasse": 1.0, "pl_rade": 1.0 });
+        assert!(close(
+            value(DerivedQuantity::Density, earth.clone()),
+            5.514,
+            1e-9
+        ));
+        assert!(close(
+            value(DerivedQuantity::SurfaceGravity, earth),
+            9.807,
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn density_and_gravity_scale_with_mass_and_radius() {
+        // Eight times the mass in twice the radius: the same density and
+        // twice the gravity.
+        let planet = json!({ "pl_bmasse": 8.0, "pl_rade": 2.0 });
+        assert!(close(
+            value(DerivedQuantity::Density, planet.clone()),
+            5.514,
+            1e-9
+        ));
+        assert!(close(
+            value(DerivedQuantity::SurfaceGravity, planet),
+            19.614,
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn a_year_around_the_sun_is_one_au() {
+        let earth = json!({ "st_mass": 1.0, "pl_orbper": DAYS_PER_YEAR });
+        assert!(close(
+            value(DerivedQuantity::SemiMajorAxis, earth),
+            1.0,
+            1e-9
+        ));
+        // Jupiter's period is 11.86 years at 5.2 AU.
+        let jupiter = json!({ "st_mass": 1.0, "pl_orbper": 4332.59 });
+        assert!(close(
+            value(DerivedQuantity::SemiMajorAxis, jupiter),
+            5.2,
+            0.01
+        ));
+    }
+
+    #[test]
+    fn the_earth_is_at_about_278_kelvin() {
+        let earth = json!({
+            "st_teff": 5772.0,
+            "st_rad": 1.0,
+            "st_mass": 1.0,
+            "pl_orbper": DAYS_PER_YEAR,
+        });
+        assert!(close(
+            value(DerivedQuantity::EquilibriumTemperature, earth),
+            278.3,
+            0.1
+        ));
+    }
+
+    #[test]
+    fn relative_errors_add_in_quadrature() {
+        let density = DerivedQuantity::Density.compute(&planet(json!({
+            "pl_bmasse": 1.0, "pl_bmasseerr1": 0.1, "pl_bmasseerr2": -0.1,
+            "pl_rade": 1.0, "pl_radeerr1": 0.1, "pl_radeerr2": -0.2,
+        })));
+        // A larger radius lowers the density, so the radius' lower error
+        // raises it.
+        let upper = 5.514 * (0.1f64.powi(2) + 0.6f64.powi(2)).sqrt();
+        let lower = 5.514 * (0.1f64.powi(2) + 0.3f64.powi(2)).sqrt();
+        assert!(close(density.err_upper.unwrap(), upper, 1e-9));
+        assert!(close(density.err_lower.unwrap(), lower, 1e-9));
+        assert_eq!(density.limit, Limit::None);
+    }
+
+    #[test]
+    fn limits_carry_through_or_conflict() {
+        let density = |columns| DerivedQuantity::Density.compute(&planet(columns));
+        let upper_mass = density(json!({ "pl_bmasse": 1.0, "pl_bmasselim": 1, "pl_rade": 1.0 }));
+        assert_eq!(upper_mass.limit, Limit::Upper);
+        let lower_radius = density(json!({ "pl_bmasse": 1.0, "pl_rade": 1.0, "pl_radelim": -1 }));
+        assert_eq!(lower_radius.limit, Limit::Upper);
+        let upper_radius = density(json!({ "pl_bmasse": 1.0, "pl_rade": 1.0, "pl_radelim": 1 }));
+        assert_eq!(upper_radius.limit, Limit::Lower);
+
+        let conflicting = density(json!({
+            "pl_bmasse": 1.0, "pl_bmasselim": 1, "pl_rade": 1.0, "pl_radelim": 1,
+        }));
+        assert_eq!(conflicting, Measurement::default());
+        assert_eq!(density(json!({ "pl_bmasse": 1.0 })), Measurement::default());
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+/// One step of a query plan, shaped like a row of SQLite's `EXPLAIN QUERY
+/// PLAN` output.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct PlanStep {
+    pub id: i64,
+    pub parent: i64,
+    pub detail: String,
+}
+
+/// How the database ran a record query, for checking that filters use an
+/// index.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueryPlan {
+    pub sql: String,
+    pub steps: Vec<PlanStep>,
+    /// Time taken to fetch the page, in milliseconds.
+    pub elapsed_ms: f64,
+}
+
+impl QueryPlan {
+    /// The steps in output order with their depth in the plan tree.
+    pub fn tree(&self) -> Vec<(usize, &PlanStep)> {
+        let mut depths = Vec::<(i64, usize)>::new();
+        self.steps
+            .iter()
+            .map(|step| {
+                let depth = depths
+                    .iter()
+                    .find(|(id, _)| *id == step.parent)
+                    .map_or(0, |(_, depth)| depth + 1);
+                depths.push((step.id, depth));
+                (depth, step)
+            })
+            .collect()
+    }
+
+    /// Whether any step reads the whole table instead of using an index, as
+    /// SQLite's `SCAN table` or Postgres' `Seq Scan`.
+    pub fn full_scan(&self) -> bool {
+        self.steps.iter().any(|step| {
+            (step.detail.starts_with("SCAN") && !step.detail.contains("USING"))
+                || step.detail.starts_with("Seq Scan")
+        })
+    }
+}
@@ -0,0 +1,242 @@
+//! Filters compiled from the query builder's inputs, independent of where
+//! they're evaluated: in SQL by `storage`, or in memory against loaded rows.
+
+use crate::model::{
+    columns::{find_column, Column, ColumnKind},
+    data::{PlanetData, Value},
+    input::{Input, CONSISTENT_OP, IN_OP, RELATIVE_ERROR_OP},
+    measurement::{parse_consistency, parse_percent, Limit, Measurement, ToleranceError},
+    text_query::parse_list,
+    units::{parse_value, UnitError},
+};
+use std::{cmp::Ordering, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessOrEqual,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    pub fn parse(op: &str) -> Option<Comparison> {
+        match op {
+            "=" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            "<" => Some(Comparison::Less),
+            ">" => Some(Comparison::Greater),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            _ => None,
+        }
+    }
+
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::Greater => ">",
+            Comparison::LessOrEqual => "<=",
+            Comparison::GreaterOrEqual => ">=",
+        }
+    }
+
+    pub fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::NotEqual => ordering.is_ne(),
+            Comparison::Less => ordering.is_lt(),
+            Comparison::Greater => ordering.is_gt(),
+            Comparison::LessOrEqual => ordering.is_le(),
+            Comparison::GreaterOrEqual => ordering.is_ge(),
+        }
+    }
+}
+
+/// One validated filter, ANDed with the others of its query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Compare {
+        column: &'static Column,
+        op: Comparison,
+        value: Value,
+    },
+    /// The column equals one of `values`.
+    In {
+        column: &'static Column,
+        values: Vec<Value>,
+    },
+    /// The larger relative uncertainty of the column is below `max`, a
+    /// fraction.
+    RelativeError { column: &'static Column, max: f64 },
+    /// The column's value is within `sigmas` uncertainties of `target`.
+    Consistent {
+        column: &'static Column,
+        target: f64,
+        sigmas: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    UnknownField(String),
+    UnknownOperator(String),
+    NoUncertainties(&'static str),
+    InvalidValue { value: String, label: &'static str },
+    Unit(UnitError),
+    Tolerance(ToleranceError),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::UnknownField(field) => write!(f, "unknown field '{field}'"),
+            FilterError::UnknownOperator(op) => write!(f, "unknown comparison operator '{op}'"),
+            FilterError::NoUncertainties(label) => write!(f, "'{label}' has no uncertainties"),
+            FilterError::InvalidValue { value, label } => {
+                write!(f, "'{value}' is not a valid value for '{label}'")
+            }
+            FilterError::Unit(error) => error.fmt(f),
+            FilterError::Tolerance(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl From<UnitError> for FilterError {
+    fn from(error: UnitError) -> Self {
+        FilterError::Unit(error)
+    }
+}
+
+impl From<ToleranceError> for FilterError {
+    fn from(error: ToleranceError) -> Self {
+        FilterError::Tolerance(error)
+    }
+}
+
+impl Predicate {
+    /// Checks the input's field and operator and parses its value, in the
+    /// column's canonical unit for measurements.
+    pub fn compile(input: Input) -> Result<Predicate, FilterError> {
+        let column = find_column(&input.field)
+            .filter(|column| !column.hidden)
+            .ok_or_else(|| FilterError::UnknownField(input.field.clone()))?;
+
+        match (input.comparison_op.as_str(), column.quantity) {
+            (RELATIVE_ERROR_OP | CONSISTENT_OP, _) if !column.errors => {
+                Err(FilterError::NoUncertainties(column.label))
+            }
+            (RELATIVE_ERROR_OP, _) => Ok(Predicate::RelativeError {
+                column,
+                max: parse_percent(&input.value)?,
+            }),
+            (CONSISTENT_OP, Some(quantity)) => {
+                let (target, sigmas) = parse_consistency(&input.value, quantity)?;
+                Ok(Predicate::Consistent {
+                    column,
+                    target,
+                    sigmas,
+                })
+            }
+            (IN_OP, _) => {
+                let values = parse_list(&input.value).map_err(|_| FilterError::InvalidValue {
+                    value: input.value.clone(),
+                    label: column.label,
+                })?;
+                let values = values
+                    .into_iter()
+                    .map(|value| compile_value(column, value))
+                    .collect::<Result<_, _>>()?;
+                Ok(Predicate::In { column, values })
+            }
+            (op, _) => {
+                let op = Comparison::parse(op)
+                    .ok_or_else(|| FilterError::UnknownOperator(op.to_string()))?;
+                let value = compile_value(column, input.value)?;
+                Ok(Predicate::Compare { column, op, value })
+            }
+        }
+    }
+
+    /// Whether a row passes the filter, reading its fields with `get`. Like
+    /// SQL, any comparison with a missing value fails.
+    pub fn matches(&self, get: impl Fn(&str) -> Value) -> bool {
+        match self {
+            Predicate::Compare { column, op, value } => {
+                compare(&get(column.id), value).is_some_and(|ordering| op.holds(ordering))
+            }
+            Predicate::In { column, values } => {
+                let field = get(column.id);
+                values
+                    .iter()
+                    .any(|value| compare(&field, value).is_some_and(Ordering::is_eq))
+            }
+            Predicate::RelativeError { column, max } => measurement(column, &get)
+                .relative_uncertainty()
+                .is_some_and(|relative| relative < *max),
+            Predicate::Consistent {
+                column,
+                target,
+                sigmas,
+            } => measurement(column, &get).consistent_with(*target, *sigmas),
+        }
+    }
+
+    pub fn matches_planet(&self, planet: &PlanetData) -> bool {
+        self.matches(|field| planet.value(field).unwrap_or(Value::Null))
+    }
+}
+
+/// Parses a value for the column, in its canonical unit for measurements.
+fn compile_value(column: &'static Column, value: String) -> Result<Value, FilterError> {
+    let invalid = || FilterError::InvalidValue {
+        value: value.clone(),
+        label: column.label,
+    };
+    Ok(match (column.quantity, column.kind) {
+        (Some(quantity), _) => Value::Real(parse_value(&value, quantity)?),
+        (None, ColumnKind::Integer | ColumnKind::Flag) => {
+            Value::Integer(value.trim().parse().map_err(|_| invalid())?)
+        }
+        (None, ColumnKind::Real) => Value::Real(value.trim().parse().map_err(|_| invalid())?),
+        (None, ColumnKind::Text) => Value::Text(value),
+    })
+}
+
+/// Orders a field against a filter value, or `None` if either is missing.
+fn compare(field: &Value, value: &Value) -> Option<Ordering> {
+    match (field, value) {
+        (Value::Text(field), Value::Text(value)) => Some(field.cmp(value)),
+        (field, value) => number(field)
+            .zip(number(value))
+            .and_then(|(field, value)| field.partial_cmp(&value)),
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_i64().map(|value| value as f64))
+}
+
+/// A measurement with its uncertainties and, if the column has one, its
+/// limit flag.
+fn measurement(column: &Column, get: &impl Fn(&str) -> Value) -> Measurement {
+    let limit = if column.limit {
+        get(&format!("{}lim", column.sql)).as_i64()
+    } else {
+        None
+    };
+    Measurement::new(
+        number(&get(column.id)),
+        number(&get(&format!("{}err1", column.sql))),
+        number(&get(&format!("{}err2", column.sql))),
+        Limit::from_flag(limit),
+    )
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Query {
     pub inputs: Vec<Input>,
 }
@@ -17,7 +17,16 @@ impl Default for Query {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Filters on the relative uncertainty of a measurement, given in percent.
+pub const RELATIVE_ERROR_OP: &str = "rel_err<";
+/// Filters on a measurement being consistent with a value within a number of
+/// standard deviations, e.g. `1.0, 2σ`.
+pub const CONSISTENT_OP: &str = "consistent";
+/// Filters on a field being one of a list of values, separated by commas
+/// and quoted as in the text syntax, e.g. `Transit, "Radial Velocity"`.
+pub const IN_OP: &str = "in";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Input {
     pub field: String,
     pub comparison_op: String,
@@ -25,19 +34,19 @@ pub struct Input {
 }
 
 impl Input {
-    pub fn new() -> Input {
+    pub fn new(field: &str, comparison_op: &str, value: &str) -> Input {
         Input {
-            field: "".to_string(),
-            comparison_op: "".to_string(),
-            value: "".to_string(),
+            field: field.to_string(),
+            comparison_op: comparison_op.to_string(),
+            value: value.to_string(),
         }
     }
 }
 
-impl Default for Input {
-    fn default() -> Self {
-        Self::new()
-    }
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Sort {
+    pub field: String,
+    pub descending: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
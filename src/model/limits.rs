@@ -0,0 +1,99 @@
+use crate::model::input::Input;
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+/// Starts every timeout message, so the UI can tell timeouts from other
+/// errors once they've been flattened into a `ServerFnError`.
+pub const TIMEOUT_MESSAGE: &str = "query timed out";
+
+/// Starts every rate limit message, for the same reason.
+pub const RATE_LIMIT_MESSAGE: &str = "too many requests";
+
+/// Bounds on the work a single query may ask of the database.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// How long a query may run before the database cancels it.
+    pub timeout: Duration,
+    pub max_filters: usize,
+    /// The longest filter value accepted, in characters.
+    pub max_value_length: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            timeout: Duration::from_secs(5),
+            max_filters: 20,
+            max_value_length: 200,
+        }
+    }
+}
+
+impl QueryLimits {
+    pub fn check(&self, query: &[Input]) -> Result<(), LimitError> {
+        if query.len() > self.max_filters {
+            return Err(LimitError::TooManyFilters(self.max_filters));
+        }
+        match query
+            .iter()
+            .find(|input| input.value.chars().count() > self.max_value_length)
+        {
+            Some(input) => Err(LimitError::ValueTooLong {
+                field: input.field.clone(),
+                max: self.max_value_length,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    TooManyFilters(usize),
+    ValueTooLong { field: String, max: usize },
+    Timeout(Duration),
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::TooManyFilters(max) => write!(f, "at most {max} filters are allowed"),
+            LimitError::ValueTooLong { field, max } => {
+                write!(f, "the value for '{field}' is longer than {max} characters")
+            }
+            LimitError::Timeout(timeout) => {
+                write!(f, "{TIMEOUT_MESSAGE} after {:.1} s", timeout.as_secs_f64())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// Whether an error message reports a query timing out.
+pub fn is_timeout(message: &str) -> bool {
+    message.contains(TIMEOUT_MESSAGE)
+}
+
+/// What the server says when it turns a request away for coming too soon
+/// after others, with how many seconds to wait.
+pub fn rate_limit_message(retry_after: u64) -> String {
+    format!("{RATE_LIMIT_MESSAGE}, try again in {retry_after} s")
+}
+
+/// Whether an error message reports a request being rate limited.
+pub fn is_rate_limited(message: &str) -> bool {
+    message.contains(RATE_LIMIT_MESSAGE)
+}
+
+/// How many seconds a rate limit message says to wait.
+pub fn retry_after(message: &str) -> Option<u64> {
+    let message = &message[message.find(RATE_LIMIT_MESSAGE)?..];
+    message
+        .split("try again in ")
+        .nth(1)?
+        .split(' ')
+        .next()?
+        .parse()
+        .ok()
+}
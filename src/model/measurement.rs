@@ -0,0 +1,334 @@
+use crate::model::units::{convert, parse_value, Quantity, UnitError, UnitSystem};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Significant figures uncertainties are rounded to.
+pub const ERROR_SIG_FIGS: i32 = 2;
+/// Significant figures a value without uncertainties is rounded to.
+pub const VALUE_SIG_FIGS: i32 = 4;
+
+/// The archive's `*lim` flag: whether a value is a measurement or a limit.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum Limit {
+    #[default]
+    None,
+    /// The true value is at most the reported value.
+    Upper,
+    /// The true value is at least the reported value.
+    Lower,
+}
+
+impl Limit {
+    pub fn from_flag(flag: Option<i64>) -> Limit {
+        match flag {
+            Some(1) => Limit::Upper,
+            Some(-1) => Limit::Lower,
+            _ => Limit::None,
+        }
+    }
+
+    pub fn flag(&self) -> i64 {
+        match self {
+            Limit::None => 0,
+            Limit::Upper => 1,
+            Limit::Lower => -1,
+        }
+    }
+}
+
+/// A value with its asymmetric uncertainties, as reported by the archive in
+/// the `*err1` (upper) and `*err2` (lower) columns.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct Measurement {
+    pub value: Option<f64>,
+    /// The upper uncertainty, always positive.
+    pub err_upper: Option<f64>,
+    /// The lower uncertainty, always positive.
+    pub err_lower: Option<f64>,
+    pub limit: Limit,
+}
+
+impl Measurement {
+    pub fn new(
+        value: Option<f64>,
+        err1: Option<f64>,
+        err2: Option<f64>,
+        limit: Limit,
+    ) -> Measurement {
+        Measurement {
+            value,
+            err_upper: err1.map(f64::abs),
+            err_lower: err2.map(f64::abs),
+            limit,
+        }
+    }
+
+    /// The larger of the two uncertainties relative to the value.
+    pub fn relative_uncertainty(&self) -> Option<f64> {
+        let value = self.value?.abs();
+        let error = self.err_upper?.max(self.err_lower?);
+        (value > 0.0).then(|| error / value)
+    }
+
+    /// Whether `target` lies within `sigmas` uncertainties of the value. A
+    /// limit is consistent with any target on its side of it.
+    pub fn consistent_with(&self, target: f64, sigmas: f64) -> bool {
+        match (self.limit, self.value, self.err_upper, self.err_lower) {
+            (Limit::Upper, Some(value), ..) => target <= value,
+            (Limit::Lower, Some(value), ..) => target >= value,
+            (Limit::None, Some(value), Some(upper), Some(lower)) => {
+                value - sigmas * lower <= target && target <= value + sigmas * upper
+            }
+            _ => false,
+        }
+    }
+
+    /// Converts a measurement in the canonical unit of `quantity` to the unit
+    /// `system` displays it in.
+    pub fn convert(&self, quantity: Quantity, system: UnitSystem) -> Measurement {
+        let convert = |value: Option<f64>| value.map(|v| convert(v, quantity, system));
+        Measurement {
+            value: convert(self.value),
+            err_upper: convert(self.err_upper),
+            err_lower: convert(self.err_lower),
+            limit: self.limit,
+        }
+    }
+
+    /// Rounds the uncertainties to [`ERROR_SIG_FIGS`] significant figures and
+    /// the value to the same decimal place as the smaller uncertainty.
+    pub fn format(&self) -> FormattedMeasurement {
+        let Some(value) = self.value else {
+            return FormattedMeasurement::default();
+        };
+
+        let errors = [self.err_upper, self.err_lower];
+        let smallest_error = errors
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|error| *error > 0.0)
+            .reduce(f64::min);
+        let decimals = match smallest_error {
+            Some(error) => decimals_for(error, ERROR_SIG_FIGS),
+            None => decimals_for(value, VALUE_SIG_FIGS),
+        };
+        let format_error = |sign: char, error: Option<f64>| {
+            error
+                .map(|error| {
+                    let decimals = decimals_for(error, ERROR_SIG_FIGS);
+                    format!("{sign}{}", format_decimals(error, decimals))
+                })
+                .unwrap_or_default()
+        };
+
+        let prefix = match self.limit {
+            Limit::None => "",
+            Limit::Upper => "< ",
+            Limit::Lower => "> ",
+        };
+
+        FormattedMeasurement {
+            value: format!("{prefix}{}", format_decimals(value, decimals)),
+            upper: format_error('+', self.err_upper),
+            lower: format_error('-', self.err_lower),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FormattedMeasurement {
+    pub value: String,
+    pub upper: String,
+    pub lower: String,
+}
+
+/// The number of decimal places that show `value` to `sig_figs`
+/// significant figures.
+fn decimals_for(value: f64, sig_figs: i32) -> usize {
+    if value == 0.0 || !value.is_finite() {
+        return 0;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    (sig_figs - 1 - magnitude).max(0) as usize
+}
+
+fn format_decimals(value: f64, decimals: usize) -> String {
+    format!("{value:.decimals$}")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToleranceError {
+    Value(UnitError),
+    InvalidPercent(String),
+    InvalidSigma(String),
+}
+
+impl fmt::Display for ToleranceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToleranceError::Value(error) => error.fmt(f),
+            ToleranceError::InvalidPercent(value) => {
+                write!(f, "'{value}' is not a percentage")
+            }
+            ToleranceError::InvalidSigma(value) => {
+                write!(f, "'{value}' is not a number of standard deviations")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ToleranceError {}
+
+impl From<UnitError> for ToleranceError {
+    fn from(error: UnitError) -> Self {
+        ToleranceError::Value(error)
+    }
+}
+
+/// Parses a relative uncertainty such as `20%` or `20` into a fraction.
+pub fn parse_percent(input: &str) -> Result<f64, ToleranceError> {
+    let trimmed = input.trim();
+    trimmed
+        .strip_suffix('%')
+        .unwrap_or(trimmed)
+        .trim()
+        .parse::<f64>()
+        .map(|percent| percent / 100.0)
+        .map_err(|_| ToleranceError::InvalidPercent(trimmed.to_string()))
+}
+
+/// Parses a consistency target such as `1.0`, `1.0 Rjup` or `1.0 Rjup, 2σ`
+/// into the target in the canonical unit of `quantity` and a number of
+/// standard deviations, which defaults to one.
+pub fn parse_consistency(input: &str, quantity: Quantity) -> Result<(f64, f64), ToleranceError> {
+    let (target, sigmas) = input.split_once(',').unwrap_or((input, "1"));
+    let target = parse_value(target, quantity)?;
+
+    let sigmas = sigmas.trim();
+    let sigmas = ["σ", "sigma"]
+        .iter()
+        .find_map(|suffix| sigmas.strip_suffix(suffix))
+        .unwrap_or(sigmas)
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| ToleranceError::InvalidSigma(sigmas.to_string()))?;
+
+    Ok((target, sigmas))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::units::EARTH_RADII_PER_JUPITER;
+
+    fn measurement(value: f64, err1: f64, err2: f64) -> Measurement {
+        Measurement::new(Some(value), Some(err1), Some(err2), Limit::None)
+    }
+
+    fn formatted(value: &str, upper: &str, lower: &str) -> FormattedMeasurement {
+        FormattedMeasurement {
+            value: value.to_string(),
+            upper: upper.to_string(),
+            lower: lower.to_string(),
+        }
+    }
+
+    #[test]
+    fn uncertainties_round_to_two_significant_figures() {
+        assert_eq!(
+            measurement(1.23456, 0.01234, -0.0456).format(),
+            formatted("1.235", "+0.012", "-0.046")
+        );
+        assert_eq!(
+            measurement(365.256, 1.5, -12.0).format(),
+            formatted("365.3", "+1.5", "-12")
+        );
+        // A value is never rounded coarser than to a whole number.
+        assert_eq!(
+            measurement(5778.4, 120.0, -90.0).format(),
+            formatted("5778", "+120", "-90")
+        );
+        // A zero uncertainty doesn't decide the value's precision.
+        assert_eq!(
+            measurement(2.5, 0.0, -0.25).format(),
+            formatted("2.50", "+0", "-0.25")
+        );
+    }
+
+    #[test]
+    fn values_without_uncertainties_round_to_four_significant_figures() {
+        let bare = |value| Measurement::new(Some(value), None, None, Limit::None);
+        assert_eq!(bare(1.23456).format(), formatted("1.235", "", ""));
+        assert_eq!(bare(0.000123456).format(), formatted("0.0001235", "", ""));
+        assert_eq!(bare(123456.0).format(), formatted("123456", "", ""));
+        assert_eq!(
+            Measurement::new(None, Some(1.0), None, Limit::None).format(),
+            FormattedMeasurement::default()
+        );
+    }
+
+    #[test]
+    fn limits_are_prefixed() {
+        let upper = Measurement::new(Some(2.0), None, None, Limit::Upper);
+        assert_eq!(upper.format().value, "< 2.000");
+        let lower = Measurement::new(Some(0.5), None, None, Limit::Lower);
+        assert_eq!(lower.format().value, "> 0.5000");
+        assert_eq!(Limit::from_flag(Some(1)), Limit::Upper);
+        assert_eq!(Limit::from_flag(Some(-1)), Limit::Lower);
+        assert_eq!(Limit::from_flag(None), Limit::None);
+    }
+
+    #[test]
+    fn consistency() {
+        let radius = measurement(1.0, 0.1, -0.2);
+        assert!(radius.consistent_with(1.1, 1.0));
+        assert!(radius.consistent_with(0.8, 1.0));
+        assert!(!radius.consistent_with(1.15, 1.0));
+        assert!(radius.consistent_with(1.15, 2.0));
+        assert!(!radius.consistent_with(0.5, 2.0));
+        assert!(!Measurement::new(Some(1.0), None, None, Limit::None).consistent_with(1.0, 1.0));
+
+        // A limit is consistent with anything on its side, however far.
+        let upper = Measurement::new(Some(2.0), None, None, Limit::Upper);
+        assert!(upper.consistent_with(0.1, 1.0));
+        assert!(!upper.consistent_with(2.5, 3.0));
+        let lower = Measurement::new(Some(2.0), Some(0.1), Some(0.1), Limit::Lower);
+        assert!(lower.consistent_with(50.0, 1.0));
+        assert!(!lower.consistent_with(1.95, 1.0));
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(parse_percent("20%"), Ok(0.2));
+        assert_eq!(parse_percent(" 5 % "), Ok(0.05));
+        assert_eq!(parse_percent("50"), Ok(0.5));
+        assert_eq!(
+            parse_percent(" lots% "),
+            Err(ToleranceError::InvalidPercent("lots%".to_string()))
+        );
+    }
+
+    #[test]
+    fn consistency_targets() {
+        let radius = Quantity::PlanetRadius;
+        assert_eq!(parse_consistency("1.5", radius), Ok((1.5, 1.0)));
+        assert_eq!(parse_consistency("1.5, 3", radius), Ok((1.5, 3.0)));
+        assert_eq!(parse_consistency("1.5, 2σ", radius), Ok((1.5, 2.0)));
+        assert_eq!(parse_consistency("1.5,0.5 sigma", radius), Ok((1.5, 0.5)));
+        let (target, sigmas) = parse_consistency("1.0 Rjup, 2σ", radius).unwrap();
+        assert!((target - EARTH_RADII_PER_JUPITER).abs() < 1e-9);
+        assert_eq!(sigmas, 2.0);
+
+        assert_eq!(
+            parse_consistency("1.5, twoσ", radius),
+            Err(ToleranceError::InvalidSigma("twoσ".to_string()))
+        );
+        assert_eq!(
+            parse_consistency("huge, 2σ", radius),
+            Err(ToleranceError::Value(UnitError::InvalidNumber(
+                "huge".to_string()
+            )))
+        );
+    }
+}
@@ -1,2 +1,19 @@
+pub mod cache;
+pub mod columns;
+pub mod console;
 pub mod data;
+pub mod derived;
+pub mod explain;
+pub mod filter;
 pub mod input;
+pub mod limits;
+pub mod measurement;
+pub mod plot;
+pub mod similarity;
+pub mod snapshot;
+pub mod stats;
+pub mod system;
+pub mod text_query;
+pub mod timeline;
+pub mod units;
+pub mod user;
@@ -0,0 +1,618 @@
+use crate::model::{
+    columns::find_column,
+    input::Input,
+    measurement::Measurement,
+    units::{Quantity, UnitSystem},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// The most planets returned for a plot, enough for the whole archive while
+/// keeping the SVG responsive.
+pub const PLOT_LIMIT: usize = 10_000;
+pub const WIDTH: f64 = 720.0;
+pub const HEIGHT: f64 = 440.0;
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+
+/// Left, right, bottom and top edges of the plotting area in pixels.
+pub const LEFT: f64 = MARGIN_LEFT;
+pub const RIGHT: f64 = WIDTH - MARGIN_RIGHT;
+pub const BOTTOM: f64 = HEIGHT - MARGIN_BOTTOM;
+pub const TOP: f64 = MARGIN_TOP;
+
+pub const HISTOGRAM_BINS: usize = 20;
+const TARGET_TICKS: f64 = 5.0;
+
+/// Colours assigned to the archive's discovery methods, in a fixed order so
+/// plots are coloured the same everywhere.
+const METHOD_COLORS: [(&str, &str); 11] = [
+    ("Transit", "#1f77b4"),
+    ("Radial Velocity", "#d62728"),
+    ("Microlensing", "#2ca02c"),
+    ("Imaging", "#ff7f0e"),
+    ("Transit Timing Variations", "#9467bd"),
+    ("Eclipse Timing Variations", "#8c564b"),
+    ("Orbital Brightness Modulation", "#e377c2"),
+    ("Pulsar Timing", "#17becf"),
+    ("Astrometry", "#bcbd22"),
+    ("Pulsation Timing Variations", "#393b79"),
+    ("Disk Kinematics", "#637939"),
+];
+const OTHER_COLOR: &str = "#7f7f7f";
+
+pub fn method_color(method: Option<&str>) -> &'static str {
+    METHOD_COLORS
+        .iter()
+        .find(|(name, _)| Some(*name) == method)
+        .map(|(_, color)| *color)
+        .unwrap_or(OTHER_COLOR)
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PlotKind {
+    #[default]
+    Scatter,
+    Histogram,
+}
+
+impl PlotKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlotKind::Scatter => "scatter",
+            PlotKind::Histogram => "histogram",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<PlotKind> {
+        match value {
+            "scatter" => Some(PlotKind::Scatter),
+            "histogram" => Some(PlotKind::Histogram),
+            _ => None,
+        }
+    }
+}
+
+/// The filters, axes and options of a plot, as taken by the plot endpoint's
+/// query string.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PlotParams {
+    #[serde(default)]
+    pub query: Vec<Input>,
+    pub x: String,
+    /// Defaults to `x`, for histograms.
+    #[serde(default)]
+    pub y: Option<String>,
+    #[serde(default)]
+    pub kind: PlotKind,
+    #[serde(default)]
+    pub log_x: bool,
+    #[serde(default)]
+    pub log_y: bool,
+    #[serde(default)]
+    pub units: UnitSystem,
+}
+
+/// One planet's values on the two plotted columns.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlotPoint {
+    pub id: i64,
+    pub pl_name: String,
+    pub discovery_method: Option<String>,
+    pub x: Measurement,
+    pub y: Measurement,
+}
+
+/// The filtered planets to plot, with values in canonical units.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlotData {
+    /// The id of the column on the x axis.
+    pub x: String,
+    /// The id of the column on the y axis.
+    pub y: String,
+    pub points: Vec<PlotPoint>,
+    /// Whether more planets matched than were returned.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+    pub position: f64,
+    pub label: String,
+}
+
+/// A circle of the scatter plot, with its error bars as pixel ranges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterMark {
+    pub id: i64,
+    pub pl_name: String,
+    pub color: &'static str,
+    pub cx: f64,
+    pub cy: f64,
+    pub x_bar: Option<(f64, f64)>,
+    pub y_bar: Option<(f64, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterLayout {
+    pub marks: Vec<ScatterMark>,
+    pub x_ticks: Vec<Tick>,
+    pub y_ticks: Vec<Tick>,
+    /// The discovery methods present, with their colours.
+    pub legend: Vec<(String, &'static str)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBar {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub count: usize,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramLayout {
+    pub bars: Vec<HistogramBar>,
+    pub x_ticks: Vec<Tick>,
+    pub y_ticks: Vec<Tick>,
+}
+
+impl PlotData {
+    /// Converts every value to the units `system` displays them in.
+    pub fn convert(&self, system: UnitSystem) -> PlotData {
+        let convert = |id: &str, measurement: Measurement| match quantity(id) {
+            Some(quantity) => measurement.convert(quantity, system),
+            None => measurement,
+        };
+        PlotData {
+            points: self
+                .points
+                .iter()
+                .map(|point| PlotPoint {
+                    x: convert(&self.x, point.x),
+                    y: convert(&self.y, point.y),
+                    ..point.clone()
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Lays out the planets with a value on both axes, or `None` if there
+    /// are none.
+    pub fn scatter(&self, log_x: bool, log_y: bool) -> Option<ScatterLayout> {
+        let plotted = self
+            .points
+            .iter()
+            .filter(|point| {
+                positive_if(point.x.value, log_x).is_some()
+                    && positive_if(point.y.value, log_y).is_some()
+            })
+            .collect::<Vec<_>>();
+        let x_scale = Scale::fit(plotted.iter().flat_map(|p| p.x.value), log_x, LEFT, RIGHT)?;
+        let y_scale = Scale::fit(plotted.iter().flat_map(|p| p.y.value), log_y, BOTTOM, TOP)?;
+
+        let marks = plotted
+            .iter()
+            .filter_map(|point| {
+                Some(ScatterMark {
+                    id: point.id,
+                    pl_name: point.pl_name.clone(),
+                    color: method_color(point.discovery_method.as_deref()),
+                    cx: x_scale.project(point.x.value?)?,
+                    cy: y_scale.project(point.y.value?)?,
+                    x_bar: x_scale.error_bar(&point.x),
+                    y_bar: y_scale.error_bar(&point.y),
+                })
+            })
+            .collect();
+
+        let mut legend = plotted
+            .iter()
+            .map(|point| point.discovery_method.clone().unwrap_or_default())
+            .collect::<Vec<_>>();
+        legend.sort();
+        legend.dedup();
+        let legend = legend
+            .into_iter()
+            .map(|method| {
+                let color = method_color(Some(&method));
+                (method, color)
+            })
+            .collect();
+
+        Some(ScatterLayout {
+            marks,
+            x_ticks: x_scale.ticks(0.0),
+            y_ticks: y_scale.ticks(0.0),
+            legend,
+        })
+    }
+
+    /// Bins the x values into [`HISTOGRAM_BINS`] bins of equal width, on a
+    /// log scale if `log_x` is set.
+    pub fn histogram(&self, log_x: bool) -> Option<HistogramLayout> {
+        let values = self
+            .points
+            .iter()
+            .filter_map(|point| positive_if(point.x.value, log_x))
+            .collect::<Vec<_>>();
+        let x_scale = Scale::fit(values.iter().copied(), log_x, LEFT, RIGHT)?;
+
+        let mut counts = [0usize; HISTOGRAM_BINS];
+        let width = (x_scale.max - x_scale.min) / HISTOGRAM_BINS as f64;
+        for value in &values {
+            let Some(t) = x_scale.transform(*value) else {
+                continue;
+            };
+            let bin = ((t - x_scale.min) / width).floor() as usize;
+            counts[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+
+        let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+        let y_scale = Scale::linear(0.0, max_count as f64, BOTTOM, TOP);
+
+        let bars = counts
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| {
+                let start = x_scale.min + width * bin as f64;
+                let end = start + width;
+                let left = x_scale.position(start);
+                let right = x_scale.position(end);
+                let top = y_scale.position(*count as f64);
+                HistogramBar {
+                    x: left,
+                    y: top,
+                    width: right - left,
+                    height: BOTTOM - top,
+                    count: *count,
+                    start: x_scale.untransform(start),
+                    end: x_scale.untransform(end),
+                }
+            })
+            .collect();
+
+        Some(HistogramLayout {
+            bars,
+            x_ticks: x_scale.ticks(0.0),
+            // Counts are whole numbers, so never tick between them.
+            y_ticks: y_scale.ticks(1.0),
+        })
+    }
+}
+
+fn quantity(id: &str) -> Option<Quantity> {
+    find_column(id).and_then(|column| column.quantity)
+}
+
+/// The axis title of a column, with the unit `system` displays it in.
+pub fn axis_label(id: &str, system: UnitSystem) -> String {
+    let Some(column) = find_column(id) else {
+        return id.to_string();
+    };
+    match column.quantity {
+        Some(quantity) => format!("{} [{}]", column.label, system.unit_for(quantity)),
+        None => column.label.to_string(),
+    }
+}
+
+fn positive_if(value: Option<f64>, log: bool) -> Option<f64> {
+    value.filter(|v| v.is_finite() && (!log || *v > 0.0))
+}
+
+/// Maps values onto a pixel range, linearly or by their logarithm.
+///
+/// `min` and `max` are in transformed units, so log10 of the value on a log
+/// scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale {
+    min: f64,
+    max: f64,
+    log: bool,
+    start: f64,
+    end: f64,
+}
+
+impl Scale {
+    pub fn linear(min: f64, max: f64, start: f64, end: f64) -> Scale {
+        Scale {
+            min,
+            max,
+            log: false,
+            start,
+            end,
+        }
+    }
+
+    /// Fits a scale to the values, padded so none sit on the axes, or `None`
+    /// if there are no values to show.
+    pub fn fit(
+        values: impl Iterator<Item = f64>,
+        log: bool,
+        start: f64,
+        end: f64,
+    ) -> Option<Scale> {
+        let scale = Scale {
+            min: 0.0,
+            max: 0.0,
+            log,
+            start,
+            end,
+        };
+        let (min, max) = values.filter_map(|value| scale.transform(value)).fold(
+            None,
+            |range, t| match range {
+                None => Some((t, t)),
+                Some((min, max)) => Some((f64::min(min, t), f64::max(max, t))),
+            },
+        )?;
+        let padding = if min == max { 0.5 } else { (max - min) * 0.05 };
+        Some(Scale {
+            min: min - padding,
+            max: max + padding,
+            ..scale
+        })
+    }
+
+    fn transform(&self, value: f64) -> Option<f64> {
+        match self.log {
+            true => (value > 0.0 && value.is_finite()).then(|| value.log10()),
+            false => value.is_finite().then_some(value),
+        }
+    }
+
+    fn untransform(&self, t: f64) -> f64 {
+        match self.log {
+            true => 10f64.powf(t),
+            false => t,
+        }
+    }
+
+    /// The pixel position of a transformed value.
+    pub fn position(&self, t: f64) -> f64 {
+        self.start + (t - self.min) / (self.max - self.min) * (self.end - self.start)
+    }
+
+    pub fn project(&self, value: f64) -> Option<f64> {
+        self.transform(value).map(|t| self.position(t))
+    }
+
+    /// The pixel range covered by a measurement's uncertainties, clamped to
+    /// the axis where the lower end is not positive on a log scale.
+    fn error_bar(&self, measurement: &Measurement) -> Option<(f64, f64)> {
+        let value = measurement.value?;
+        if measurement.err_upper.is_none() && measurement.err_lower.is_none() {
+            return None;
+        }
+        let low = value - measurement.err_lower.unwrap_or(0.0);
+        let high = value + measurement.err_upper.unwrap_or(0.0);
+        let clamp = |position: f64| {
+            let (lower, upper) = (self.start.min(self.end), self.start.max(self.end));
+            position.clamp(lower, upper)
+        };
+        Some((
+            clamp(self.project(low).unwrap_or(self.start)),
+            clamp(self.project(high)?),
+        ))
+    }
+
+    /// Ticks at round numbers on a linear scale, and at powers of ten on a
+    /// log scale, at least `min_step` apart on a linear scale.
+    pub fn ticks(&self, min_step: f64) -> Vec<Tick> {
+        let tick = |t: f64, value: f64| Tick {
+            position: self.position(t),
+            label: format_tick(value),
+        };
+        if self.log {
+            let decades = (self.min.ceil() as i32..=self.max.floor() as i32).collect::<Vec<_>>();
+            if !decades.is_empty() {
+                return decades
+                    .into_iter()
+                    .map(|decade| tick(decade as f64, 10f64.powi(decade)))
+                    .collect();
+            }
+        }
+
+        let step = nice_step((self.max - self.min) / TARGET_TICKS).max(min_step);
+        let first = (self.min / step).ceil() as i64;
+        let last = (self.max / step).floor() as i64;
+        (first..=last)
+            .map(|i| {
+                let t = i as f64 * step;
+                tick(t, self.untransform(t))
+            })
+            .collect()
+    }
+}
+
+/// The 1, 2 or 5 times a power of ten nearest above `raw`.
+fn nice_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+pub fn format_tick(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude != 0.0 && !(1e-3..1e5).contains(&magnitude) {
+        return format!("{value:.0e}");
+    }
+    let text = format!("{value:.3}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// Renders a plot as a standalone SVG document.
+///
+/// The output depends only on `data` and the options, with coordinates at a
+/// fixed precision, so the same query always renders byte-for-byte the same
+/// image.
+pub fn render_svg(data: &PlotData, params: &PlotParams) -> String {
+    let data = data.convert(params.units);
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif">"#
+    );
+    svg.push_str(r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    let x_label = axis_label(&data.x, params.units);
+    match params.kind {
+        PlotKind::Scatter => match data.scatter(params.log_x, params.log_y) {
+            Some(layout) => {
+                let y_label = axis_label(&data.y, params.units);
+                write_axes(
+                    &mut svg,
+                    &layout.x_ticks,
+                    &layout.y_ticks,
+                    &x_label,
+                    &y_label,
+                );
+                for mark in &layout.marks {
+                    let _ = write!(svg, r#"<g><title>{}</title>"#, escape_xml(&mark.pl_name));
+                    if let Some((low, high)) = mark.x_bar {
+                        write_line(&mut svg, (low, mark.cy), (high, mark.cy), mark.color);
+                    }
+                    if let Some((low, high)) = mark.y_bar {
+                        write_line(&mut svg, (mark.cx, low), (mark.cx, high), mark.color);
+                    }
+                    let _ = write!(
+                        svg,
+                        r#"<circle cx="{:.2}" cy="{:.2}" r="3" fill="{}" fill-opacity="0.7"/></g>"#,
+                        mark.cx, mark.cy, mark.color
+                    );
+                }
+                write_legend(&mut svg, &layout.legend);
+            }
+            None => write_message(&mut svg, "No planets have values on both axes."),
+        },
+        PlotKind::Histogram => match data.histogram(params.log_x) {
+            Some(layout) => {
+                write_axes(
+                    &mut svg,
+                    &layout.x_ticks,
+                    &layout.y_ticks,
+                    &x_label,
+                    "Planets",
+                );
+                for bar in &layout.bars {
+                    let _ = write!(
+                        svg,
+                        r##"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="#1f77b4" fill-opacity="0.8" stroke="white"><title>{} to {}: {}</title></rect>"##,
+                        bar.x,
+                        bar.y,
+                        bar.width,
+                        bar.height,
+                        format_tick(bar.start),
+                        format_tick(bar.end),
+                        bar.count
+                    );
+                }
+            }
+            None => write_message(&mut svg, "No planets have a value to plot."),
+        },
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn write_line(svg: &mut String, (x1, y1): (f64, f64), (x2, y2): (f64, f64), color: &str) {
+    let _ = write!(
+        svg,
+        r#"<line x1="{x1:.2}" y1="{y1:.2}" x2="{x2:.2}" y2="{y2:.2}" stroke="{color}"/>"#
+    );
+}
+
+fn write_text(svg: &mut String, x: f64, y: f64, anchor: &str, attributes: &str, text: &str) {
+    let _ = write!(
+        svg,
+        r#"<text x="{x:.2}" y="{y:.2}" text-anchor="{anchor}" {attributes}>{}</text>"#,
+        escape_xml(text)
+    );
+}
+
+fn write_axes(svg: &mut String, x_ticks: &[Tick], y_ticks: &[Tick], x_label: &str, y_label: &str) {
+    write_line(svg, (LEFT, BOTTOM), (RIGHT, BOTTOM), "black");
+    write_line(svg, (LEFT, BOTTOM), (LEFT, TOP), "black");
+    for tick in x_ticks {
+        write_line(
+            svg,
+            (tick.position, BOTTOM),
+            (tick.position, BOTTOM + 5.0),
+            "black",
+        );
+        let y = BOTTOM + 18.0;
+        write_text(
+            svg,
+            tick.position,
+            y,
+            "middle",
+            r#"font-size="11""#,
+            &tick.label,
+        );
+    }
+    for tick in y_ticks {
+        write_line(
+            svg,
+            (LEFT - 5.0, tick.position),
+            (LEFT, tick.position),
+            "black",
+        );
+        let y = tick.position + 4.0;
+        write_text(svg, LEFT - 8.0, y, "end", r#"font-size="11""#, &tick.label);
+    }
+    let x = (LEFT + RIGHT) / 2.0;
+    write_text(svg, x, HEIGHT - 8.0, "middle", r#"font-size="12""#, x_label);
+    let rotate = format!(
+        r#"font-size="12" transform="rotate(-90 14 {:.2})""#,
+        (TOP + BOTTOM) / 2.0
+    );
+    write_text(svg, 14.0, (TOP + BOTTOM) / 2.0, "middle", &rotate, y_label);
+}
+
+/// Lists the discovery methods down the top right corner of the plot.
+fn write_legend(svg: &mut String, legend: &[(String, &'static str)]) {
+    for (row, (method, color)) in legend.iter().enumerate() {
+        let y = TOP + 12.0 + 16.0 * row as f64;
+        let _ = write!(
+            svg,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="4" fill="{color}"/>"#,
+            RIGHT - 10.0,
+            y - 4.0
+        );
+        let method = if method.is_empty() { "Unknown" } else { method };
+        write_text(svg, RIGHT - 18.0, y, "end", r#"font-size="11""#, method);
+    }
+}
+
+fn write_message(svg: &mut String, message: &str) {
+    write_text(
+        svg,
+        WIDTH / 2.0,
+        HEIGHT / 2.0,
+        "middle",
+        r#"font-size="14""#,
+        message,
+    );
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The most similar planets returned by one search.
+pub const MAX_SIMILAR: usize = 100;
+/// The squared distance, in standard deviations, charged for a parameter the
+/// target has but a candidate lacks, so candidates with missing values rank
+/// below those with comparable ones without being excluded.
+const MISSING_PENALTY: f64 = 4.0;
+
+/// A parameter planets can be compared on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SimilarityParameter {
+    Radius,
+    Mass,
+    Period,
+    SpectralType,
+}
+
+impl SimilarityParameter {
+    pub const ALL: [SimilarityParameter; 4] = [
+        SimilarityParameter::Radius,
+        SimilarityParameter::Mass,
+        SimilarityParameter::Period,
+        SimilarityParameter::SpectralType,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SimilarityParameter::Radius => "Radius",
+            SimilarityParameter::Mass => "Mass",
+            SimilarityParameter::Period => "Period",
+            SimilarityParameter::SpectralType => "Spectral Type",
+        }
+    }
+}
+
+/// The values a planet is compared on.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Candidate {
+    pub id: i64,
+    pub pl_rade: Option<f64>,
+    pub pl_bmasse: Option<f64>,
+    pub pl_orbper: Option<f64>,
+    pub st_spectype: Option<String>,
+}
+
+impl Candidate {
+    /// The parameter on the scale distances are measured in: log10 for
+    /// radius, mass and period, which span orders of magnitude, and the
+    /// position in the spectral sequence for spectral type.
+    fn coordinate(&self, parameter: SimilarityParameter) -> Option<f64> {
+        let log = |value: Option<f64>| value.filter(|v| *v > 0.0).map(f64::log10);
+        match parameter {
+            SimilarityParameter::Radius => log(self.pl_rade),
+            SimilarityParameter::Mass => log(self.pl_bmasse),
+            SimilarityParameter::Period => log(self.pl_orbper),
+            SimilarityParameter::SpectralType => {
+                self.st_spectype.as_deref().and_then(spectral_sequence)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimilarityError {
+    NoParameters,
+    UnknownPlanet(i64),
+    /// The planet has no value for any chosen parameter.
+    NoValues(String),
+}
+
+impl fmt::Display for SimilarityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimilarityError::NoParameters => f.write_str("choose at least one parameter"),
+            SimilarityError::UnknownPlanet(id) => write!(f, "no planet with id {id}"),
+            SimilarityError::NoValues(parameters) => {
+                write!(f, "the planet has no value for {parameters}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimilarityError {}
+
+/// Finds the `count` candidates nearest to the one with id `target`, nearest
+/// first, with their distances.
+///
+/// Each parameter is normalised by its standard deviation over all
+/// candidates, and the distance is the root mean square of the normalised
+/// differences over the parameters the target has a value for.
+pub fn most_similar(
+    candidates: &[Candidate],
+    target: i64,
+    parameters: &[SimilarityParameter],
+    count: usize,
+) -> Result<Vec<(i64, f64)>, SimilarityError> {
+    if parameters.is_empty() {
+        return Err(SimilarityError::NoParameters);
+    }
+    let target = candidates
+        .iter()
+        .find(|candidate| candidate.id == target)
+        .ok_or(SimilarityError::UnknownPlanet(target))?;
+
+    // Parameters the target lacks can't be compared on, so drop them.
+    let dimensions = parameters
+        .iter()
+        .filter_map(|parameter| {
+            let value = target.coordinate(*parameter)?;
+            let spread =
+                standard_deviation(candidates.iter().filter_map(|c| c.coordinate(*parameter)))
+                    .filter(|spread| *spread > 0.0)
+                    .unwrap_or(1.0);
+            Some((*parameter, value, spread))
+        })
+        .collect::<Vec<_>>();
+    if dimensions.is_empty() {
+        let names = parameters
+            .iter()
+            .map(|parameter| parameter.label().to_lowercase())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(SimilarityError::NoValues(names));
+    }
+
+    let mut distances = candidates
+        .iter()
+        .filter(|candidate| candidate.id != target.id)
+        .filter_map(|candidate| {
+            let mut sum = 0.0;
+            let mut compared = 0;
+            for (parameter, value, spread) in &dimensions {
+                match candidate.coordinate(*parameter) {
+                    Some(other) => {
+                        sum += ((other - value) / spread).powi(2);
+                        compared += 1;
+                    }
+                    None => sum += MISSING_PENALTY,
+                }
+            }
+            // A candidate with nothing in common is not similar at all.
+            (compared > 0).then(|| (candidate.id, (sum / dimensions.len() as f64).sqrt()))
+        })
+        .collect::<Vec<_>>();
+
+    distances.sort_by(|(a_id, a), (b_id, b)| a.total_cmp(b).then(a_id.cmp(b_id)));
+    distances.truncate(count.min(MAX_SIMILAR));
+    Ok(distances)
+}
+
+fn standard_deviation(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let values = values.collect::<Vec<_>>();
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance =
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+/// The position of a spectral type such as `G2 V` or `K1.5` in the sequence
+/// O0 … M9 … Y9, ten steps per class, or `None` if it doesn't start with a
+/// class letter.
+pub fn spectral_sequence(spectral_type: &str) -> Option<f64> {
+    const CLASSES: &str = "OBAFGKMLTY";
+
+    let spectral_type = spectral_type.trim_start();
+    let class = spectral_type.chars().next()?;
+    let class = CLASSES.find(class.to_ascii_uppercase())? as f64;
+    let subclass = spectral_type[1..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect::<String>()
+        .parse::<f64>()
+        .unwrap_or(5.0);
+    Some(class * 10.0 + subclass.min(9.9))
+}
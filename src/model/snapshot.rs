@@ -0,0 +1,355 @@
+//! Queries over a snapshot of the dataset held in memory, for the static
+//! build, which has no server to send them to. Each answers like its server
+//! function does against the database.
+
+use crate::model::{
+    columns::{find_column, Column, ColumnKind},
+    data::{Data, PlanetData, Value, PAGE_SIZE},
+    filter::{FilterError, Predicate},
+    input::{Input, PageKind, Sort},
+    measurement::{Limit, Measurement},
+    plot::{PlotData, PlotPoint, PLOT_LIMIT},
+    similarity::{most_similar, Candidate, SimilarityError, SimilarityParameter},
+    stats::{summarise, Statistics},
+    system::{PlanetarySystem, SYSTEM_LIMIT},
+    timeline::{StackBy, Timeline, YearCount},
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BTreeMap, fmt};
+
+/// The default rows of `exoplanet_data`, in order of id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Snapshot {
+    pub planets: Vec<PlanetData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotError {
+    Filter(FilterError),
+    Similarity(SimilarityError),
+    UnknownField(String),
+    NotNumeric(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Filter(error) => error.fmt(f),
+            SnapshotError::Similarity(error) => error.fmt(f),
+            SnapshotError::UnknownField(field) => write!(f, "unknown field '{field}'"),
+            SnapshotError::NotNumeric(field) => write!(f, "'{field}' is not a numeric field"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<FilterError> for SnapshotError {
+    fn from(error: FilterError) -> Self {
+        SnapshotError::Filter(error)
+    }
+}
+
+impl From<SimilarityError> for SnapshotError {
+    fn from(error: SimilarityError) -> Self {
+        SnapshotError::Similarity(error)
+    }
+}
+
+fn visible(id: &str) -> Result<&'static Column, SnapshotError> {
+    find_column(id)
+        .filter(|column| !column.hidden)
+        .ok_or_else(|| SnapshotError::UnknownField(id.to_string()))
+}
+
+fn numeric(id: &str) -> Result<&'static Column, SnapshotError> {
+    find_column(id)
+        .filter(|column| {
+            !column.hidden && matches!(column.kind, ColumnKind::Integer | ColumnKind::Real)
+        })
+        .ok_or_else(|| SnapshotError::NotNumeric(id.to_string()))
+}
+
+/// Orders values like the database: text by bytes, numbers by value.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        (a, b) => {
+            let number =
+                |value: &Value| value.as_f64().or_else(|| value.as_i64().map(|v| v as f64));
+            number(a)
+                .zip(number(b))
+                .map_or(Ordering::Equal, |(a, b)| a.total_cmp(&b))
+        }
+    }
+}
+
+fn text(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// The measurement of a column, read like `plot_sql` reads it.
+fn measurement(planet: &PlanetData, column: &Column) -> Measurement {
+    let number = |field: &str| planet.value(field).and_then(|value| value.as_f64());
+    let error = |suffix: &str| match column.errors {
+        true => number(&format!("{}{suffix}", column.sql)),
+        false => None,
+    };
+    let limit = match column.limit {
+        true => planet
+            .value(&format!("{}lim", column.sql))
+            .and_then(|value| value.as_i64()),
+        false => None,
+    };
+    Measurement::new(
+        number(column.id),
+        error("err1"),
+        error("err2"),
+        Limit::from_flag(limit),
+    )
+}
+
+impl Snapshot {
+    pub fn new(mut planets: Vec<PlanetData>) -> Snapshot {
+        planets.retain(|planet| planet.default_flag);
+        planets.sort_by_key(|planet| planet.id);
+        Snapshot { planets }
+    }
+
+    /// The date of the most recently released row, how fresh the data is.
+    pub fn latest_release(&self) -> Option<&str> {
+        self.planets
+            .iter()
+            .filter_map(|planet| planet.release_date.as_deref())
+            .max()
+            // Dates are stored as `YYYY-MM-DD`, sometimes with a time.
+            .map(|date| date.get(..10).unwrap_or(date))
+    }
+
+    /// The planets passing every filter, in order of id.
+    pub fn select(&self, query: Vec<Input>) -> Result<Vec<&PlanetData>, SnapshotError> {
+        let filters = query
+            .into_iter()
+            .map(Predicate::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self
+            .planets
+            .iter()
+            .filter(|planet| filters.iter().all(|filter| filter.matches_planet(planet)))
+            .collect())
+    }
+
+    /// A page of records, paged by offset when sorted and by id otherwise,
+    /// like `records_sql`.
+    pub fn records(
+        &self,
+        query: Vec<Input>,
+        sort: Option<Sort>,
+        anchor_id: i64,
+        page_direction: PageKind,
+    ) -> Result<Option<Data>, SnapshotError> {
+        let mut planets = self.select(query)?;
+
+        if let Some(sort) = sort {
+            let column = visible(&sort.field)?;
+            // Missing values sort last whichever the direction.
+            planets.sort_by(|a, b| {
+                let (a_value, b_value) = (
+                    a.value(column.id).unwrap_or(Value::Null),
+                    b.value(column.id).unwrap_or(Value::Null),
+                );
+                let ordering = match sort.descending {
+                    true => compare(&b_value, &a_value),
+                    false => compare(&a_value, &b_value),
+                };
+                a_value
+                    .is_null()
+                    .cmp(&b_value.is_null())
+                    .then(ordering)
+                    .then(a.id.cmp(&b.id))
+            });
+            let offset = match page_direction {
+                PageKind::Next => anchor_id,
+                PageKind::Prev => (anchor_id - PAGE_SIZE).max(0),
+            };
+            let page = planets
+                .into_iter()
+                .skip(offset as usize)
+                .take(PAGE_SIZE as usize)
+                .cloned()
+                .collect();
+            return Ok(Data::build_sorted(page, offset));
+        }
+
+        let page = match page_direction {
+            PageKind::Next => planets
+                .into_iter()
+                .filter(|planet| planet.id > anchor_id)
+                .take(PAGE_SIZE as usize)
+                .cloned()
+                .collect(),
+            PageKind::Prev => {
+                let before = planets
+                    .into_iter()
+                    .filter(|planet| planet.id < anchor_id)
+                    .collect::<Vec<_>>();
+                let start = before.len().saturating_sub(PAGE_SIZE as usize);
+                before[start..]
+                    .iter()
+                    .map(|planet| (*planet).clone())
+                    .collect()
+            }
+        };
+        Ok(Data::build(page))
+    }
+
+    pub fn stats(
+        &self,
+        query: Vec<Input>,
+        group_by: String,
+        column: String,
+    ) -> Result<Statistics, SnapshotError> {
+        let group_column = visible(&group_by)?;
+        let value_column = numeric(&column)?;
+        let groups = summarise(self.select(query)?.into_iter().map(|planet| {
+            (
+                planet.value(group_column.id).and_then(text),
+                planet
+                    .value(value_column.id)
+                    .and_then(|value| value.as_f64()),
+            )
+        }));
+        Ok(Statistics {
+            group_by,
+            column,
+            groups,
+        })
+    }
+
+    pub fn plot(&self, query: Vec<Input>, x: String, y: String) -> Result<PlotData, SnapshotError> {
+        let x_column = numeric(&x)?;
+        let y_column = numeric(&y)?;
+        let mut points = self
+            .select(query)?
+            .into_iter()
+            .map(|planet| PlotPoint {
+                id: planet.id,
+                pl_name: planet.pl_name.clone(),
+                discovery_method: planet.discovery_method.clone(),
+                x: measurement(planet, x_column),
+                y: measurement(planet, y_column),
+            })
+            .filter(|point| point.x.value.is_some())
+            .take(PLOT_LIMIT + 1)
+            .collect::<Vec<_>>();
+        let truncated = points.len() > PLOT_LIMIT;
+        points.truncate(PLOT_LIMIT);
+        Ok(PlotData {
+            x,
+            y,
+            points,
+            truncated,
+        })
+    }
+
+    pub fn timeline(
+        &self,
+        query: Vec<Input>,
+        stack_by: StackBy,
+    ) -> Result<Timeline, SnapshotError> {
+        let category = find_column(stack_by.column())
+            .ok_or_else(|| SnapshotError::UnknownField(stack_by.column().to_string()))?;
+        let mut counts = BTreeMap::<(i64, Option<String>), i64>::new();
+        for planet in self.select(query)? {
+            if let Some(year) = planet.disc_year {
+                let category = planet.value(category.id).and_then(text);
+                *counts.entry((year, category)).or_default() += 1;
+            }
+        }
+        let counts = counts
+            .into_iter()
+            .map(|((year, category), count)| YearCount {
+                year,
+                category,
+                count,
+            })
+            .collect();
+        Ok(Timeline { stack_by, counts })
+    }
+
+    /// The multi-planet systems with a planet passing the filters, like
+    /// the server's `find_systems`.
+    pub fn systems(&self, query: Vec<Input>) -> Result<Vec<PlanetarySystem>, SnapshotError> {
+        let mut hosts = Vec::<(&str, i64)>::new();
+        for planet in self.select(query)? {
+            let Some(count) = planet.sy_pnum.filter(|count| *count > 1) else {
+                continue;
+            };
+            match hosts.iter_mut().find(|(host, _)| *host == planet.hostname) {
+                Some((_, most)) => *most = (*most).max(count),
+                None => hosts.push((planet.hostname.as_str(), count)),
+            }
+        }
+        hosts.sort_by(|(a_host, a), (b_host, b)| b.cmp(a).then(a_host.cmp(b_host)));
+        hosts.truncate(SYSTEM_LIMIT as usize);
+
+        let mut planets = self
+            .planets
+            .iter()
+            .filter(|planet| hosts.iter().any(|(host, _)| *host == planet.hostname))
+            .cloned()
+            .collect::<Vec<_>>();
+        planets.sort_by(|a, b| {
+            b.sy_pnum
+                .cmp(&a.sy_pnum)
+                .then_with(|| a.hostname.cmp(&b.hostname))
+                .then_with(|| a.pl_orbper.is_none().cmp(&b.pl_orbper.is_none()))
+                .then_with(|| {
+                    a.pl_orbper
+                        .partial_cmp(&b.pl_orbper)
+                        .unwrap_or(Ordering::Equal)
+                })
+                .then_with(|| a.pl_name.cmp(&b.pl_name))
+        });
+        Ok(PlanetarySystem::group(planets))
+    }
+
+    pub fn similar(
+        &self,
+        id: i64,
+        parameters: Vec<SimilarityParameter>,
+        count: usize,
+    ) -> Result<Option<Data>, SnapshotError> {
+        let candidates = self
+            .planets
+            .iter()
+            .map(|planet| Candidate {
+                id: planet.id,
+                pl_rade: planet.pl_rade,
+                pl_bmasse: planet.pl_bmasse,
+                pl_orbper: planet.pl_orbper,
+                st_spectype: planet.st_spectype.clone(),
+            })
+            .collect::<Vec<_>>();
+        let ids = most_similar(&candidates, id, &parameters, count)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        Ok(Data::build_sorted(self.planets_by_id(&ids), 0))
+    }
+
+    pub fn planet(&self, id: i64) -> Option<PlanetData> {
+        self.planets
+            .binary_search_by_key(&id, |planet| planet.id)
+            .ok()
+            .map(|index| self.planets[index].clone())
+    }
+
+    /// The planets with the given ids, in the order of `ids`.
+    pub fn planets_by_id(&self, ids: &[i64]) -> Vec<PlanetData> {
+        ids.iter().filter_map(|id| self.planet(*id)).collect()
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, hash::Hash};
+
+/// Summary statistics of one numeric column over a group of planets.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct GroupStats {
+    /// The value of the group-by column, `None` for planets without one.
+    pub group_value: Option<String>,
+    /// The number of planets in the group, including those without a value.
+    pub count: i64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+}
+
+/// Summarises planets' values by group the way the stats query does, for
+/// queries answered in memory. Takes each planet's group and value, and
+/// returns the largest group first, ties in order of group. Groups are
+/// written out once each, so any key that displays as the group will do.
+pub fn summarise<K: Eq + Hash + ToString>(
+    planets: impl IntoIterator<Item = (Option<K>, Option<f64>)>,
+) -> Vec<GroupStats> {
+    let mut groups = HashMap::<Option<K>, (i64, Vec<f64>)>::new();
+    for (group, value) in planets {
+        let (count, values) = groups.entry(group).or_default();
+        *count += 1;
+        values.extend(value);
+    }
+
+    let mut stats = groups
+        .into_iter()
+        .map(|(group, (count, mut values))| {
+            values.sort_by(f64::total_cmp);
+            let n = values.len();
+            GroupStats {
+                group_value: group.map(|group| group.to_string()),
+                count,
+                min: values.first().copied(),
+                max: values.last().copied(),
+                mean: (n > 0).then(|| values.iter().sum::<f64>() / n as f64),
+                median: (n > 0).then(|| (values[(n - 1) / 2] + values[n / 2]) / 2.0),
+            }
+        })
+        .collect::<Vec<_>>();
+    stats.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.group_value.cmp(&b.group_value))
+    });
+    stats
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Statistics {
+    /// The id of the column the planets are grouped by.
+    pub group_by: String,
+    /// The id of the column the statistics are computed over.
+    pub column: String,
+    pub groups: Vec<GroupStats>,
+}
+
+impl Statistics {
+    pub const HEADERS: [&'static str; 6] = ["group", "count", "min", "max", "mean", "median"];
+
+    /// Renders the statistics as CSV, one row per group.
+    pub fn to_csv(&self) -> String {
+        let number = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+        let mut csv = Statistics::HEADERS.join(",");
+        csv.push('\n');
+        for group in &self.groups {
+            let row = [
+                csv_field(group.group_value.as_deref().unwrap_or("")),
+                group.count.to_string(),
+                number(group.min),
+                number(group.max),
+                number(group.mean),
+                number(group.median),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,182 @@
+use crate::model::{
+    data::PlanetData,
+    derived::DerivedQuantity,
+    plot::{Scale, Tick, LEFT, RIGHT},
+};
+use serde::{Deserialize, Serialize};
+
+/// The most systems shown in the system diagram.
+pub const SYSTEM_LIMIT: i64 = 50;
+/// Height of each system's row in the diagram, in pixels.
+pub const ROW_HEIGHT: f64 = 56.0;
+/// Height of the axis below the rows.
+pub const AXIS_HEIGHT: f64 = 40.0;
+const MIN_PLANET_RADIUS: f64 = 2.0;
+const MAX_PLANET_RADIUS: f64 = 16.0;
+/// Marker radius, in pixels, of a planet with no known radius.
+const UNKNOWN_PLANET_RADIUS: f64 = 4.0;
+
+/// The planets orbiting one host, in order of orbital period.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlanetarySystem {
+    pub hostname: String,
+    pub planets: Vec<PlanetData>,
+}
+
+impl PlanetarySystem {
+    /// Groups planets by `hostname`, keeping the order hosts first appear in.
+    pub fn group(planets: Vec<PlanetData>) -> Vec<PlanetarySystem> {
+        let mut systems = Vec::<PlanetarySystem>::new();
+        for planet in planets {
+            match systems
+                .iter_mut()
+                .find(|system| system.hostname == planet.hostname)
+            {
+                Some(system) => system.planets.push(planet),
+                None => systems.push(PlanetarySystem {
+                    hostname: planet.hostname.clone(),
+                    planets: vec![planet],
+                }),
+            }
+        }
+        systems
+    }
+
+    pub fn circumbinary(&self) -> bool {
+        self.planets.iter().any(|planet| planet.cb_flag == Some(1))
+    }
+
+    pub fn star_count(&self) -> i64 {
+        self.planets
+            .iter()
+            .filter_map(|planet| planet.sy_snum)
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+/// The orbital quantity planets are placed by.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum SystemAxis {
+    #[default]
+    Period,
+    SemiMajorAxis,
+}
+
+impl SystemAxis {
+    pub const ALL: [SystemAxis; 2] = [SystemAxis::Period, SystemAxis::SemiMajorAxis];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SystemAxis::Period => "Orbital Period [days]",
+            SystemAxis::SemiMajorAxis => "Orbit Semi-Major Axis [au]",
+        }
+    }
+
+    /// The planet's position on the axis, falling back to the semi-major axis
+    /// derived from Kepler's third law where none is published.
+    pub fn value(&self, planet: &PlanetData) -> Option<f64> {
+        match self {
+            SystemAxis::Period => planet.pl_orbper,
+            SystemAxis::SemiMajorAxis => planet
+                .pl_orbsmax
+                .or_else(|| DerivedQuantity::SemiMajorAxis.compute(planet).value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanetMark {
+    pub id: i64,
+    pub pl_name: String,
+    pub cx: f64,
+    pub cy: f64,
+    pub r: f64,
+    /// Whether the marker has a known radius behind it.
+    pub sized: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemRow {
+    pub hostname: String,
+    /// The vertical centre of the row.
+    pub y: f64,
+    pub planets: Vec<PlanetMark>,
+    /// Planets with no value on the axis, which can't be placed.
+    pub unplaced: Vec<String>,
+    pub circumbinary: bool,
+    pub star_count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemLayout {
+    pub rows: Vec<SystemRow>,
+    pub ticks: Vec<Tick>,
+    pub height: f64,
+}
+
+/// Lays out one row per system on a log scale shared by every system, so
+/// their architectures can be compared, or `None` if no planet can be placed.
+pub fn layout(systems: &[PlanetarySystem], axis: SystemAxis) -> Option<SystemLayout> {
+    let values = systems
+        .iter()
+        .flat_map(|system| &system.planets)
+        .filter_map(|planet| axis.value(planet));
+    let scale = Scale::fit(values, true, LEFT, RIGHT)?;
+
+    // Marker area is proportional to radius, capped at the largest radius
+    // in the systems shown.
+    let largest = systems
+        .iter()
+        .flat_map(|system| &system.planets)
+        .filter_map(|planet| planet.pl_rade)
+        .fold(0.0, f64::max);
+    let marker = |radius: Option<f64>| match radius.filter(|r| *r > 0.0 && largest > 0.0) {
+        Some(radius) => (
+            (MAX_PLANET_RADIUS * (radius / largest).sqrt()).max(MIN_PLANET_RADIUS),
+            true,
+        ),
+        None => (UNKNOWN_PLANET_RADIUS, false),
+    };
+
+    let rows = systems
+        .iter()
+        .enumerate()
+        .map(|(index, system)| {
+            // Leave room above the orbit line for the row's label.
+            let y = ROW_HEIGHT * (index as f64 + 0.5) + 6.0;
+            let mut planets = Vec::new();
+            let mut unplaced = Vec::new();
+            for planet in &system.planets {
+                match axis.value(planet).and_then(|value| scale.project(value)) {
+                    Some(cx) => {
+                        let (r, sized) = marker(planet.pl_rade);
+                        planets.push(PlanetMark {
+                            id: planet.id,
+                            pl_name: planet.pl_name.clone(),
+                            cx,
+                            cy: y,
+                            r,
+                            sized,
+                        });
+                    }
+                    None => unplaced.push(planet.pl_name.clone()),
+                }
+            }
+            SystemRow {
+                hostname: system.hostname.clone(),
+                y,
+                planets,
+                unplaced,
+                circumbinary: system.circumbinary(),
+                star_count: system.star_count(),
+            }
+        })
+        .collect();
+
+    Some(SystemLayout {
+        rows,
+        ticks: scale.ticks(0.0),
+        height: ROW_HEIGHT * systems.len() as f64 + AXIS_HEIGHT,
+    })
+}
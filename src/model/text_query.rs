@@ -0,0 +1,656 @@
+//! A compact text syntax for queries, as an alternative to the builder's
+//! dropdowns, e.g.
+//!
+//! ```text
+//! pl_rade between 1 and 2 and discovery_method in ("Transit") sort pl_bmasse desc
+//! ```
+//!
+//! Filters are `field op value`, with the builder's operators (`=`, `!=`,
+//! `<`, `>`, `<=`, `>=`, `rel_err<` and `consistent`), `field between a and
+//! b` or `field in (a, b, ...)`, joined with `and`. Values with spaces or
+//! punctuation are double quoted. Keywords are case-insensitive.
+//!
+//! Queries parse to the builder's `Query` and `Sort`, and print back to
+//! text, so either can be edited and carried over to the other.
+
+use crate::model::{
+    columns::{find_column, Column},
+    filter::Predicate,
+    input::{Input, Query, Sort, CONSISTENT_OP, IN_OP, RELATIVE_ERROR_OP},
+};
+use std::fmt;
+
+const KEYWORDS: [&str; 7] = [
+    "and",
+    "between",
+    IN_OP,
+    CONSISTENT_OP,
+    "sort",
+    "asc",
+    "desc",
+];
+
+/// The filters and sort order of a text query.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextQuery {
+    pub query: Query,
+    pub sort: Option<Sort>,
+}
+
+/// Why a text query couldn't be parsed, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The character offset of the problem in the text.
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(position: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Word(String),
+    Quoted(String),
+    Op(&'static str),
+    Open,
+    Close,
+    Comma,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: Kind,
+    position: usize,
+}
+
+impl Token {
+    /// Whether the token is the unquoted keyword `keyword`.
+    fn is(&self, keyword: &str) -> bool {
+        matches!(&self.kind, Kind::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn describe(&self) -> String {
+        match &self.kind {
+            Kind::Word(word) => format!("'{word}'"),
+            Kind::Quoted(value) => format!("\"{value}\""),
+            Kind::Op(op) => format!("'{op}'"),
+            Kind::Open => "'('".to_string(),
+            Kind::Close => "')'".to_string(),
+            Kind::Comma => "','".to_string(),
+        }
+    }
+}
+
+/// Whether `c` can appear in an unquoted word.
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '"' | '(' | ')' | ',' | '<' | '>' | '=' | '!')
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let position = i;
+        let kind = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => {
+                i += 1;
+                Kind::Open
+            }
+            ')' => {
+                i += 1;
+                Kind::Close
+            }
+            ',' => {
+                i += 1;
+                Kind::Comma
+            }
+            '"' => {
+                i += 1;
+                let mut value = String::new();
+                loop {
+                    match chars.get(i) {
+                        None => return Err(ParseError::new(position, "unterminated string")),
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.get(i + 1), Some('"' | '\\')) => {
+                            value.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            value.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                Kind::Quoted(value)
+            }
+            '<' | '>' | '=' | '!' => {
+                let op = match (chars[i], chars.get(i + 1)) {
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('!', Some('=')) => "!=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    ('=', _) => "=",
+                    _ => return Err(ParseError::new(position, "expected '!='")),
+                };
+                i += op.chars().count();
+                Kind::Op(op)
+            }
+            _ => {
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                let word = chars[position..i].iter().collect::<String>();
+                // `rel_err<` reads as a word followed by `<`.
+                if word.eq_ignore_ascii_case("rel_err") && chars.get(i) == Some(&'<') {
+                    i += 1;
+                    Kind::Op(RELATIVE_ERROR_OP)
+                } else {
+                    Kind::Word(word)
+                }
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    /// The length of the text, where errors at its end are reported.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn position(&self) -> usize {
+        self.peek().map_or(self.end, |token| token.position)
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let found = self
+            .peek()
+            .map_or("the end of the query".to_string(), Token::describe);
+        ParseError::new(
+            self.position(),
+            format!("expected {expected}, found {found}"),
+        )
+    }
+
+    fn eat(&mut self, keyword: &str) -> bool {
+        let found = self.peek().is_some_and(|token| token.is(keyword));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, kind: Kind, expected: &str) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) if token.kind == kind => {
+                self.next += 1;
+                Ok(())
+            }
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn field(&mut self) -> Result<&'static Column, ParseError> {
+        let position = self.position();
+        match self.peek().map(|token| &token.kind) {
+            Some(Kind::Word(word)) => {
+                let column = find_column(&word.to_ascii_lowercase())
+                    .filter(|column| !column.hidden)
+                    .ok_or_else(|| ParseError::new(position, format!("unknown field '{word}'")))?;
+                self.next += 1;
+                Ok(column)
+            }
+            _ => Err(self.error("a field")),
+        }
+    }
+
+    fn value(&mut self) -> Result<(String, usize), ParseError> {
+        let position = self.position();
+        match self.peek().map(|token| &token.kind) {
+            Some(Kind::Word(value) | Kind::Quoted(value)) => {
+                let value = value.clone();
+                self.next += 1;
+                Ok((value, position))
+            }
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    /// Values separated by commas, up to the end of the tokens.
+    fn values(&mut self) -> Result<Vec<(String, usize)>, ParseError> {
+        let mut values = vec![self.value()?];
+        while self.peek().is_some_and(|token| token.kind == Kind::Comma) {
+            self.next += 1;
+            values.push(self.value()?);
+        }
+        Ok(values)
+    }
+
+    /// Parses one filter, pushing its inputs.
+    fn filter(&mut self, inputs: &mut Vec<Input>) -> Result<(), ParseError> {
+        let column = self.field()?;
+        if self.eat(IN_OP) {
+            self.expect(Kind::Open, "'('")?;
+            let values = self.values()?;
+            self.expect(Kind::Close, "',' or ')'")?;
+            // Check each value as its own, so an error points at it.
+            for (value, position) in &values {
+                Predicate::compile(Input::new(column.id, "=", value))
+                    .map_err(|error| ParseError::new(*position, error.to_string()))?;
+            }
+            let values = values
+                .into_iter()
+                .map(|(value, _)| value)
+                .collect::<Vec<_>>();
+            inputs.push(Input::new(column.id, IN_OP, &list_value(&values)));
+            return Ok(());
+        }
+
+        let mut values = Vec::new();
+        match self.peek().map(|token| token.kind.clone()) {
+            Some(Kind::Op(op)) => {
+                self.next += 1;
+                values.push((op, self.value()?));
+            }
+            _ if self.eat(CONSISTENT_OP) => {
+                values.push((CONSISTENT_OP, self.value()?));
+            }
+            _ if self.eat("between") => {
+                values.push((">=", self.value()?));
+                if !self.eat("and") {
+                    return Err(self.error("'and'"));
+                }
+                values.push(("<=", self.value()?));
+            }
+            _ => return Err(self.error("an operator, 'between' or 'in'")),
+        };
+
+        for (comparison_op, (value, value_position)) in values {
+            let input = Input::new(column.id, comparison_op, &value);
+            // Check the value here, so its error points at it.
+            Predicate::compile(input.clone())
+                .map_err(|error| ParseError::new(value_position, error.to_string()))?;
+            inputs.push(input);
+        }
+        Ok(())
+    }
+
+    fn query(&mut self) -> Result<TextQuery, ParseError> {
+        let mut inputs = Vec::new();
+        if self.peek().is_some_and(|token| !token.is("sort")) {
+            self.filter(&mut inputs)?;
+            while self.eat("and") {
+                self.filter(&mut inputs)?;
+            }
+        }
+
+        let mut sort = None;
+        if self.eat("sort") {
+            let column = self.field()?;
+            let descending = self.eat("desc");
+            if !descending {
+                self.eat("asc");
+            }
+            sort = Some(Sort {
+                field: column.id.to_string(),
+                descending,
+            });
+        }
+
+        if self.peek().is_some() {
+            return Err(self.error(match sort {
+                Some(_) => "the end of the query",
+                None => "'and' or 'sort'",
+            }));
+        }
+        Ok(TextQuery {
+            query: Query { inputs },
+            sort,
+        })
+    }
+}
+
+/// Parses a text query, checking its fields, operators and values.
+pub fn parse(text: &str) -> Result<TextQuery, ParseError> {
+    Parser {
+        tokens: tokenize(text)?,
+        next: 0,
+        end: text.chars().count(),
+    }
+    .query()
+}
+
+/// Parses the value of an `in` filter: values separated by commas.
+pub fn parse_list(text: &str) -> Result<Vec<String>, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        next: 0,
+        end: text.chars().count(),
+    };
+    let values = parser.values()?;
+    if parser.peek().is_some() {
+        return Err(parser.error("',' or the end of the list"));
+    }
+    Ok(values.into_iter().map(|(value, _)| value).collect())
+}
+
+/// The value of an `in` filter on `values`, which [`parse_list`] reads back.
+pub fn list_value(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| quote(value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `value` as a word if it reads back as one, or quoted otherwise.
+fn quote(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value.chars().all(is_word_char)
+        && !KEYWORDS
+            .iter()
+            .any(|keyword| value.eq_ignore_ascii_case(keyword))
+        && !value.eq_ignore_ascii_case("rel_err");
+    match bare {
+        true => value.to_string(),
+        false => format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")),
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "{}", quote(value))
+}
+
+impl fmt::Display for TextQuery {
+    /// Prints the query in the text syntax, skipping inputs the builder
+    /// hasn't filled in a field or operator for.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inputs = self
+            .query
+            .inputs
+            .iter()
+            .filter(|input| !input.field.is_empty() && !input.comparison_op.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut i = 0;
+        while i < inputs.len() {
+            if i > 0 {
+                write!(f, " and ")?;
+            }
+            let input = inputs[i];
+            write!(f, "{} ", input.field)?;
+            match inputs.get(i + 1) {
+                // `>=` then `<=` on the same field is what `between` parses to.
+                Some(upper)
+                    if input.comparison_op == ">="
+                        && upper.comparison_op == "<="
+                        && upper.field == input.field =>
+                {
+                    write!(f, "between ")?;
+                    write_value(f, &input.value)?;
+                    write!(f, " and ")?;
+                    write_value(f, &upper.value)?;
+                    i += 2;
+                    continue;
+                }
+                // Lists the builder can't parse are printed as typed, to be
+                // fixed in the text.
+                _ if input.comparison_op == IN_OP => match parse_list(&input.value) {
+                    Ok(values) => write!(f, "in ({})", list_value(&values))?,
+                    Err(_) => write!(f, "in ({})", input.value)?,
+                },
+                _ => {
+                    write!(f, "{} ", input.comparison_op)?;
+                    write_value(f, &input.value)?;
+                }
+            }
+            i += 1;
+        }
+
+        if let Some(sort) = &self.sort {
+            if !inputs.is_empty() {
+                write!(f, " ")?;
+            }
+            let order = if sort.descending { "desc" } else { "asc" };
+            write!(f, "sort {} {order}", sort.field)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(inputs: Vec<Input>, sort: Option<(&str, bool)>) -> TextQuery {
+        TextQuery {
+            query: Query { inputs },
+            sort: sort.map(|(field, descending)| Sort {
+                field: field.to_string(),
+                descending,
+            }),
+        }
+    }
+
+    /// Checks `query` prints as `text` and parses back to itself.
+    fn round_trip(query: TextQuery, text: &str) {
+        assert_eq!(query.to_string(), text);
+        assert_eq!(parse(text), Ok(query));
+    }
+
+    fn error_at(text: &str) -> usize {
+        parse(text).unwrap_err().position
+    }
+
+    #[test]
+    fn comparisons_round_trip() {
+        round_trip(
+            query(
+                vec![
+                    Input::new("disc_year", ">", "2010"),
+                    Input::new("hostname", "!=", "TRAPPIST-1"),
+                ],
+                Some(("pl_bmasse", true)),
+            ),
+            "disc_year > 2010 and hostname != TRAPPIST-1 sort pl_bmasse desc",
+        );
+        round_trip(
+            query(Vec::new(), Some(("pl_rade", false))),
+            "sort pl_rade asc",
+        );
+        round_trip(query(Vec::new(), None), "");
+    }
+
+    #[test]
+    fn between_round_trips() {
+        round_trip(
+            query(
+                vec![
+                    Input::new("pl_rade", ">=", "1"),
+                    Input::new("pl_rade", "<=", "2"),
+                ],
+                None,
+            ),
+            "pl_rade between 1 and 2",
+        );
+        // Bounds on different fields stay separate filters.
+        round_trip(
+            query(
+                vec![
+                    Input::new("pl_rade", ">=", "1"),
+                    Input::new("pl_bmasse", "<=", "2"),
+                ],
+                None,
+            ),
+            "pl_rade >= 1 and pl_bmasse <= 2",
+        );
+    }
+
+    #[test]
+    fn uncertainty_operators_round_trip() {
+        round_trip(
+            query(vec![Input::new("pl_rade", RELATIVE_ERROR_OP, "10%")], None),
+            "pl_rade rel_err< 10%",
+        );
+        round_trip(
+            query(
+                vec![Input::new("pl_rade", CONSISTENT_OP, "1.0 Rjup, 2σ")],
+                None,
+            ),
+            "pl_rade consistent \"1.0 Rjup, 2σ\"",
+        );
+        round_trip(
+            query(vec![Input::new("pl_rade", CONSISTENT_OP, "1.5")], None),
+            "pl_rade consistent 1.5",
+        );
+    }
+
+    #[test]
+    fn quoted_values_round_trip() {
+        round_trip(
+            query(vec![Input::new("hostname", "=", "51 Peg")], None),
+            "hostname = \"51 Peg\"",
+        );
+        round_trip(
+            query(vec![Input::new("hostname", "=", "say \"hi\" \\ bye")], None),
+            "hostname = \"say \\\"hi\\\" \\\\ bye\"",
+        );
+        round_trip(
+            query(vec![Input::new("hostname", "=", "a(b),c<d")], None),
+            "hostname = \"a(b),c<d\"",
+        );
+        round_trip(
+            query(vec![Input::new("hostname", "=", "")], None),
+            "hostname = \"\"",
+        );
+    }
+
+    #[test]
+    fn keywords_as_values_round_trip() {
+        for keyword in [
+            "and",
+            "between",
+            "consistent",
+            "sort",
+            "ASC",
+            "desc",
+            "rel_err",
+            "in",
+        ] {
+            round_trip(
+                query(vec![Input::new("hostname", "=", keyword)], None),
+                &format!("hostname = \"{keyword}\""),
+            );
+        }
+    }
+
+    #[test]
+    fn fields_and_keywords_are_case_insensitive() {
+        assert_eq!(
+            parse("PL_RADE BETWEEN 1 AND 2 SORT Disc_Year DESC"),
+            Ok(query(
+                vec![
+                    Input::new("pl_rade", ">=", "1"),
+                    Input::new("pl_rade", "<=", "2")
+                ],
+                Some(("disc_year", true)),
+            ))
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        assert_eq!(error_at("pl_radius > 1"), 0);
+        assert_eq!(error_at("pl_rade > 1 and hostname"), 24);
+        assert_eq!(error_at("pl_rade >"), 9);
+        assert_eq!(error_at("disc_year = soon"), 12);
+        assert_eq!(error_at("pl_rade between 1 or 2"), 18);
+        assert_eq!(error_at("hostname = \"51 Peg"), 11);
+        assert_eq!(error_at("pl_rade ! 1"), 8);
+        assert_eq!(error_at("pl_rade > 1 sort pl_rade desc pl_bmasse"), 30);
+        // Positions count characters, not bytes.
+        assert_eq!(error_at("hostname = \"σ\" and σ"), 19);
+    }
+
+    #[test]
+    fn in_lists_round_trip() {
+        assert_eq!(
+            parse(
+                "pl_rade between 1 and 2 and discovery_method in (\"Transit\") \
+                sort pl_bmasse desc"
+            ),
+            Ok(query(
+                vec![
+                    Input::new("pl_rade", ">=", "1"),
+                    Input::new("pl_rade", "<=", "2"),
+                    Input::new("discovery_method", IN_OP, "Transit"),
+                ],
+                Some(("pl_bmasse", true)),
+            ))
+        );
+        round_trip(
+            query(
+                vec![Input::new(
+                    "discovery_method",
+                    IN_OP,
+                    "Transit, \"Radial Velocity\"",
+                )],
+                None,
+            ),
+            "discovery_method in (Transit, \"Radial Velocity\")",
+        );
+        // The builder's lists print as they parse.
+        assert_eq!(
+            query(
+                vec![Input::new("discovery_method", IN_OP, "\"Imaging\",Transit")],
+                None
+            )
+            .to_string(),
+            "discovery_method in (Imaging, Transit)"
+        );
+        assert_eq!(
+            parse_list("Transit, \"Radial Velocity\""),
+            Ok(vec!["Transit".to_string(), "Radial Velocity".to_string()])
+        );
+        assert!(parse_list("").is_err());
+        assert!(parse_list("Transit Imaging").is_err());
+    }
+
+    #[test]
+    fn in_lists_point_at_their_errors() {
+        assert_eq!(error_at("discovery_method in Transit"), 20);
+        assert_eq!(error_at("discovery_method in (Transit"), 28);
+        assert_eq!(error_at("discovery_method in ()"), 21);
+        assert_eq!(error_at("disc_year in (2010, soon)"), 20);
+    }
+}
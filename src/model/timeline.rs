@@ -0,0 +1,231 @@
+use crate::model::plot::{method_color, Scale, Tick, BOTTOM, LEFT, RIGHT, TOP};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The most categories stacked before the smallest are merged into
+/// [`OTHER`].
+pub const MAX_CATEGORIES: usize = 8;
+pub const OTHER: &str = "Other";
+pub const UNKNOWN: &str = "Unknown";
+
+/// Colours of facilities, which unlike discovery methods are too many to
+/// assign fixed colours.
+const PALETTE: [&str; MAX_CATEGORIES - 1] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2",
+];
+const OTHER_COLOR: &str = "#7f7f7f";
+
+/// The column discoveries are stacked by.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum StackBy {
+    #[default]
+    Method,
+    Facility,
+}
+
+impl StackBy {
+    pub const ALL: [StackBy; 2] = [StackBy::Method, StackBy::Facility];
+
+    /// The id of the column in the catalog.
+    pub fn column(&self) -> &'static str {
+        match self {
+            StackBy::Method => "discovery_method",
+            StackBy::Facility => "disc_facility",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StackBy::Method => "Discovery Method",
+            StackBy::Facility => "Discovery Facility",
+        }
+    }
+}
+
+/// The number of planets discovered in a year by one method or facility.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct YearCount {
+    pub year: i64,
+    pub category: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Timeline {
+    pub stack_by: StackBy,
+    pub counts: Vec<YearCount>,
+}
+
+/// One category's segment of a year's bar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackedBar {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub color: &'static str,
+    pub year: i64,
+    pub category: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineLayout {
+    pub bars: Vec<StackedBar>,
+    pub x_ticks: Vec<Tick>,
+    pub y_ticks: Vec<Tick>,
+    pub legend: Vec<(String, &'static str)>,
+}
+
+impl Timeline {
+    pub fn total(&self) -> i64 {
+        self.counts.iter().map(|count| count.count).sum()
+    }
+
+    /// Every year from the first discovery to the last.
+    pub fn years(&self) -> Vec<i64> {
+        let first = self.counts.iter().map(|count| count.year).min();
+        let last = self.counts.iter().map(|count| count.year).max();
+        match (first, last) {
+            (Some(first), Some(last)) => (first..=last).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The category a count is stacked under.
+    fn category<'a>(&self, count: &'a YearCount, shown: &[String]) -> &'a str {
+        let category = count.category.as_deref().unwrap_or(UNKNOWN);
+        match shown.iter().any(|shown| shown == category) {
+            true => category,
+            false => OTHER,
+        }
+    }
+
+    /// The categories stacked, largest first, with the smallest merged into
+    /// [`OTHER`] when there are more than [`MAX_CATEGORIES`].
+    pub fn categories(&self) -> Vec<String> {
+        let mut totals = HashMap::<&str, i64>::new();
+        for count in &self.counts {
+            *totals
+                .entry(count.category.as_deref().unwrap_or(UNKNOWN))
+                .or_default() += count.count;
+        }
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by(|(a, a_total), (b, b_total)| b_total.cmp(a_total).then(a.cmp(b)));
+
+        let mut categories = totals
+            .into_iter()
+            .map(|(category, _)| category.to_string())
+            .collect::<Vec<_>>();
+        if categories.len() > MAX_CATEGORIES {
+            categories.truncate(MAX_CATEGORIES - 1);
+            categories.push(OTHER.to_string());
+        }
+        categories
+    }
+
+    /// The counts of each category for every year in [`Timeline::years`],
+    /// summed over the years so far if `cumulative` is set.
+    pub fn series(&self, cumulative: bool) -> Vec<(String, Vec<i64>)> {
+        let years = self.years();
+        let categories = self.categories();
+        let mut series = categories
+            .iter()
+            .map(|category| (category.clone(), vec![0; years.len()]))
+            .collect::<Vec<_>>();
+
+        for count in &self.counts {
+            let category = self.category(count, &categories);
+            let index = (count.year - years[0]) as usize;
+            if let Some((_, values)) = series.iter_mut().find(|(name, _)| name == category) {
+                values[index] += count.count;
+            }
+        }
+
+        if cumulative {
+            for (_, values) in &mut series {
+                let mut total = 0;
+                for value in values.iter_mut() {
+                    total += *value;
+                    *value = total;
+                }
+            }
+        }
+        series
+    }
+
+    pub fn color(&self, category: &str, index: usize) -> &'static str {
+        match (self.stack_by, category) {
+            (_, OTHER | UNKNOWN) => OTHER_COLOR,
+            (StackBy::Method, method) => method_color(Some(method)),
+            (StackBy::Facility, _) => PALETTE.get(index).copied().unwrap_or(OTHER_COLOR),
+        }
+    }
+
+    /// Lays out a bar per year with a segment per category, or `None` if no
+    /// planets have a discovery year.
+    pub fn layout(&self, cumulative: bool) -> Option<TimelineLayout> {
+        let years = self.years();
+        if years.is_empty() {
+            return None;
+        }
+        let series = self.series(cumulative);
+
+        let totals = (0..years.len())
+            .map(|index| series.iter().map(|(_, values)| values[index]).sum::<i64>())
+            .collect::<Vec<_>>();
+        let max_total = totals.iter().copied().max().unwrap_or(0).max(1);
+        let y_scale = Scale::linear(0.0, max_total as f64, BOTTOM, TOP);
+
+        let band = (RIGHT - LEFT) / years.len() as f64;
+        let mut bars = Vec::new();
+        for (index, year) in years.iter().enumerate() {
+            let mut stacked = 0;
+            for (category_index, (category, values)) in series.iter().enumerate() {
+                let count = values[index];
+                if count == 0 {
+                    continue;
+                }
+                let bottom = y_scale.position(stacked as f64);
+                stacked += count;
+                let top = y_scale.position(stacked as f64);
+                bars.push(StackedBar {
+                    x: LEFT + band * (index as f64 + 0.1),
+                    y: top,
+                    width: band * 0.8,
+                    height: bottom - top,
+                    color: self.color(category, category_index),
+                    year: *year,
+                    category: category.clone(),
+                    count,
+                });
+            }
+        }
+
+        // Label at most about ten years so the labels don't overlap.
+        let step = years.len().div_ceil(10);
+        let x_ticks = years
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| index % step == 0)
+            .map(|(index, year)| Tick {
+                position: LEFT + band * (index as f64 + 0.5),
+                label: year.to_string(),
+            })
+            .collect();
+
+        let legend = series
+            .iter()
+            .enumerate()
+            .map(|(index, (category, _))| (category.clone(), self.color(category, index)))
+            .collect();
+
+        Some(TimelineLayout {
+            bars,
+            x_ticks,
+            y_ticks: y_scale.ticks(1.0),
+            legend,
+        })
+    }
+}
@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Earth radii per Jupiter radius, as used by the NASA Exoplanet Archive.
+pub const EARTH_RADII_PER_JUPITER: f64 = 11.2089;
+/// Earth masses per Jupiter mass, as used by the NASA Exoplanet Archive.
+pub const EARTH_MASSES_PER_JUPITER: f64 = 317.8;
+pub const DAYS_PER_YEAR: f64 = 365.25;
+/// Astronomical units per solar radius.
+pub const AU_PER_SOLAR_RADIUS: f64 = 0.004_650_467;
+pub const LIGHT_YEARS_PER_PARSEC: f64 = 3.261_564;
+
+/// The physical quantity a column measures. Values of a quantity are stored
+/// in the database in its canonical unit.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Quantity {
+    PlanetRadius,
+    PlanetMass,
+    Period,
+    StellarRadius,
+    StellarMass,
+    Temperature,
+    OrbitalDistance,
+    Distance,
+    Density,
+    SurfaceGravity,
+}
+
+impl Quantity {
+    pub fn canonical_unit(&self) -> Unit {
+        match self {
+            Quantity::PlanetRadius => Unit::EarthRadius,
+            Quantity::PlanetMass => Unit::EarthMass,
+            Quantity::Period => Unit::Day,
+            Quantity::StellarRadius => Unit::SolarRadius,
+            Quantity::StellarMass => Unit::SolarMass,
+            Quantity::Temperature => Unit::Kelvin,
+            Quantity::OrbitalDistance => Unit::Au,
+            Quantity::Distance => Unit::Parsec,
+            Quantity::Density => Unit::GramPerCubicCm,
+            Quantity::SurfaceGravity => Unit::MeterPerSecondSquared,
+        }
+    }
+
+    pub fn units(&self) -> &'static [Unit] {
+        match self {
+            Quantity::PlanetRadius => &[Unit::EarthRadius, Unit::JupiterRadius],
+            Quantity::PlanetMass => &[Unit::EarthMass, Unit::JupiterMass],
+            Quantity::Period => &[Unit::Day, Unit::Year],
+            Quantity::StellarRadius => &[Unit::SolarRadius],
+            Quantity::StellarMass => &[Unit::SolarMass],
+            Quantity::Temperature => &[Unit::Kelvin],
+            Quantity::OrbitalDistance => &[Unit::Au],
+            Quantity::Distance => &[Unit::Parsec, Unit::LightYear],
+            Quantity::Density => &[Unit::GramPerCubicCm],
+            Quantity::SurfaceGravity => &[Unit::MeterPerSecondSquared],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Unit {
+    EarthRadius,
+    JupiterRadius,
+    EarthMass,
+    JupiterMass,
+    Day,
+    Year,
+    SolarRadius,
+    SolarMass,
+    Kelvin,
+    Au,
+    Parsec,
+    LightYear,
+    GramPerCubicCm,
+    MeterPerSecondSquared,
+}
+
+impl Unit {
+    pub fn quantity(&self) -> Quantity {
+        match self {
+            Unit::EarthRadius | Unit::JupiterRadius => Quantity::PlanetRadius,
+            Unit::EarthMass | Unit::JupiterMass => Quantity::PlanetMass,
+            Unit::Day | Unit::Year => Quantity::Period,
+            Unit::SolarRadius => Quantity::StellarRadius,
+            Unit::SolarMass => Quantity::StellarMass,
+            Unit::Kelvin => Quantity::Temperature,
+            Unit::Au => Quantity::OrbitalDistance,
+            Unit::Parsec | Unit::LightYear => Quantity::Distance,
+            Unit::GramPerCubicCm => Quantity::Density,
+            Unit::MeterPerSecondSquared => Quantity::SurfaceGravity,
+        }
+    }
+
+    /// How many of the quantity's canonical unit one of this unit is.
+    pub fn factor(&self) -> f64 {
+        match self {
+            Unit::EarthRadius
+            | Unit::EarthMass
+            | Unit::Day
+            | Unit::SolarRadius
+            | Unit::SolarMass
+            | Unit::Kelvin
+            | Unit::Au
+            | Unit::Parsec
+            | Unit::GramPerCubicCm
+            | Unit::MeterPerSecondSquared => 1.0,
+            Unit::JupiterRadius => EARTH_RADII_PER_JUPITER,
+            Unit::JupiterMass => EARTH_MASSES_PER_JUPITER,
+            Unit::Year => DAYS_PER_YEAR,
+            Unit::LightYear => 1.0 / LIGHT_YEARS_PER_PARSEC,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::EarthRadius => "Rearth",
+            Unit::JupiterRadius => "Rjup",
+            Unit::EarthMass => "Mearth",
+            Unit::JupiterMass => "Mjup",
+            Unit::Day => "days",
+            Unit::Year => "years",
+            Unit::SolarRadius => "Rsun",
+            Unit::SolarMass => "Msun",
+            Unit::Kelvin => "K",
+            Unit::Au => "AU",
+            Unit::Parsec => "pc",
+            Unit::LightYear => "ly",
+            Unit::GramPerCubicCm => "g/cm3",
+            Unit::MeterPerSecondSquared => "m/s2",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Unit::EarthRadius => "Earth Radius",
+            Unit::JupiterRadius => "Jupiter Radius",
+            Unit::EarthMass => "Earth Mass",
+            Unit::JupiterMass => "Jupiter Mass",
+            Unit::Day => "days",
+            Unit::Year => "years",
+            Unit::SolarRadius => "Solar Radius",
+            Unit::SolarMass => "Solar Mass",
+            Unit::Kelvin => "K",
+            Unit::Au => "AU",
+            Unit::Parsec => "pc",
+            Unit::LightYear => "light years",
+            Unit::GramPerCubicCm => "g/cm³",
+            Unit::MeterPerSecondSquared => "m/s²",
+        }
+    }
+
+    /// Parses a unit suffix, accepting the symbol and a few common spellings.
+    pub fn parse(symbol: &str) -> Option<Unit> {
+        match symbol.to_ascii_lowercase().as_str() {
+            "re" | "rearth" | "r_earth" | "earth radius" | "earth radii" => Some(Unit::EarthRadius),
+            "rj" | "rjup" | "r_jup" | "jupiter radius" | "jupiter radii" => {
+                Some(Unit::JupiterRadius)
+            }
+            "me" | "mearth" | "m_earth" | "earth mass" | "earth masses" => Some(Unit::EarthMass),
+            "mj" | "mjup" | "m_jup" | "jupiter mass" | "jupiter masses" => Some(Unit::JupiterMass),
+            "d" | "day" | "days" => Some(Unit::Day),
+            "y" | "yr" | "year" | "years" => Some(Unit::Year),
+            "rsun" | "r_sun" | "solar radius" | "solar radii" => Some(Unit::SolarRadius),
+            "msun" | "m_sun" | "solar mass" | "solar masses" => Some(Unit::SolarMass),
+            "k" | "kelvin" => Some(Unit::Kelvin),
+            "au" => Some(Unit::Au),
+            "pc" | "parsec" | "parsecs" => Some(Unit::Parsec),
+            "ly" | "light year" | "light years" => Some(Unit::LightYear),
+            "g/cm3" | "g/cm^3" | "g/cc" => Some(Unit::GramPerCubicCm),
+            "m/s2" | "m/s^2" => Some(Unit::MeterPerSecondSquared),
+            _ => None,
+        }
+    }
+
+    pub fn to_canonical(&self, value: f64) -> f64 {
+        value * self.factor()
+    }
+
+    pub fn from_canonical(&self, value: f64) -> f64 {
+        value / self.factor()
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// The set of units measurements are displayed in.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum UnitSystem {
+    /// Earth radii, Earth masses and days, as stored in the archive.
+    #[default]
+    Terrestrial,
+    /// Jupiter radii, Jupiter masses and years.
+    Jovian,
+}
+
+impl UnitSystem {
+    pub const ALL: [UnitSystem; 2] = [UnitSystem::Terrestrial, UnitSystem::Jovian];
+
+    pub fn unit_for(&self, quantity: Quantity) -> Unit {
+        match (self, quantity) {
+            (UnitSystem::Terrestrial, quantity) => quantity.canonical_unit(),
+            (UnitSystem::Jovian, Quantity::PlanetRadius) => Unit::JupiterRadius,
+            (UnitSystem::Jovian, Quantity::PlanetMass) => Unit::JupiterMass,
+            (UnitSystem::Jovian, Quantity::Period) => Unit::Year,
+            (UnitSystem::Jovian, quantity) => quantity.canonical_unit(),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnitSystem::Terrestrial => "terrestrial",
+            UnitSystem::Jovian => "jovian",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UnitSystem::Terrestrial => "Earth / days",
+            UnitSystem::Jovian => "Jupiter / years",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<UnitSystem> {
+        UnitSystem::ALL
+            .into_iter()
+            .find(|system| system.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnitError {
+    InvalidNumber(String),
+    UnknownUnit(String),
+    WrongQuantity { unit: Unit, expected: Quantity },
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitError::InvalidNumber(value) => write!(f, "'{value}' is not a number"),
+            UnitError::UnknownUnit(unit) => write!(f, "unknown unit '{unit}'"),
+            UnitError::WrongQuantity { unit, expected } => {
+                let accepted = expected
+                    .units()
+                    .iter()
+                    .map(Unit::symbol)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "unit '{unit}' cannot be used here, expected one of: {accepted}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+/// Parses a value such as `1.2 Mjup` or `3.5` into the canonical unit of
+/// `quantity`. A bare number is taken to already be in the canonical unit.
+pub fn parse_value(input: &str, quantity: Quantity) -> Result<f64, UnitError> {
+    let input = input.trim();
+    // The number is the longest prefix that parses, so exponents like `1e3`
+    // are kept while `1.2Mjup` and `5 earth radii` split where expected.
+    let (value, unit) = input
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(input.len()))
+        .rev()
+        .find_map(|index| {
+            input[..index]
+                .parse::<f64>()
+                .ok()
+                .map(|value| (value, &input[index..]))
+        })
+        .ok_or_else(|| UnitError::InvalidNumber(input.to_string()))?;
+
+    let unit = match unit.trim() {
+        "" => quantity.canonical_unit(),
+        symbol => Unit::parse(symbol).ok_or_else(|| UnitError::UnknownUnit(symbol.to_string()))?,
+    };
+
+    if unit.quantity() != quantity {
+        return Err(UnitError::WrongQuantity {
+            unit,
+            expected: quantity,
+        });
+    }
+
+    Ok(unit.to_canonical(value))
+}
+
+/// Converts a value stored in the canonical unit of `quantity` to the unit
+/// `system` displays it in.
+pub fn convert(value: f64, quantity: Quantity, system: UnitSystem) -> f64 {
+    system.unit_for(quantity).from_canonical(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    #[test]
+    fn bare_numbers_are_in_the_canonical_unit() {
+        assert_eq!(parse_value("1.5", Quantity::PlanetRadius), Ok(1.5));
+        assert_eq!(parse_value("  42 ", Quantity::Period), Ok(42.0));
+        assert_eq!(parse_value("1e3", Quantity::Temperature), Ok(1000.0));
+        assert_eq!(parse_value("-2.5e-1", Quantity::Density), Ok(-0.25));
+    }
+
+    #[test]
+    fn suffixes_convert_to_the_canonical_unit() {
+        let radius = parse_value("1 Rjup", Quantity::PlanetRadius).unwrap();
+        assert!(close(radius, EARTH_RADII_PER_JUPITER));
+        let mass = parse_value("2mj", Quantity::PlanetMass).unwrap();
+        assert!(close(mass, 2.0 * EARTH_MASSES_PER_JUPITER));
+        let period = parse_value("1.5 years", Quantity::Period).unwrap();
+        assert!(close(period, 1.5 * DAYS_PER_YEAR));
+        let distance = parse_value("10 ly", Quantity::Distance).unwrap();
+        assert!(close(distance, 10.0 / LIGHT_YEARS_PER_PARSEC));
+        assert_eq!(
+            parse_value("5 earth radii", Quantity::PlanetRadius),
+            Ok(5.0)
+        );
+        assert_eq!(parse_value("3 g/cm^3", Quantity::Density), Ok(3.0));
+    }
+
+    #[test]
+    fn bad_values_are_explained() {
+        assert_eq!(
+            parse_value("big", Quantity::PlanetRadius),
+            Err(UnitError::InvalidNumber("big".to_string()))
+        );
+        assert_eq!(
+            parse_value("", Quantity::PlanetRadius),
+            Err(UnitError::InvalidNumber(String::new()))
+        );
+        assert_eq!(
+            parse_value("3 furlongs", Quantity::OrbitalDistance),
+            Err(UnitError::UnknownUnit("furlongs".to_string()))
+        );
+        let error = parse_value("1 Mjup", Quantity::PlanetRadius).unwrap_err();
+        assert_eq!(
+            error,
+            UnitError::WrongQuantity {
+                unit: Unit::JupiterMass,
+                expected: Quantity::PlanetRadius,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "unit 'Mjup' cannot be used here, expected one of: Rearth, Rjup"
+        );
+    }
+
+    #[test]
+    fn terrestrial_units_are_canonical() {
+        for unit in [
+            Unit::EarthRadius,
+            Unit::EarthMass,
+            Unit::Day,
+            Unit::SolarRadius,
+            Unit::Parsec,
+        ] {
+            let quantity = unit.quantity();
+            assert_eq!(UnitSystem::Terrestrial.unit_for(quantity), unit);
+            assert_eq!(convert(12.5, quantity, UnitSystem::Terrestrial), 12.5);
+        }
+    }
+
+    #[test]
+    fn jovian_units_convert_planets_and_periods() {
+        let table = [
+            (Quantity::PlanetRadius, Unit::JupiterRadius),
+            (Quantity::PlanetMass, Unit::JupiterMass),
+            (Quantity::Period, Unit::Year),
+            // Stellar and other quantities keep their canonical unit.
+            (Quantity::StellarMass, Unit::SolarMass),
+            (Quantity::Distance, Unit::Parsec),
+        ];
+        for (quantity, unit) in table {
+            assert_eq!(UnitSystem::Jovian.unit_for(quantity), unit);
+        }
+        assert!(close(
+            convert(
+                EARTH_RADII_PER_JUPITER,
+                Quantity::PlanetRadius,
+                UnitSystem::Jovian
+            ),
+            1.0
+        ));
+        assert!(close(
+            convert(317.8, Quantity::PlanetMass, UnitSystem::Jovian),
+            1.0
+        ));
+        assert!(close(
+            convert(730.5, Quantity::Period, UnitSystem::Jovian),
+            2.0
+        ));
+    }
+
+    #[test]
+    fn units_round_trip() {
+        for quantity in [
+            Quantity::PlanetRadius,
+            Quantity::PlanetMass,
+            Quantity::Period,
+            Quantity::Distance,
+        ] {
+            for unit in quantity.units() {
+                assert_eq!(unit.quantity(), quantity);
+                assert_eq!(Unit::parse(unit.symbol()), Some(*unit));
+                assert!(close(unit.from_canonical(unit.to_canonical(3.7)), 3.7));
+            }
+        }
+        for system in UnitSystem::ALL {
+            assert_eq!(UnitSystem::parse(system.as_str()), Some(system));
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Signed-in users and what they keep between visits.
+
+use crate::model::input::{Input, Sort};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The most queries kept in a user's history.
+pub const HISTORY_LIMIT: usize = 50;
+
+/// What a user may do. Each role can do everything the ones before it can.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Runs queries, statistics, plots and exports.
+    #[default]
+    Viewer,
+    /// Also runs statements in the SQL console.
+    Analyst,
+    /// Also imports data, manages the cache and sets users' roles.
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Viewer, Role::Analyst, Role::Admin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Analyst => "analyst",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Role> {
+        Role::ALL.into_iter().find(|role| role.as_str() == name)
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub role: Role,
+}
+
+/// Whether sign-in is available, how, and who is signed in.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct AuthStatus {
+    /// Set when the server has a user database.
+    pub enabled: bool,
+    /// Set when an OpenID Connect provider is configured.
+    pub oidc: bool,
+    pub user: Option<User>,
+    /// What the visitor may do: their role when signed in, viewer when not,
+    /// and admin when sign-in is off.
+    pub role: Role,
+}
+
+/// A query kept under a name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedQuery {
+    pub id: i64,
+    pub name: String,
+    pub query: Vec<Input>,
+    pub sort: Option<Sort>,
+}
+
+/// A query the user ran.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub query: Vec<Input>,
+    pub sort: Option<Sort>,
+    /// When it ran, in seconds since the Unix epoch.
+    pub ran_at: i64,
+}
@@ -0,0 +1,87 @@
+//! Serves plots rendered entirely on the server, so links to live plots can
+//! be embedded in slides and wiki pages.
+//!
+//! `/plot.svg` and `/plot.png` take a [`PlotParams`] query string, e.g.
+//! `/plot.svg?x=pl_rade&y=pl_bmasse&log_y=true&query[0][field]=disc_year&query[0][comparison_op]=>&query[0][value]=2015`.
+
+use crate::{
+    api::{fetch_plot, plot_query},
+    config::Config,
+    model::plot::{render_svg, PlotParams, HEIGHT, WIDTH},
+};
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound},
+    get,
+    http::header::ContentEncoding,
+    web, HttpRequest, HttpResponse,
+};
+use resvg::{
+    tiny_skia,
+    usvg::{self, fontdb, TreeParsing, TreeTextToPath},
+};
+use std::sync::OnceLock;
+
+/// Nesting depth of the query string, enough for `query[0][field]`.
+const QUERY_DEPTH: usize = 5;
+
+#[get("/plot.{format}")]
+pub async fn plot_image(
+    req: HttpRequest,
+    format: web::Path<String>,
+    config: web::Data<Config>,
+) -> actix_web::Result<HttpResponse> {
+    let params: PlotParams = serde_qs::Config::new(QUERY_DEPTH, false)
+        .deserialize_str(req.query_string())
+        .map_err(ErrorBadRequest)?;
+    let y = params.y.clone().unwrap_or_else(|| params.x.clone());
+
+    // Bad axes or filters are the caller's to fix, so they hear why; what
+    // goes wrong after that stays in the log.
+    let plot = plot_query(&config, params.query.clone(), params.x.clone(), y)
+        .map_err(|error| ErrorBadRequest(error.to_string()))?;
+    let data = fetch_plot(&config, plot).await.map_err(|error| {
+        leptos::logging::error!("{}", error);
+        ErrorInternalServerError("the plot couldn't be rendered")
+    })?;
+    let svg = render_svg(&data, &params);
+
+    match format.as_str() {
+        "svg" => Ok(HttpResponse::Ok().content_type("image/svg+xml").body(svg)),
+        "png" => {
+            // Rasterising takes long enough to stall the worker's other
+            // requests, so it runs on the blocking pool.
+            let png = web::block(move || render_png(&svg)).await??;
+            Ok(HttpResponse::Ok()
+                .content_type("image/png")
+                // PNGs are already compressed.
+                .insert_header(ContentEncoding::Identity)
+                .body(png))
+        }
+        format => Err(ErrorNotFound(format!("unsupported plot format '{format}'"))),
+    }
+}
+
+/// The font plots are drawn in, bundled so PNGs look the same on every
+/// server whatever fonts it has installed.
+const FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+fn fonts() -> &'static fontdb::Database {
+    static FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut fonts = fontdb::Database::new();
+        fonts.load_font_data(FONT.to_vec());
+        fonts.set_sans_serif_family("DejaVu Sans");
+        fonts
+    })
+}
+
+fn render_png(svg: &str) -> actix_web::Result<Vec<u8>> {
+    let mut tree =
+        usvg::Tree::from_str(svg, &usvg::Options::default()).map_err(ErrorInternalServerError)?;
+    tree.convert_text(fonts());
+
+    let mut pixmap = tiny_skia::Pixmap::new(WIDTH as u32, HEIGHT as u32)
+        .ok_or_else(|| ErrorInternalServerError("could not allocate the plot image"))?;
+    resvg::Tree::from_usvg(&tree).render(tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(ErrorInternalServerError)
+}
@@ -0,0 +1,487 @@
+//! Token-bucket rate limiting of the API and exports, as actix middleware.
+//!
+//! Every request draws from its IP address's buckets, and a signed-in user's
+//! from their own as well, so more accounts don't buy more requests from one
+//! address. Behind a trusted reverse proxy the address is the client's, from
+//! `X-Forwarded-For`. Statistics, plots, exports and similarity searches
+//! scan every matching row, and sign-in is open to password guessing, so
+//! they draw from a separate, stricter bucket. A request that finds its bucket empty gets `429 Too Many
+//! Requests` with `Retry-After`.
+
+use crate::{
+    auth::request_user, config::RateLimitConfig, model::limits::rate_limit_message,
+    snapshot::SNAPSHOT_URL,
+};
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderMap, RETRY_AFTER},
+    HttpResponse,
+};
+use futures::future::LocalBoxFuture;
+use std::{
+    collections::HashMap,
+    future::{ready, Ready},
+    mem,
+    net::{IpAddr, Ipv4Addr},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Paths limited as exports rather than queries, by prefix. Server functions
+/// are routed by name, so a prefix covers any suffix leptos adds.
+const EXPORT_PATHS: [&str; 9] = [
+    "/api/query_stats",
+    "/api/query_plot",
+    "/api/query_timeline",
+    "/api/run_sql",
+    "/api/find_similar_planets",
+    "/api/login",
+    "/auth/oidc/",
+    "/plot.",
+    SNAPSHOT_URL,
+];
+
+/// The most buckets kept. Past it the least recently used go, which only
+/// hands their visitors a fresh allowance.
+const MAX_BUCKETS: usize = 10_000;
+
+/// How fast a bucket refills and how much it holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    /// Requests allowed a minute, on average. Zero turns the limit off.
+    pub per_minute: f64,
+    /// Requests allowed at once after a quiet spell.
+    pub burst: f64,
+}
+
+impl Limit {
+    fn is_off(&self) -> bool {
+        self.per_minute <= 0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Class {
+    Query,
+    /// Statistics, plots, exports, similarity searches and sign-in.
+    Export,
+}
+
+impl Class {
+    /// The class of requests to `path`, if they're limited at all.
+    pub fn of(path: &str) -> Option<Class> {
+        if EXPORT_PATHS.iter().any(|prefix| path.starts_with(prefix)) {
+            Some(Class::Export)
+        } else if path.starts_with("/api/") {
+            Some(Class::Query)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Visitor {
+    User(i64),
+    Ip(IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_minute / 60.0).min(limit.burst);
+        self.updated = now;
+    }
+
+    /// Refills the bucket, then says how long until there's a token to
+    /// take if there isn't one now.
+    fn wait(&mut self, limit: &Limit, now: Instant) -> Option<Duration> {
+        self.refill(limit, now);
+        (self.tokens < 1.0)
+            .then(|| Duration::from_secs_f64((1.0 - self.tokens) * 60.0 / limit.per_minute))
+    }
+}
+
+/// Buckets in two generations of up to half of [`MAX_BUCKETS`] each. When
+/// the recent one fills it becomes the older one, dropping the last, and
+/// buckets still in use move back; so each request costs a lookup or two
+/// rather than a scan.
+#[derive(Default)]
+struct Buckets {
+    recent: HashMap<(Visitor, Class), Bucket>,
+    older: HashMap<(Visitor, Class), Bucket>,
+}
+
+impl Buckets {
+    fn get(&mut self, key: (Visitor, Class), fresh: Bucket) -> &mut Bucket {
+        if !self.recent.contains_key(&key) && self.recent.len() >= MAX_BUCKETS / 2 {
+            self.older = mem::take(&mut self.recent);
+        }
+        let older = &mut self.older;
+        self.recent
+            .entry(key)
+            .or_insert_with(|| older.remove(&key).unwrap_or(fresh))
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.recent.len() + self.older.len()
+    }
+}
+
+/// Every visitor's buckets, shared by the actix workers.
+pub struct RateLimiter {
+    query: Limit,
+    export: Limit,
+    trusted_proxies: Vec<IpAddr>,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(query: Limit, export: Limit) -> RateLimiter {
+        RateLimiter {
+            query,
+            export,
+            trusted_proxies: Vec::new(),
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Takes the client's address from `X-Forwarded-For` on requests from
+    /// these proxies.
+    pub fn trusting(mut self, proxies: Vec<IpAddr>) -> RateLimiter {
+        self.trusted_proxies = proxies;
+        self
+    }
+
+    /// Limits from the `rate_limit` section of the configuration.
+    pub fn from_config(config: &RateLimitConfig) -> RateLimiter {
+        RateLimiter::new(
+            Limit {
+                per_minute: config.per_minute,
+                burst: config.burst,
+            },
+            Limit {
+                per_minute: config.export_per_minute,
+                burst: config.export_burst,
+            },
+        )
+        .trusting(config.trusted_proxies.clone())
+    }
+
+    fn limit(&self, class: Class) -> &Limit {
+        match class {
+            Class::Query => &self.query,
+            Class::Export => &self.export,
+        }
+    }
+
+    /// Counts a request drawing from each of `visitors`' buckets, or says
+    /// how long until they all have a token and it would be allowed.
+    pub fn check(&self, visitors: &[Visitor], class: Class, now: Instant) -> Result<(), Duration> {
+        let limit = self.limit(class);
+        if limit.is_off() {
+            return Ok(());
+        }
+        // A panic while holding the lock can't leave the map inconsistent,
+        // so keep using it.
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let fresh = || Bucket {
+            tokens: limit.burst,
+            updated: now,
+        };
+        // Nothing is taken unless every bucket can spare it.
+        let wait = visitors
+            .iter()
+            .filter_map(|visitor| buckets.get((*visitor, class), fresh()).wait(limit, now))
+            .max();
+        if let Some(wait) = wait {
+            return Err(wait);
+        }
+        for visitor in visitors {
+            buckets.get((*visitor, class), fresh()).tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// The address of the client making a request from `peer`: the last
+    /// address in `X-Forwarded-For` that isn't a trusted proxy, when `peer`
+    /// is one. Addresses before it could be made up by the client.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = peer;
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        for hop in forwarded.iter().rev() {
+            if !self.trusted_proxies.contains(&client) {
+                break;
+            }
+            match hop.trim().parse() {
+                Ok(ip) => client = ip,
+                Err(_) => break,
+            }
+        }
+        client
+    }
+}
+
+/// Middleware applying a [`RateLimiter`] to the requests it limits.
+#[derive(Clone)]
+pub struct RateLimit {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimit {
+    /// Limits with `limiter`, which should be created once and shared by
+    /// every worker's app.
+    pub fn new(limiter: Arc<RateLimiter>) -> RateLimit {
+        RateLimit { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limiter = self.limiter.clone();
+        let class = Class::of(req.path());
+
+        Box::pin(async move {
+            if let Some(class) = class {
+                let peer = req
+                    .peer_addr()
+                    .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |addr| addr.ip());
+                let mut visitors = vec![Visitor::Ip(limiter.client_ip(peer, req.headers()))];
+                // A failed session lookup still leaves the address to limit.
+                if let Ok(Some(user)) = request_user(req.request()).await {
+                    visitors.push(Visitor::User(user.id));
+                }
+                if let Err(wait) = limiter.check(&visitors, class, Instant::now()) {
+                    let secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+                    // The body is the message as a JSON string: the server
+                    // function client only passes a failed response's body on
+                    // through the error from decoding it, which quotes it.
+                    let response = HttpResponse::TooManyRequests()
+                        .insert_header((RETRY_AFTER, secs.to_string()))
+                        .json(rate_limit_message(secs));
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+            }
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(
+            Limit {
+                per_minute: 60.0,
+                burst: 2.0,
+            },
+            Limit {
+                per_minute: 6.0,
+                burst: 1.0,
+            },
+        )
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    fn forwarded_for(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(
+                HeaderName::from_static("x-forwarded-for"),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn expensive_and_sign_in_paths_are_limited_strictly() {
+        for path in [
+            "/api/query_plot123",
+            "/api/find_similar_planets456",
+            "/api/login789",
+            "/auth/oidc/login",
+            "/auth/oidc/callback",
+            "/assets/snapshot.json.gz",
+            "/plot.png",
+        ] {
+            assert_eq!(Class::of(path), Some(Class::Export), "{path}");
+        }
+        assert_eq!(Class::of("/api/query_db123"), Some(Class::Query));
+        assert_eq!(Class::of("/api/logout123"), Some(Class::Query));
+        assert_eq!(Class::of("/assets/favicon.ico"), None);
+    }
+
+    #[test]
+    fn buckets_allow_a_burst_then_refill() {
+        let limiter = limiter();
+        let visitor = [Visitor::Ip(ip("192.0.2.1"))];
+        let now = Instant::now();
+        assert_eq!(limiter.check(&visitor, Class::Query, now), Ok(()));
+        assert_eq!(limiter.check(&visitor, Class::Query, now), Ok(()));
+        assert_eq!(limiter.check(&visitor, Class::Query, now), Err(SECOND));
+        assert_eq!(limiter.check(&visitor, Class::Query, now + SECOND), Ok(()));
+
+        // Exports have their own bucket.
+        assert_eq!(limiter.check(&visitor, Class::Export, now), Ok(()));
+        assert_eq!(
+            limiter.check(&visitor, Class::Export, now),
+            Err(10 * SECOND)
+        );
+        // Others have theirs.
+        let other = [Visitor::Ip(ip("192.0.2.2"))];
+        assert_eq!(limiter.check(&other, Class::Export, now), Ok(()));
+    }
+
+    #[test]
+    fn signed_in_users_are_limited_by_address_too() {
+        let limiter = limiter();
+        let now = Instant::now();
+        let shared = Visitor::Ip(ip("192.0.2.1"));
+        let first = [shared, Visitor::User(1)];
+        assert_eq!(limiter.check(&first, Class::Query, now), Ok(()));
+        assert_eq!(limiter.check(&first, Class::Query, now), Ok(()));
+
+        // Another account from the same address gets nothing more, and isn't
+        // charged for being turned away.
+        let second = [shared, Visitor::User(2)];
+        assert_eq!(limiter.check(&second, Class::Query, now), Err(SECOND));
+        let elsewhere = [Visitor::Ip(ip("192.0.2.2")), Visitor::User(2)];
+        assert_eq!(limiter.check(&elsewhere, Class::Query, now), Ok(()));
+        assert_eq!(limiter.check(&elsewhere, Class::Query, now), Ok(()));
+        // The user's own bucket is empty wherever they go.
+        let again = [Visitor::Ip(ip("192.0.2.3")), Visitor::User(2)];
+        assert_eq!(limiter.check(&again, Class::Query, now), Err(SECOND));
+    }
+
+    #[test]
+    fn a_zero_rate_turns_the_limit_off() {
+        let off = Limit {
+            per_minute: 0.0,
+            burst: 1.0,
+        };
+        let limiter = RateLimiter::new(off, off);
+        let visitor = [Visitor::Ip(ip("192.0.2.1"))];
+        for _ in 0..10 {
+            assert_eq!(
+                limiter.check(&visitor, Class::Query, Instant::now()),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn buckets_are_bounded_and_keep_recent_visitors() {
+        let limiter = limiter();
+        let now = Instant::now();
+        let regular = [Visitor::User(0)];
+        for id in 1..3 * MAX_BUCKETS as i64 {
+            // The regular visitor keeps coming back, so never loses their
+            // bucket.
+            let result = limiter.check(&regular, Class::Query, now);
+            assert_eq!(result.is_ok(), id <= 2);
+            assert_eq!(
+                limiter.check(&[Visitor::User(id)], Class::Query, now),
+                Ok(())
+            );
+        }
+        assert!(limiter.buckets.lock().unwrap().len() <= MAX_BUCKETS);
+    }
+
+    #[test]
+    fn forwarded_addresses_are_only_taken_from_trusted_proxies() {
+        let proxy = ip("10.0.0.1");
+        let limiter = limiter().trusting(vec![proxy, ip("10.0.0.2")]);
+        let client = ip("203.0.113.7");
+
+        // Anyone else could claim any address.
+        let stranger = ip("192.0.2.1");
+        assert_eq!(
+            limiter.client_ip(stranger, &forwarded_for(&["203.0.113.7"])),
+            stranger
+        );
+        assert_eq!(limiter.client_ip(proxy, &HeaderMap::new()), proxy);
+        assert_eq!(
+            limiter.client_ip(proxy, &forwarded_for(&["203.0.113.7"])),
+            client
+        );
+        // Addresses the client sent ahead of its own are ignored, and
+        // proxies in a chain are skipped.
+        assert_eq!(
+            limiter.client_ip(
+                proxy,
+                &forwarded_for(&["198.51.100.1, 203.0.113.7, 10.0.0.2"])
+            ),
+            client
+        );
+        assert_eq!(
+            limiter.client_ip(proxy, &forwarded_for(&["198.51.100.1", "203.0.113.7"])),
+            client
+        );
+        assert_eq!(
+            limiter.client_ip(proxy, &forwarded_for(&["not an address"])),
+            proxy
+        );
+    }
+}
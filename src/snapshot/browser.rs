@@ -0,0 +1,183 @@
+use crate::{
+    api::{
+        FindSimilar, GetPlanet, GetPlanets, QueryDb, QueryPlot, QueryStats, QuerySystems,
+        QueryTimeline,
+    },
+    model::{
+        data::PlanetData,
+        input::Input,
+        snapshot::Snapshot,
+        timeline::{StackBy, Timeline},
+    },
+    snapshot::{decode, idb, SNAPSHOT_URL},
+};
+use gloo_net::http::Request;
+use js_sys::Uint8Array;
+use leptos::{create_action, Action, ServerFn, ServerFnError};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::JsValue;
+
+const BYTES_KEY: &str = "bytes";
+const ETAG_KEY: &str = "etag";
+
+thread_local! {
+    static SNAPSHOT: RefCell<Option<Rc<Snapshot>>> = RefCell::new(None);
+}
+
+fn request_error(error: impl ToString) -> ServerFnError {
+    ServerFnError::Request(error.to_string())
+}
+
+/// The copy kept in IndexedDB, with its tag.
+async fn stored() -> Option<(Vec<u8>, Option<String>)> {
+    let bytes = idb::get(BYTES_KEY).await.ok()??;
+    let etag = idb::get(ETAG_KEY).await.ok().flatten();
+    Some((
+        Uint8Array::new(&bytes).to_vec(),
+        etag.and_then(|etag| etag.as_string()),
+    ))
+}
+
+async fn store(bytes: &[u8], etag: Option<String>) -> Result<(), JsValue> {
+    idb::put(BYTES_KEY, &Uint8Array::from(bytes).into()).await?;
+    match etag {
+        Some(etag) => idb::put(ETAG_KEY, &JsValue::from_str(&etag)).await,
+        None => Ok(()),
+    }
+}
+
+/// Brings the local copy up to date with the server's, when it can be
+/// reached, and loads it.
+pub async fn sync() -> Result<Rc<Snapshot>, ServerFnError> {
+    let stored = stored().await;
+
+    let mut request = Request::get(SNAPSHOT_URL);
+    if let Some((_, Some(etag))) = &stored {
+        request = request.header("If-None-Match", etag);
+    }
+    let bytes = match request.send().await {
+        Ok(response) if response.status() == 304 => stored.map(|(bytes, _)| bytes),
+        Ok(response) if response.ok() => {
+            let etag = response.headers().get("ETag");
+            let bytes = response.binary().await.map_err(request_error)?;
+            // Without storage the snapshot still works until the page is
+            // closed.
+            if let Err(error) = store(&bytes, etag).await {
+                leptos::logging::warn!("couldn't store the snapshot: {:?}", error);
+            }
+            Some(bytes)
+        }
+        Ok(response) => {
+            leptos::logging::warn!("couldn't fetch the snapshot: {}", response.status());
+            stored.map(|(bytes, _)| bytes)
+        }
+        // Offline, so make do with the local copy.
+        Err(_) => stored.map(|(bytes, _)| bytes),
+    };
+    let bytes = bytes.ok_or_else(|| request_error("the dataset hasn't been downloaded yet"))?;
+
+    let snapshot = Rc::new(decode(&bytes)?);
+    SNAPSHOT.with(|cached| *cached.borrow_mut() = Some(snapshot.clone()));
+    Ok(snapshot)
+}
+
+/// The snapshot, synced on first use.
+pub async fn snapshot() -> Result<Rc<Snapshot>, ServerFnError> {
+    match SNAPSHOT.with(|snapshot| snapshot.borrow().clone()) {
+        Some(snapshot) => Ok(snapshot),
+        None => sync().await,
+    }
+}
+
+/// A server function that can be answered from the snapshot.
+pub trait LocalServerFn: Clone + ServerFn {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError>;
+}
+
+/// Runs a server function. The static build has no server, so it always
+/// answers from the snapshot; the hydrated app does when the server can't
+/// be reached.
+pub async fn call<S: LocalServerFn>(args: S) -> Result<S::Output, ServerFnError> {
+    #[cfg(feature = "hydrate")]
+    match S::call_fn_client(args.clone(), ()).await {
+        Err(ServerFnError::Request(error)) => {
+            leptos::logging::warn!("answering offline, the server is unreachable: {error}");
+        }
+        result => return result,
+    }
+    args.run(&*snapshot().await?)
+}
+
+/// Stands in for `leptos::create_server_action`, running the action with
+/// [`call`].
+pub fn create_server_action<S>() -> Action<S, Result<S::Output, ServerFnError>>
+where
+    S: LocalServerFn + 'static,
+    S::Output: 'static,
+{
+    create_action(|args: &S| call(args.clone()))
+}
+
+impl LocalServerFn for QueryDb {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        // There's no database to explain the query, so `explain` is ignored.
+        Ok(snapshot.records(self.query, self.sort, self.anchor_id, self.page_direction)?)
+    }
+}
+
+impl LocalServerFn for QueryStats {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        Ok(snapshot.stats(self.query, self.group_by, self.column)?)
+    }
+}
+
+impl LocalServerFn for QueryPlot {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        Ok(snapshot.plot(self.query, self.x, self.y)?)
+    }
+}
+
+impl LocalServerFn for QueryTimeline {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        Ok(snapshot.timeline(self.query, self.stack_by)?)
+    }
+}
+
+impl LocalServerFn for QuerySystems {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        Ok(snapshot.systems(self.query)?)
+    }
+}
+
+impl LocalServerFn for FindSimilar {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        Ok(snapshot.similar(self.id, self.parameters, self.count)?)
+    }
+}
+
+impl LocalServerFn for GetPlanet {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        Ok(snapshot.planet(self.id))
+    }
+}
+
+impl LocalServerFn for GetPlanets {
+    fn run(self, snapshot: &Snapshot) -> Result<Self::Output, ServerFnError> {
+        Ok(snapshot.planets_by_id(&self.ids))
+    }
+}
+
+pub async fn query_timeline(
+    query: Vec<Input>,
+    stack_by: StackBy,
+) -> Result<Timeline, ServerFnError> {
+    call(QueryTimeline { query, stack_by }).await
+}
+
+pub async fn get_planet(id: i64) -> Result<Option<PlanetData>, ServerFnError> {
+    call(GetPlanet { id }).await
+}
+
+pub async fn get_planets(ids: Vec<i64>) -> Result<Vec<PlanetData>, ServerFnError> {
+    call(GetPlanets { ids }).await
+}
@@ -0,0 +1,73 @@
+//! Just enough IndexedDB to keep the snapshot between visits: one object
+//! store of values by key.
+
+use js_sys::Promise;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
+
+const DATABASE: &str = "exoplanet_query_app";
+const STORE: &str = "snapshot";
+
+/// Waits for a request to succeed, returning its result.
+async fn complete(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        let succeeded = request.clone();
+        request.set_onsuccess(Some(
+            Closure::once_into_js(move || {
+                let result = succeeded.result().unwrap_or(JsValue::UNDEFINED);
+                let _ = resolve.call1(&JsValue::NULL, &result);
+            })
+            .unchecked_ref(),
+        ));
+        let failed = request.clone();
+        request.set_onerror(Some(
+            Closure::once_into_js(move || {
+                let error = failed.error().ok().flatten().map(JsValue::from);
+                let _ = reject.call1(&JsValue::NULL, &error.unwrap_or(JsValue::UNDEFINED));
+            })
+            .unchecked_ref(),
+        ));
+    });
+    JsFuture::from(promise).await
+}
+
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = leptos::window()
+        .indexed_db()?
+        .ok_or_else(|| JsValue::from_str("IndexedDB is unavailable"))?;
+    let request: IdbOpenDbRequest = factory.open_with_u32(DATABASE, 1)?;
+    let upgrading = request.clone();
+    request.set_onupgradeneeded(Some(
+        Closure::once_into_js(move || {
+            if let Ok(database) = upgrading.result() {
+                let _ = database
+                    .unchecked_into::<IdbDatabase>()
+                    .create_object_store(STORE);
+            }
+        })
+        .unchecked_ref(),
+    ));
+    Ok(complete(&request).await?.unchecked_into())
+}
+
+/// The value stored under `key`, if any.
+pub async fn get(key: &str) -> Result<Option<JsValue>, JsValue> {
+    let database = open().await?;
+    let request = database
+        .transaction_with_str(STORE)?
+        .object_store(STORE)?
+        .get(&JsValue::from_str(key))?;
+    let value = complete(&request).await?;
+    Ok((!value.is_undefined()).then_some(value))
+}
+
+pub async fn put(key: &str, value: &JsValue) -> Result<(), JsValue> {
+    let database = open().await?;
+    let request = database
+        .transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)?
+        .object_store(STORE)?
+        .put_with_key(value, &JsValue::from_str(key))?;
+    complete(&request).await?;
+    Ok(())
+}
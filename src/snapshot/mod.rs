@@ -0,0 +1,41 @@
+//! The dataset snapshot: the default rows of `exoplanet_data` as gzipped
+//! JSON, for answering queries in the browser.
+//!
+//! The server serves it at [`SNAPSHOT_URL`], and writes it for the static
+//! build with `exoplanet_query_app snapshot <path>`. Browsers keep a copy in
+//! IndexedDB. The static `csr` build answers every server function from it,
+//! and the hydrated app falls back to it when the server can't be reached.
+
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+mod browser;
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+mod idb;
+#[cfg(feature = "ssr")]
+mod server;
+
+use crate::model::snapshot::Snapshot;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use leptos::ServerFnError;
+use std::io::Write;
+
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+pub use browser::{
+    create_server_action, get_planet, get_planets, query_timeline, snapshot, sync, LocalServerFn,
+};
+#[cfg(feature = "ssr")]
+pub use server::{invalidate, snapshot_file, write};
+
+/// Where the snapshot is served from, under the assets directory so the
+/// static build can ship it as a file.
+pub const SNAPSHOT_URL: &str = "/assets/snapshot.json.gz";
+
+pub fn encode(snapshot: &Snapshot) -> Result<Vec<u8>, ServerFnError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    serde_json::to_writer(&mut encoder, snapshot)?;
+    encoder.flush()?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Snapshot, ServerFnError> {
+    Ok(serde_json::from_reader(GzDecoder::new(bytes))?)
+}
@@ -0,0 +1,125 @@
+use crate::{
+    api::storage,
+    model::snapshot::Snapshot,
+    snapshot::encode,
+    storage::{Sql, Storage},
+};
+use actix_web::{
+    error::ErrorInternalServerError,
+    get,
+    http::header::{CacheControl, CacheDirective, ContentEncoding, ETag, EntityTag, IfNoneMatch},
+    web::Bytes,
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use leptos::ServerFnError;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+/// The encoded snapshot with a tag identifying its contents.
+struct Encoded {
+    bytes: Bytes,
+    etag: EntityTag,
+}
+
+/// The encoded snapshot, if it's been encoded since the last invalidation,
+/// and the number of invalidations.
+struct Cached {
+    encoded: Option<Arc<Encoded>>,
+    generation: u64,
+}
+
+static CACHED: RwLock<Cached> = RwLock::new(Cached {
+    encoded: None,
+    generation: 0,
+});
+
+async fn read(conn: &mut impl Storage) -> Result<Snapshot, ServerFnError> {
+    let mut sql = Sql::new(conn.dialect());
+    sql.push(format!(
+        "SELECT {} FROM exoplanet_data WHERE default_flag = 1 ORDER BY id",
+        conn.dialect().planet_columns()
+    ));
+    Ok(Snapshot::new(conn.fetch_planets(&sql).await?))
+}
+
+/// Writes the snapshot to `path`, returning the number of rows in it.
+pub async fn write(conn: &mut impl Storage, path: &Path) -> Result<usize, ServerFnError> {
+    let snapshot = read(conn).await?;
+    std::fs::write(path, encode(&snapshot)?)?;
+    Ok(snapshot.planets.len())
+}
+
+/// The snapshot of the current data, encoded on first use and kept until
+/// the next import.
+async fn encoded() -> Result<Arc<Encoded>, ServerFnError> {
+    let generation = {
+        let cached = CACHED
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(encoded) = &cached.encoded {
+            return Ok(encoded.clone());
+        }
+        cached.generation
+    };
+
+    let bytes = encode(&read(&mut storage().await?).await?)?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let encoded = Arc::new(Encoded {
+        etag: EntityTag::new_strong(format!("{:016x}", hasher.finish())),
+        bytes: bytes.into(),
+    });
+    // An import while this one was being read may have replaced the data
+    // it was read from, so only keep it if there wasn't one.
+    let mut cached = CACHED
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if cached.generation == generation {
+        cached.encoded = Some(encoded.clone());
+    }
+    Ok(encoded)
+}
+
+/// Drops the encoded snapshot, for after the data has changed.
+pub fn invalidate() {
+    let mut cached = CACHED
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    cached.encoded = None;
+    cached.generation += 1;
+}
+
+/// Serves the snapshot, tagged so browsers holding the current one can skip
+/// downloading it again.
+#[get("/assets/snapshot.json.gz")]
+pub async fn snapshot_file(req: HttpRequest) -> actix_web::Result<HttpResponse> {
+    let encoded = encoded().await.map_err(|error| {
+        leptos::logging::error!("{}", error);
+        ErrorInternalServerError(error)
+    })?;
+
+    let current = match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&encoded.etag)),
+        None => false,
+    };
+    let mut response = match current {
+        true => HttpResponse::NotModified(),
+        false => HttpResponse::Ok(),
+    };
+    response
+        .insert_header(ETag(encoded.etag.clone()))
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]));
+    Ok(match current {
+        true => response.finish(),
+        false => response
+            .content_type("application/gzip")
+            // Already gzipped, so not worth compressing again.
+            .insert_header(ContentEncoding::Identity)
+            .body(encoded.bytes.clone()),
+    })
+}
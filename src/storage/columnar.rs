@@ -0,0 +1,576 @@
+//! An in-memory column store of the default `exoplanet_data` rows, enabled by
+//! the `columnar` feature.
+//!
+//! The store is loaded at startup and reloaded after every import. While it's
+//! loaded, connections answer stats and plot queries by scanning its typed
+//! column vectors, and pass row lookups through to the database. The archive
+//! is small enough that a plain vector per column beats a round trip to the
+//! database without pulling in a query engine.
+
+use crate::{
+    model::{
+        columns::{Column, ColumnKind, COLUMNS},
+        console::ConsoleResult,
+        data::{PlanetData, Value},
+        explain::PlanStep,
+        filter::{Comparison, Predicate},
+        measurement::{Limit, Measurement},
+        plot::PlotPoint,
+        similarity::Candidate,
+        stats::{summarise, GroupStats},
+        timeline::YearCount,
+    },
+    storage::{Connection, Dialect, Sql, Storage},
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+enum ColumnData {
+    Integer(Vec<Option<i64>>),
+    Real(Vec<Option<f64>>),
+    Text(Vec<Option<String>>),
+}
+
+impl ColumnData {
+    fn new(kind: ColumnKind, capacity: usize) -> ColumnData {
+        match kind {
+            ColumnKind::Integer | ColumnKind::Flag => {
+                ColumnData::Integer(Vec::with_capacity(capacity))
+            }
+            ColumnKind::Real => ColumnData::Real(Vec::with_capacity(capacity)),
+            ColumnKind::Text => ColumnData::Text(Vec::with_capacity(capacity)),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        match self {
+            ColumnData::Integer(values) => values.push(value.as_i64()),
+            ColumnData::Real(values) => values.push(value.as_f64()),
+            ColumnData::Text(values) => values.push(match value {
+                Value::Null => None,
+                value => Some(value.to_string()),
+            }),
+        }
+    }
+
+    fn key(&self, row: usize) -> Option<Key<'_>> {
+        match self {
+            ColumnData::Integer(values) => values[row].map(Key::Integer),
+            ColumnData::Real(values) => values[row].map(|value| Key::Real(value.to_bits())),
+            ColumnData::Text(values) => values[row].as_deref().map(Key::Text),
+        }
+    }
+
+    fn integer(&self, row: usize) -> Option<i64> {
+        match self {
+            ColumnData::Integer(values) => values[row],
+            _ => None,
+        }
+    }
+
+    fn number(&self, row: usize) -> Option<f64> {
+        match self {
+            ColumnData::Integer(values) => values[row].map(|value| value as f64),
+            ColumnData::Real(values) => values[row],
+            ColumnData::Text(_) => None,
+        }
+    }
+
+    fn text(&self, row: usize) -> Option<&str> {
+        match self {
+            ColumnData::Text(values) => values[row].as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// A value borrowed from a column, for grouping rows without copying it.
+/// Reals are kept as their bits so keys can be hashed.
+#[derive(PartialEq, Eq, Hash)]
+enum Key<'a> {
+    Integer(i64),
+    Real(u64),
+    Text(&'a str),
+}
+
+impl fmt::Display for Key<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Integer(value) => write!(f, "{value}"),
+            Key::Real(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            Key::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+/// A column's value, uncertainty and limit vectors, found once per query.
+struct MeasurementColumns<'a> {
+    value: Option<&'a ColumnData>,
+    err_upper: Option<&'a ColumnData>,
+    err_lower: Option<&'a ColumnData>,
+    limit: Option<&'a ColumnData>,
+}
+
+impl MeasurementColumns<'_> {
+    fn get(&self, row: usize) -> Measurement {
+        let number = |column: Option<&ColumnData>| column.and_then(|column| column.number(row));
+        Measurement::new(
+            number(self.value),
+            number(self.err_upper),
+            number(self.err_lower),
+            Limit::from_flag(self.limit.and_then(|column| column.integer(row))),
+        )
+    }
+}
+
+/// A `Predicate` with its columns found, evaluated the same way against
+/// rows of the store.
+enum RowFilter<'a> {
+    Compare {
+        column: Option<&'a ColumnData>,
+        op: Comparison,
+        text: Option<&'a str>,
+        number: Option<f64>,
+    },
+    /// Any of the filters matches, for `in` lists.
+    Any(Vec<RowFilter<'a>>),
+    RelativeError {
+        measurement: MeasurementColumns<'a>,
+        max: f64,
+    },
+    Consistent {
+        measurement: MeasurementColumns<'a>,
+        target: f64,
+        sigmas: f64,
+    },
+}
+
+impl RowFilter<'_> {
+    fn matches(&self, row: usize) -> bool {
+        match self {
+            RowFilter::Compare {
+                column,
+                op,
+                text,
+                number,
+            } => {
+                let ordering = match (column, text) {
+                    (Some(ColumnData::Text(values)), Some(text)) => {
+                        values[row].as_deref().map(|value| value.cmp(text))
+                    }
+                    (Some(column), _) => column
+                        .number(row)
+                        .zip(*number)
+                        .and_then(|(value, number)| value.partial_cmp(&number)),
+                    (None, _) => None,
+                };
+                ordering.is_some_and(|ordering| op.holds(ordering))
+            }
+            RowFilter::Any(filters) => filters.iter().any(|filter| filter.matches(row)),
+            RowFilter::RelativeError { measurement, max } => measurement
+                .get(row)
+                .relative_uncertainty()
+                .is_some_and(|relative| relative < *max),
+            RowFilter::Consistent {
+                measurement,
+                target,
+                sigmas,
+            } => measurement.get(row).consistent_with(*target, *sigmas),
+        }
+    }
+}
+
+/// The default rows of `exoplanet_data` in order of id, one vector per
+/// `PlanetData` field.
+pub struct ColumnStore {
+    rows: usize,
+    columns: HashMap<String, ColumnData>,
+}
+
+impl ColumnStore {
+    pub fn new(planets: &[PlanetData]) -> ColumnStore {
+        let mut fields = vec![
+            ("id".to_string(), ColumnKind::Integer),
+            ("pl_name".to_string(), ColumnKind::Text),
+        ];
+        for column in COLUMNS {
+            // Fields are named after the column id and its uncertainty and
+            // limit columns after the SQL name, as in `PlanetData`.
+            let mut stored = column.stored_columns();
+            stored[0].0 = column.id.to_string();
+            fields.extend(stored);
+        }
+
+        let mut columns = HashMap::new();
+        for (name, kind) in fields {
+            if columns.contains_key(&name) {
+                continue;
+            }
+            let mut data = ColumnData::new(kind, planets.len());
+            for planet in planets {
+                data.push(planet.value(&name).unwrap_or(Value::Null));
+            }
+            columns.insert(name, data);
+        }
+        ColumnStore {
+            rows: planets.len(),
+            columns,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    fn column(&self, field: &str) -> Option<&ColumnData> {
+        self.columns.get(field)
+    }
+
+    fn measurement_columns(&self, column: &Column) -> MeasurementColumns<'_> {
+        let error = |suffix: &str| match column.errors {
+            true => self.column(&format!("{}{suffix}", column.sql)),
+            false => None,
+        };
+        MeasurementColumns {
+            value: self.column(column.id),
+            err_upper: error("err1"),
+            err_lower: error("err2"),
+            limit: match column.limit {
+                true => self.column(&format!("{}lim", column.sql)),
+                false => None,
+            },
+        }
+    }
+
+    fn compare<'a>(&'a self, column: &Column, op: Comparison, value: &'a Value) -> RowFilter<'a> {
+        RowFilter::Compare {
+            column: self.column(column.id),
+            op,
+            text: match value {
+                Value::Text(text) => Some(text),
+                _ => None,
+            },
+            number: value
+                .as_f64()
+                .or_else(|| value.as_i64().map(|value| value as f64)),
+        }
+    }
+
+    fn row_filter<'a>(&'a self, filter: &'a Predicate) -> RowFilter<'a> {
+        match filter {
+            Predicate::Compare { column, op, value } => self.compare(column, *op, value),
+            Predicate::In { column, values } => RowFilter::Any(
+                values
+                    .iter()
+                    .map(|value| self.compare(column, Comparison::Equal, value))
+                    .collect(),
+            ),
+            Predicate::RelativeError { column, max } => RowFilter::RelativeError {
+                measurement: self.measurement_columns(column),
+                max: *max,
+            },
+            Predicate::Consistent {
+                column,
+                target,
+                sigmas,
+            } => RowFilter::Consistent {
+                measurement: self.measurement_columns(column),
+                target: *target,
+                sigmas: *sigmas,
+            },
+        }
+    }
+
+    /// The rows passing every filter.
+    fn select<'a>(&'a self, filters: &'a [Predicate]) -> impl Iterator<Item = usize> + 'a {
+        let filters = filters
+            .iter()
+            .map(|filter| self.row_filter(filter))
+            .collect::<Vec<_>>();
+        (0..self.rows).filter(move |row| filters.iter().all(|filter| filter.matches(*row)))
+    }
+
+    pub fn group_stats(
+        &self,
+        filters: &[Predicate],
+        group_by: &Column,
+        value: &Column,
+    ) -> Vec<GroupStats> {
+        let groups = self.column(group_by.id);
+        let values = self.column(value.id);
+        summarise(self.select(filters).map(|row| {
+            (
+                groups.and_then(|column| column.key(row)),
+                values.and_then(|column| column.number(row)),
+            )
+        }))
+    }
+
+    pub fn plot_points(
+        &self,
+        filters: &[Predicate],
+        x: &Column,
+        y: &Column,
+        limit: usize,
+    ) -> Vec<PlotPoint> {
+        let ids = self.column("id");
+        let names = self.column("pl_name");
+        let methods = self.column("discovery_method");
+        let (x, y) = (self.measurement_columns(x), self.measurement_columns(y));
+        let text = |column: Option<&ColumnData>, row| {
+            column
+                .and_then(|column| column.text(row))
+                .map(str::to_string)
+        };
+        self.select(filters)
+            .filter(|row| x.value.and_then(|column| column.number(*row)).is_some())
+            .take(limit)
+            .map(|row| PlotPoint {
+                id: ids
+                    .and_then(|column| column.integer(row))
+                    .unwrap_or_default(),
+                pl_name: text(names, row).unwrap_or_default(),
+                discovery_method: text(methods, row),
+                x: x.get(row),
+                y: y.get(row),
+            })
+            .collect()
+    }
+}
+
+static STORE: RwLock<Option<Arc<ColumnStore>>> = RwLock::new(None);
+
+/// The loaded store, if any.
+pub fn loaded() -> Option<Arc<ColumnStore>> {
+    STORE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Loads the default rows of `exoplanet_data` into a new store, replacing
+/// the current one, and returns the number of rows loaded. Without the table
+/// there's nothing to load and queries go to the database.
+pub async fn reload(conn: &mut impl Storage) -> Result<usize, sqlx::Error> {
+    let store = match conn.table_exists("exoplanet_data").await? {
+        true => {
+            let mut sql = Sql::new(conn.dialect());
+            sql.push(format!(
+                "SELECT {} FROM exoplanet_data WHERE default_flag = 1 ORDER BY id",
+                conn.dialect().planet_columns()
+            ));
+            Some(Arc::new(ColumnStore::new(&conn.fetch_planets(&sql).await?)))
+        }
+        false => None,
+    };
+    let rows = store.as_ref().map_or(0, |store| store.len());
+    *STORE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = store;
+    Ok(rows)
+}
+
+/// A database connection for row lookups with stats and plots answered from
+/// a column store.
+pub struct Columnar {
+    store: Arc<ColumnStore>,
+    rows: Box<Connection>,
+}
+
+impl Columnar {
+    pub fn new(store: Arc<ColumnStore>, rows: Connection) -> Columnar {
+        Columnar {
+            store,
+            rows: Box::new(rows),
+        }
+    }
+
+    /// The database connection rows are read from.
+    pub fn database(&mut self) -> &mut Connection {
+        &mut self.rows
+    }
+}
+
+#[async_trait]
+impl Storage for Columnar {
+    fn dialect(&self) -> Dialect {
+        self.rows.dialect()
+    }
+
+    async fn set_timeout(&mut self, timeout: Duration) -> Result<(), sqlx::Error> {
+        self.rows.set_timeout(timeout).await
+    }
+
+    async fn clear_timeout(&mut self) -> Result<(), sqlx::Error> {
+        self.rows.clear_timeout().await
+    }
+
+    async fn execute(&mut self, sql: &Sql) -> Result<u64, sqlx::Error> {
+        self.rows.execute(sql).await
+    }
+
+    async fn execute_script(&mut self, script: &str) -> Result<(), sqlx::Error> {
+        self.rows.execute_script(script).await
+    }
+
+    async fn table_exists(&mut self, table: &str) -> Result<bool, sqlx::Error> {
+        self.rows.table_exists(table).await
+    }
+
+    async fn table_columns(&mut self, table: &str) -> Result<Vec<String>, sqlx::Error> {
+        self.rows.table_columns(table).await
+    }
+
+    async fn column_types(&mut self, table: &str) -> Result<Vec<(String, String)>, sqlx::Error> {
+        self.rows.column_types(table).await
+    }
+
+    async fn fetch_planets(&mut self, sql: &Sql) -> Result<Vec<PlanetData>, sqlx::Error> {
+        self.rows.fetch_planets(sql).await
+    }
+
+    async fn explain(&mut self, sql: &Sql) -> Result<Vec<PlanStep>, sqlx::Error> {
+        self.rows.explain(sql).await
+    }
+
+    async fn group_stats(
+        &mut self,
+        filters: &[Predicate],
+        group_by: &'static Column,
+        value: &'static Column,
+    ) -> Result<Vec<GroupStats>, sqlx::Error> {
+        Ok(self.store.group_stats(filters, group_by, value))
+    }
+
+    async fn plot_points(
+        &mut self,
+        filters: &[Predicate],
+        x: &'static Column,
+        y: &'static Column,
+        limit: usize,
+    ) -> Result<Vec<PlotPoint>, sqlx::Error> {
+        Ok(self.store.plot_points(filters, x, y, limit))
+    }
+
+    async fn year_counts(
+        &mut self,
+        filters: &[Predicate],
+        category: &'static Column,
+    ) -> Result<Vec<YearCount>, sqlx::Error> {
+        self.rows.year_counts(filters, category).await
+    }
+
+    async fn similarity_candidates(&mut self, id: i64) -> Result<Vec<Candidate>, sqlx::Error> {
+        self.rows.similarity_candidates(id).await
+    }
+
+    async fn fetch_text(
+        &mut self,
+        statement: &str,
+        limit: usize,
+    ) -> Result<ConsoleResult, sqlx::Error> {
+        self.rows.fetch_text(statement, limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        columns::find_column,
+        input::{Input, CONSISTENT_OP, IN_OP, RELATIVE_ERROR_OP},
+    };
+    use serde_json::json;
+
+    fn planets() -> Vec<PlanetData> {
+        [
+            json!({ "id": 1, "pl_name": "A b", "hostname": "A", "disc_year": 2016,
+                    "discoverymethod": "Transit", "pl_rade": 1.0, "pl_radeerr1": 0.1,
+                    "pl_radeerr2": -0.1, "pl_radelim": 0 }),
+            json!({ "id": 2, "pl_name": "B b", "hostname": "B", "disc_year": 1995,
+                    "pl_rade": 2.0, "pl_radelim": 1 }),
+            json!({ "id": 3, "pl_name": "B c", "hostname": "B", "disc_year": 2016 }),
+        ]
+        .into_iter()
+        .map(|mut planet| {
+            planet["default_flag"] = json!(true);
+            serde_json::from_value(planet).unwrap()
+        })
+        .collect()
+    }
+
+    fn predicate(field: &str, comparison_op: &str, value: &str) -> Predicate {
+        Predicate::compile(Input::new(field, comparison_op, value)).unwrap()
+    }
+
+    #[test]
+    fn filters_match_the_rows_they_match_in_memory() {
+        let planets = planets();
+        let store = ColumnStore::new(&planets);
+        for filter in [
+            predicate("hostname", "=", "B"),
+            predicate("hostname", ">", "A"),
+            predicate("disc_year", "<", "2000"),
+            predicate("disc_year", "!=", "2016"),
+            predicate("hostname", IN_OP, "A, C"),
+            predicate("disc_year", IN_OP, "1995, 2016"),
+            predicate("pl_rade", ">=", "1.5"),
+            predicate("pl_rade", RELATIVE_ERROR_OP, "20"),
+            predicate("pl_rade", CONSISTENT_OP, "1.05"),
+            predicate("pl_rade", CONSISTENT_OP, "1.5"),
+        ] {
+            let filters = [filter];
+            let expected = planets
+                .iter()
+                .enumerate()
+                .filter(|(_, planet)| filters[0].matches_planet(planet))
+                .map(|(row, _)| row)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                store.select(&filters).collect::<Vec<_>>(),
+                expected,
+                "{filters:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn groups_and_points_read_the_columns() {
+        let store = ColumnStore::new(&planets());
+        let hostname = find_column("hostname").unwrap();
+        let disc_year = find_column("disc_year").unwrap();
+        let pl_rade = find_column("pl_rade").unwrap();
+
+        let groups = store.group_stats(&[], disc_year, pl_rade);
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| (group.group_value.as_deref(), group.count, group.mean))
+                .collect::<Vec<_>>(),
+            [(Some("2016"), 2, Some(1.0)), (Some("1995"), 1, Some(2.0))]
+        );
+        assert_eq!(
+            store.group_stats(&[], hostname, pl_rade)[0]
+                .group_value
+                .as_deref(),
+            Some("B")
+        );
+
+        let points = store.plot_points(&[], pl_rade, disc_year, 10);
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].pl_name, "A b");
+        assert_eq!(points[0].discovery_method.as_deref(), Some("Transit"));
+        assert_eq!(points[0].x.err_upper, Some(0.1));
+        assert_eq!(points[1].x.limit, Limit::Upper);
+        assert_eq!(points[1].y.value, Some(1995.0));
+    }
+}
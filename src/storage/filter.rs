@@ -0,0 +1,87 @@
+//! Compiles the query builder's filters into WHERE clause terms.
+
+use crate::{
+    model::{data::Value, filter::Predicate, input::Input},
+    storage::sql::Sql,
+};
+use leptos::ServerFnError;
+
+/// Appends the predicate to the WHERE clause being built as an `AND` term.
+pub fn push_predicate(sql: &mut Sql, predicate: &Predicate) {
+    match predicate {
+        Predicate::Compare { column, op, value } => {
+            sql.push(format!(" AND {} {} ", column.sql, op.sql()));
+            push_value(sql, value);
+        }
+        Predicate::In { column, values } => {
+            sql.push(format!(" AND {} IN (", column.sql));
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    sql.push(", ");
+                }
+                push_value(sql, value);
+            }
+            sql.push(")");
+        }
+        Predicate::RelativeError { column, max } => {
+            let name = column.sql;
+            let error = sql
+                .dialect()
+                .greatest(&format!("abs({name}err1)"), &format!("abs({name}err2)"));
+            // Both errors and a non-zero value are needed, as in
+            // `Measurement::relative_uncertainty`: Postgres's GREATEST skips
+            // NULLs, and dividing by zero fails the whole query there.
+            sql.push(format!(
+                " AND {name}err1 IS NOT NULL AND {name}err2 IS NOT NULL \
+                 AND {error} / NULLIF(abs({name}), 0) < "
+            ));
+            sql.push_bind(*max);
+        }
+        Predicate::Consistent {
+            column,
+            target,
+            sigmas,
+        } => {
+            let name = column.sql;
+            // Limits are consistent with any target on their side, as in
+            // `Measurement::consistent_with`.
+            if column.limit {
+                sql.push(format!(" AND CASE WHEN {name}lim = 1 THEN {name} >= "));
+                sql.push_bind(*target);
+                sql.push(format!(" WHEN {name}lim = -1 THEN {name} <= "));
+                sql.push_bind(*target);
+                sql.push(" ELSE");
+            } else {
+                sql.push(" AND");
+            }
+            sql.push(format!(" ({name} - "));
+            sql.push_bind(*sigmas);
+            sql.push(format!(" * abs({name}err2) <= "));
+            sql.push_bind(*target);
+            sql.push(format!(" AND {name} + "));
+            sql.push_bind(*sigmas);
+            sql.push(format!(" * abs({name}err1) >= "));
+            sql.push_bind(*target);
+            sql.push(")");
+            if column.limit {
+                sql.push(" END");
+            }
+        }
+    }
+}
+
+fn push_value(sql: &mut Sql, value: &Value) {
+    match value {
+        Value::Integer(value) => sql.push_bind(*value),
+        Value::Real(value) => sql.push_bind(*value),
+        Value::Text(value) => sql.push_bind(value.clone()),
+        Value::Bool(value) => sql.push_bind(*value as i64),
+        Value::Null => sql.push("NULL"),
+    };
+}
+
+/// Validates `input` and appends it to the WHERE clause being built.
+pub fn push_filter(sql: &mut Sql, input: Input) -> Result<(), ServerFnError> {
+    push_predicate(sql, &Predicate::compile(input)?);
+    Ok(())
+}
@@ -0,0 +1,702 @@
+//! The query layer, abstracted over the databases the app can run on.
+//!
+//! Queries are built as [`Sql`] for a [`Dialect`] and run through the
+//! [`Storage`] trait, which SQLite and Postgres connections implement. Which
+//! one is used is picked by the scheme of the configured database URL, and
+//! requests take their connections from a shared [`Pool`].
+
+#[cfg(feature = "columnar")]
+pub mod columnar;
+pub mod filter;
+mod postgres;
+pub mod sql;
+mod sqlite;
+
+use crate::model::{
+    columns::{find_column, Column, ColumnKind},
+    console::ConsoleResult,
+    data::PlanetData,
+    explain::PlanStep,
+    filter::Predicate,
+    input::{Input, PageKind, Sort},
+    measurement::{Limit, Measurement},
+    plot::PlotPoint,
+    similarity::Candidate,
+    stats::GroupStats,
+    system::SYSTEM_LIMIT,
+    timeline::YearCount,
+};
+use async_trait::async_trait;
+use leptos::ServerFnError;
+use sqlx::{
+    pool::{PoolConnection, PoolOptions},
+    sqlite::SqliteConnectOptions,
+    ConnectOptions, Connection as _, PgConnection, PgPool, Postgres, Sqlite, SqliteConnection,
+    SqlitePool,
+};
+use std::{
+    str::FromStr,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+pub use filter::{push_filter, push_predicate};
+pub use sql::{Bind, Dialect, Sql};
+
+/// Runs queries on a database. Errors are left as `sqlx::Error` so callers
+/// can tell timeouts apart with [`is_timeout`].
+#[async_trait]
+pub trait Storage: Send {
+    fn dialect(&self) -> Dialect;
+
+    /// Makes the database cancel statements running longer than `timeout`.
+    async fn set_timeout(&mut self, timeout: Duration) -> Result<(), sqlx::Error>;
+
+    /// Lets statements run for as long as they take again.
+    async fn clear_timeout(&mut self) -> Result<(), sqlx::Error>;
+
+    /// Runs a statement, returning the number of rows it affected.
+    async fn execute(&mut self, sql: &Sql) -> Result<u64, sqlx::Error>;
+
+    /// Runs statements with no bound values, separated by semicolons.
+    async fn execute_script(&mut self, script: &str) -> Result<(), sqlx::Error>;
+
+    async fn table_exists(&mut self, table: &str) -> Result<bool, sqlx::Error>;
+
+    /// The names of a table's columns, in lower case.
+    async fn table_columns(&mut self, table: &str) -> Result<Vec<String>, sqlx::Error>;
+
+    /// The names and declared types of a table's columns, in lower case.
+    async fn column_types(&mut self, table: &str) -> Result<Vec<(String, String)>, sqlx::Error>;
+
+    async fn fetch_planets(&mut self, sql: &Sql) -> Result<Vec<PlanetData>, sqlx::Error>;
+
+    /// The database's plan for a query, as a tree of steps.
+    async fn explain(&mut self, sql: &Sql) -> Result<Vec<PlanStep>, sqlx::Error>;
+
+    /// Summarises `value` over the default rows passing `filters`, grouped
+    /// by `group_by`, largest group first.
+    async fn group_stats(
+        &mut self,
+        filters: &[Predicate],
+        group_by: &'static Column,
+        value: &'static Column,
+    ) -> Result<Vec<GroupStats>, sqlx::Error>;
+
+    /// Up to `limit` default rows passing `filters` with a value of `x`, in
+    /// order of id.
+    async fn plot_points(
+        &mut self,
+        filters: &[Predicate],
+        x: &'static Column,
+        y: &'static Column,
+        limit: usize,
+    ) -> Result<Vec<PlotPoint>, sqlx::Error>;
+
+    /// Counts the default rows passing `filters` by discovery year and
+    /// `category`, in order of year.
+    async fn year_counts(
+        &mut self,
+        filters: &[Predicate],
+        category: &'static Column,
+    ) -> Result<Vec<YearCount>, sqlx::Error>;
+
+    /// What planets are compared on for the similarity search, for every
+    /// default row and for planet `id`.
+    async fn similarity_candidates(&mut self, id: i64) -> Result<Vec<Candidate>, sqlx::Error>;
+
+    /// Runs a statement from the SQL console, returning up to `limit` of its
+    /// rows with every value as text. The statement runs on its own as a
+    /// prepared statement, in a transaction that's rolled back afterwards
+    /// and on Postgres is read-only. SQLite will run every statement in a
+    /// string, so its connection must come from `connect_read_only`.
+    async fn fetch_text(
+        &mut self,
+        statement: &str,
+        limit: usize,
+    ) -> Result<ConsoleResult, sqlx::Error>;
+}
+
+/// When the dataset's tables were last replaced, if they have been.
+static SCHEMA_CHANGED: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Marks the dataset's tables as replaced. Pooled connections opened before
+/// now may have cached statements that no longer fit them, so they're
+/// closed rather than reused.
+pub fn schema_changed() {
+    *SCHEMA_CHANGED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+}
+
+/// Whether a connection opened `age` ago is newer than the tables.
+fn opened_since_schema_change(age: Duration) -> bool {
+    let changed = *SCHEMA_CHANGED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    changed.is_none_or(|changed| age < changed.elapsed())
+}
+
+/// Up to `max_connections` connections. Each goes back without its timeout,
+/// so the next request starts afresh, and ones older than the tables are
+/// closed instead of handed out.
+fn pool_options<DB: sqlx::Database>(max_connections: u32) -> PoolOptions<DB>
+where
+    DB::Connection: Storage,
+{
+    PoolOptions::new()
+        .max_connections(max_connections)
+        .before_acquire(|_, meta| Box::pin(async move { Ok(opened_since_schema_change(meta.age)) }))
+        .after_release(|conn: &mut DB::Connection, _| {
+            Box::pin(async move {
+                conn.clear_timeout().await?;
+                Ok(true)
+            })
+        })
+}
+
+/// Connections to whichever database the URL names, shared by requests.
+pub enum Pool {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
+}
+
+impl Pool {
+    /// Opens a pool of up to `max_connections` connections to the database
+    /// at `url`.
+    pub async fn connect(url: &str, max_connections: u32) -> Result<Pool, ServerFnError> {
+        match Dialect::from_url(url) {
+            Some(Dialect::Sqlite) => Ok(Pool::Sqlite(
+                pool_options(max_connections).connect(url).await?,
+            )),
+            Some(Dialect::Postgres) => Ok(Pool::Postgres(
+                pool_options(max_connections).connect(url).await?,
+            )),
+            None => Err(ServerFnError::ServerError(
+                "the database URL must start with sqlite:, postgres: or postgresql:".to_string(),
+            )),
+        }
+    }
+
+    /// A connection from the pool, going through the column store once one
+    /// is loaded. It goes back to the pool when dropped.
+    pub async fn acquire(&self) -> Result<Connection, ServerFnError> {
+        let conn = match self {
+            Pool::Sqlite(pool) => Connection::PooledSqlite(pool.acquire().await?),
+            Pool::Postgres(pool) => Connection::PooledPostgres(Box::new(pool.acquire().await?)),
+        };
+        Ok(Connection::with_column_store(conn))
+    }
+}
+
+/// A connection to whichever database the URL names.
+pub enum Connection {
+    Sqlite(SqliteConnection),
+    // Boxed, being ten times the size of a SQLite connection.
+    Postgres(Box<PgConnection>),
+    PooledSqlite(PoolConnection<Sqlite>),
+    PooledPostgres(Box<PoolConnection<Postgres>>),
+    /// A database connection for row lookups, with stats and plots answered
+    /// from the in-memory column store.
+    #[cfg(feature = "columnar")]
+    Columnar(columnar::Columnar),
+}
+
+impl Connection {
+    /// Connects to the database at `url`, going through the column store
+    /// once one is loaded.
+    pub async fn connect(url: &str) -> Result<Connection, ServerFnError> {
+        Ok(Connection::with_column_store(
+            Connection::connect_database(url).await?,
+        ))
+    }
+
+    fn with_column_store(conn: Connection) -> Connection {
+        #[cfg(feature = "columnar")]
+        if let Some(store) = columnar::loaded() {
+            return Connection::Columnar(columnar::Columnar::new(store, conn));
+        }
+        conn
+    }
+
+    /// Connects to the database at `url` directly.
+    pub async fn connect_database(url: &str) -> Result<Connection, ServerFnError> {
+        match Dialect::from_url(url) {
+            Some(Dialect::Sqlite) => Ok(Connection::Sqlite(SqliteConnection::connect(url).await?)),
+            Some(Dialect::Postgres) => Ok(Connection::Postgres(Box::new(
+                PgConnection::connect(url).await?,
+            ))),
+            None => Err(ServerFnError::ServerError(
+                "the database URL must start with sqlite:, postgres: or postgresql:".to_string(),
+            )),
+        }
+    }
+
+    /// Connects to the database at `url` for the SQL console, opening
+    /// SQLite databases read-only.
+    pub async fn connect_read_only(url: &str) -> Result<Connection, ServerFnError> {
+        match Dialect::from_url(url) {
+            Some(Dialect::Sqlite) => {
+                let options = SqliteConnectOptions::from_str(url)?.read_only(true);
+                Ok(Connection::Sqlite(options.connect().await?))
+            }
+            _ => Connection::connect_database(url).await,
+        }
+    }
+
+    /// Begins a transaction on the database, past the column store. It's
+    /// rolled back unless committed, even when dropped part way through.
+    pub async fn begin(&mut self) -> Result<Transaction<'_>, sqlx::Error> {
+        Ok(match self.database() {
+            Connection::Sqlite(conn) => Transaction::Sqlite(conn.begin().await?),
+            Connection::Postgres(conn) => Transaction::Postgres(Box::new(conn.begin().await?)),
+            Connection::PooledSqlite(conn) => Transaction::Sqlite(conn.begin().await?),
+            Connection::PooledPostgres(conn) => {
+                Transaction::Postgres(Box::new(conn.begin().await?))
+            }
+            #[cfg(feature = "columnar")]
+            Connection::Columnar(_) => unreachable!("the column store wraps a database connection"),
+        })
+    }
+
+    /// The connection to the database itself.
+    fn database(&mut self) -> &mut Connection {
+        match self {
+            #[cfg(feature = "columnar")]
+            Connection::Columnar(conn) => conn.database().database(),
+            conn => conn,
+        }
+    }
+
+    fn storage(&mut self) -> &mut dyn Storage {
+        match self {
+            Connection::Sqlite(conn) => conn,
+            Connection::Postgres(conn) => conn.as_mut(),
+            Connection::PooledSqlite(conn) => &mut **conn,
+            Connection::PooledPostgres(conn) => &mut ***conn,
+            #[cfg(feature = "columnar")]
+            Connection::Columnar(conn) => conn,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for Connection {
+    fn dialect(&self) -> Dialect {
+        match self {
+            Connection::Sqlite(_) | Connection::PooledSqlite(_) => Dialect::Sqlite,
+            Connection::Postgres(_) | Connection::PooledPostgres(_) => Dialect::Postgres,
+            #[cfg(feature = "columnar")]
+            Connection::Columnar(conn) => conn.dialect(),
+        }
+    }
+
+    async fn set_timeout(&mut self, timeout: Duration) -> Result<(), sqlx::Error> {
+        self.storage().set_timeout(timeout).await
+    }
+
+    async fn clear_timeout(&mut self) -> Result<(), sqlx::Error> {
+        self.storage().clear_timeout().await
+    }
+
+    async fn execute(&mut self, sql: &Sql) -> Result<u64, sqlx::Error> {
+        self.storage().execute(sql).await
+    }
+
+    async fn execute_script(&mut self, script: &str) -> Result<(), sqlx::Error> {
+        self.storage().execute_script(script).await
+    }
+
+    async fn table_exists(&mut self, table: &str) -> Result<bool, sqlx::Error> {
+        self.storage().table_exists(table).await
+    }
+
+    async fn table_columns(&mut self, table: &str) -> Result<Vec<String>, sqlx::Error> {
+        self.storage().table_columns(table).await
+    }
+
+    async fn column_types(&mut self, table: &str) -> Result<Vec<(String, String)>, sqlx::Error> {
+        self.storage().column_types(table).await
+    }
+
+    async fn fetch_planets(&mut self, sql: &Sql) -> Result<Vec<PlanetData>, sqlx::Error> {
+        self.storage().fetch_planets(sql).await
+    }
+
+    async fn explain(&mut self, sql: &Sql) -> Result<Vec<PlanStep>, sqlx::Error> {
+        self.storage().explain(sql).await
+    }
+
+    async fn group_stats(
+        &mut self,
+        filters: &[Predicate],
+        group_by: &'static Column,
+        value: &'static Column,
+    ) -> Result<Vec<GroupStats>, sqlx::Error> {
+        self.storage().group_stats(filters, group_by, value).await
+    }
+
+    async fn plot_points(
+        &mut self,
+        filters: &[Predicate],
+        x: &'static Column,
+        y: &'static Column,
+        limit: usize,
+    ) -> Result<Vec<PlotPoint>, sqlx::Error> {
+        self.storage().plot_points(filters, x, y, limit).await
+    }
+
+    async fn year_counts(
+        &mut self,
+        filters: &[Predicate],
+        category: &'static Column,
+    ) -> Result<Vec<YearCount>, sqlx::Error> {
+        self.storage().year_counts(filters, category).await
+    }
+
+    async fn similarity_candidates(&mut self, id: i64) -> Result<Vec<Candidate>, sqlx::Error> {
+        self.storage().similarity_candidates(id).await
+    }
+
+    async fn fetch_text(
+        &mut self,
+        statement: &str,
+        limit: usize,
+    ) -> Result<ConsoleResult, sqlx::Error> {
+        self.storage().fetch_text(statement, limit).await
+    }
+}
+
+/// A transaction begun by [`Connection::begin`].
+pub enum Transaction<'c> {
+    Sqlite(sqlx::Transaction<'c, Sqlite>),
+    // Boxed, as a Postgres connection is.
+    Postgres(Box<sqlx::Transaction<'c, Postgres>>),
+}
+
+impl Transaction<'_> {
+    pub async fn commit(self) -> Result<(), sqlx::Error> {
+        match self {
+            Transaction::Sqlite(transaction) => transaction.commit().await,
+            Transaction::Postgres(transaction) => transaction.commit().await,
+        }
+    }
+
+    fn storage(&mut self) -> &mut dyn Storage {
+        match self {
+            Transaction::Sqlite(transaction) => &mut **transaction,
+            Transaction::Postgres(transaction) => &mut ***transaction,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for Transaction<'_> {
+    fn dialect(&self) -> Dialect {
+        match self {
+            Transaction::Sqlite(_) => Dialect::Sqlite,
+            Transaction::Postgres(_) => Dialect::Postgres,
+        }
+    }
+
+    async fn set_timeout(&mut self, timeout: Duration) -> Result<(), sqlx::Error> {
+        self.storage().set_timeout(timeout).await
+    }
+
+    async fn clear_timeout(&mut self) -> Result<(), sqlx::Error> {
+        self.storage().clear_timeout().await
+    }
+
+    async fn execute(&mut self, sql: &Sql) -> Result<u64, sqlx::Error> {
+        self.storage().execute(sql).await
+    }
+
+    async fn execute_script(&mut self, script: &str) -> Result<(), sqlx::Error> {
+        self.storage().execute_script(script).await
+    }
+
+    async fn table_exists(&mut self, table: &str) -> Result<bool, sqlx::Error> {
+        self.storage().table_exists(table).await
+    }
+
+    async fn table_columns(&mut self, table: &str) -> Result<Vec<String>, sqlx::Error> {
+        self.storage().table_columns(table).await
+    }
+
+    async fn column_types(&mut self, table: &str) -> Result<Vec<(String, String)>, sqlx::Error> {
+        self.storage().column_types(table).await
+    }
+
+    async fn fetch_planets(&mut self, sql: &Sql) -> Result<Vec<PlanetData>, sqlx::Error> {
+        self.storage().fetch_planets(sql).await
+    }
+
+    async fn explain(&mut self, sql: &Sql) -> Result<Vec<PlanStep>, sqlx::Error> {
+        self.storage().explain(sql).await
+    }
+
+    async fn group_stats(
+        &mut self,
+        filters: &[Predicate],
+        group_by: &'static Column,
+        value: &'static Column,
+    ) -> Result<Vec<GroupStats>, sqlx::Error> {
+        self.storage().group_stats(filters, group_by, value).await
+    }
+
+    async fn plot_points(
+        &mut self,
+        filters: &[Predicate],
+        x: &'static Column,
+        y: &'static Column,
+        limit: usize,
+    ) -> Result<Vec<PlotPoint>, sqlx::Error> {
+        self.storage().plot_points(filters, x, y, limit).await
+    }
+
+    async fn year_counts(
+        &mut self,
+        filters: &[Predicate],
+        category: &'static Column,
+    ) -> Result<Vec<YearCount>, sqlx::Error> {
+        self.storage().year_counts(filters, category).await
+    }
+
+    async fn similarity_candidates(&mut self, id: i64) -> Result<Vec<Candidate>, sqlx::Error> {
+        self.storage().similarity_candidates(id).await
+    }
+
+    async fn fetch_text(
+        &mut self,
+        statement: &str,
+        limit: usize,
+    ) -> Result<ConsoleResult, sqlx::Error> {
+        self.storage().fetch_text(statement, limit).await
+    }
+}
+
+/// Whether a database error is a statement cancelled by its timeout.
+pub fn is_timeout(error: &sqlx::Error) -> bool {
+    let code = error.as_database_error().and_then(|error| error.code());
+    // SQLITE_INTERRUPT and Postgres' query_canceled.
+    matches!(code.as_deref(), Some("9" | "57014"))
+}
+
+/// Builds the query for a page of `page_size` records, returning it with the
+/// page's offset if it's sorted.
+pub fn records_sql(
+    dialect: Dialect,
+    query: Vec<Input>,
+    sort: Option<Sort>,
+    anchor_id: i64,
+    page_direction: &PageKind,
+    page_size: i64,
+) -> Result<(Sql, Option<i64>), ServerFnError> {
+    let mut sql = Sql::new(dialect);
+    sql.push(format!(
+        "SELECT {} FROM exoplanet_data WHERE default_flag = 1",
+        dialect.planet_columns()
+    ));
+
+    for input in query {
+        push_filter(&mut sql, input)?;
+    }
+
+    if let Some(sort) = sort {
+        let column = find_column(&sort.field)
+            .filter(|column| !column.hidden)
+            .ok_or_else(|| {
+                ServerFnError::ServerError(format!("unknown sort field '{}'", sort.field))
+            })?;
+        let name = column.sql;
+        let direction = if sort.descending { "DESC" } else { "ASC" };
+        // Sorted results are paged by offset, with the anchor holding the
+        // offset of the next page or of the current one.
+        let offset = match page_direction {
+            PageKind::Next => anchor_id,
+            PageKind::Prev => (anchor_id - page_size).max(0),
+        };
+
+        sql.push(format!(
+            " ORDER BY {name} IS NULL, {name} {direction}, id LIMIT {page_size} OFFSET "
+        ));
+        sql.push_bind(offset);
+        return Ok((sql, Some(offset)));
+    }
+
+    match page_direction {
+        PageKind::Next => {
+            sql.push(" AND id > ");
+            sql.push_bind(anchor_id);
+            sql.push(format!(" ORDER BY id LIMIT {page_size}"));
+        }
+        PageKind::Prev => {
+            sql.push(" AND id < ");
+            sql.push_bind(anchor_id);
+            sql.push(format!(" ORDER BY id DESC LIMIT {page_size}"));
+        }
+    }
+    Ok((sql, None))
+}
+
+/// Builds the query summarising `value` by `group_by` for `group_stats`.
+pub fn stats_sql(
+    dialect: Dialect,
+    filters: &[Predicate],
+    group_by: &Column,
+    value: &Column,
+) -> Sql {
+    let real = dialect.column_type(ColumnKind::Real);
+    let mut sql = Sql::new(dialect);
+    sql.push(format!(
+        "WITH filtered AS (SELECT CAST({} AS TEXT) AS group_value, CAST({} AS {real}) AS value \
+        FROM exoplanet_data WHERE default_flag = 1",
+        group_by.sql, value.sql,
+    ));
+    for predicate in filters {
+        push_predicate(&mut sql, predicate);
+    }
+    // Neither database has a median aggregate, so rank each group's values
+    // with missing values last and average the middle one or two.
+    sql.push(
+        "), ranked AS (SELECT group_value, value, \
+        ROW_NUMBER() OVER (PARTITION BY group_value ORDER BY value IS NULL, value) AS position, \
+        COUNT(value) OVER (PARTITION BY group_value) AS n FROM filtered) \
+        SELECT group_value, COUNT(*) AS count, MIN(value) AS min, MAX(value) AS max, AVG(value) AS mean, \
+        AVG(CASE WHEN position IN ((n + 1) / 2, (n + 2) / 2) THEN value END) AS median \
+        FROM ranked GROUP BY group_value ORDER BY count DESC, group_value",
+    );
+    sql
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct PlotRow {
+    id: i64,
+    pl_name: String,
+    discovery_method: Option<String>,
+    x: Option<f64>,
+    x_err1: Option<f64>,
+    x_err2: Option<f64>,
+    x_lim: Option<i64>,
+    y: Option<f64>,
+    y_err1: Option<f64>,
+    y_err2: Option<f64>,
+    y_lim: Option<i64>,
+}
+
+impl From<PlotRow> for PlotPoint {
+    fn from(row: PlotRow) -> Self {
+        PlotPoint {
+            id: row.id,
+            pl_name: row.pl_name,
+            discovery_method: row.discovery_method,
+            x: Measurement::new(row.x, row.x_err1, row.x_err2, Limit::from_flag(row.x_lim)),
+            y: Measurement::new(row.y, row.y_err1, row.y_err2, Limit::from_flag(row.y_lim)),
+        }
+    }
+}
+
+/// Builds the query for `plot_points`, read as `PlotRow`s.
+pub fn plot_sql(
+    dialect: Dialect,
+    filters: &[Predicate],
+    x: &Column,
+    y: &Column,
+    limit: usize,
+) -> Sql {
+    let real = dialect.column_type(ColumnKind::Real);
+    let integer = dialect.column_type(ColumnKind::Integer);
+    // Columns without uncertainties or limits select NULL in their place.
+    let select = |column: &Column, alias: &str| {
+        let name = column.sql;
+        let errors = match column.errors {
+            true => format!("{name}err1 AS {alias}_err1, {name}err2 AS {alias}_err2"),
+            false => format!(
+                "CAST(NULL AS {real}) AS {alias}_err1, CAST(NULL AS {real}) AS {alias}_err2"
+            ),
+        };
+        let limit = match column.limit {
+            true => format!("{name}lim AS {alias}_lim"),
+            false => format!("CAST(NULL AS {integer}) AS {alias}_lim"),
+        };
+        format!("CAST({name} AS {real}) AS {alias}, {errors}, {limit}")
+    };
+
+    let mut sql = Sql::new(dialect);
+    sql.push(format!(
+        "SELECT id, pl_name, discoverymethod AS discovery_method, {}, {} FROM exoplanet_data \
+        WHERE default_flag = 1 AND {} IS NOT NULL",
+        select(x, "x"),
+        select(y, "y"),
+        x.sql,
+    ));
+    for predicate in filters {
+        push_predicate(&mut sql, predicate);
+    }
+    sql.push(format!(" ORDER BY id LIMIT {limit}"));
+    sql
+}
+
+/// Builds the query for `year_counts`, read as `YearCount`s.
+pub fn timeline_sql(dialect: Dialect, filters: &[Predicate], category: &Column) -> Sql {
+    let mut sql = Sql::new(dialect);
+    sql.push(format!(
+        "SELECT disc_year AS year, CAST({} AS TEXT) AS category, COUNT(*) AS count \
+        FROM exoplanet_data WHERE default_flag = 1 AND disc_year IS NOT NULL",
+        category.sql
+    ));
+    for predicate in filters {
+        push_predicate(&mut sql, predicate);
+    }
+    sql.push(" GROUP BY year, category ORDER BY year, category");
+    sql
+}
+
+/// Builds the query for `similarity_candidates`, read as `Candidate`s.
+pub fn candidates_sql(dialect: Dialect, id: i64) -> Sql {
+    let mut sql = Sql::new(dialect);
+    sql.push(
+        "SELECT id, pl_rade, pl_bmasse, pl_orbper, st_spectype FROM exoplanet_data \
+        WHERE default_flag = 1 OR id = ",
+    );
+    sql.push_bind(id);
+    sql
+}
+
+/// Builds the query for every planet in the largest multi-planet systems
+/// with a planet passing `filters`, largest system first and each system's
+/// planets outward from the star.
+pub fn systems_sql(dialect: Dialect, filters: &[Predicate]) -> Sql {
+    let mut sql = Sql::new(dialect);
+    sql.push(format!(
+        "SELECT {} FROM exoplanet_data WHERE default_flag = 1 AND hostname IN \
+        (SELECT hostname FROM exoplanet_data WHERE default_flag = 1 AND sy_pnum > 1",
+        dialect.planet_columns()
+    ));
+    for predicate in filters {
+        push_predicate(&mut sql, predicate);
+    }
+    sql.push(format!(
+        " GROUP BY hostname ORDER BY MAX(sy_pnum) DESC, hostname LIMIT {SYSTEM_LIMIT}) \
+        ORDER BY sy_pnum DESC, hostname, pl_orbper IS NULL, pl_orbper, pl_name"
+    ));
+    sql
+}
+
+/// Builds the query for the planets with the given ids, which mustn't be
+/// empty.
+pub fn planets_sql(dialect: Dialect, ids: &[i64]) -> Sql {
+    let mut sql = Sql::new(dialect);
+    sql.push(format!(
+        "SELECT {} FROM exoplanet_data WHERE id IN (",
+        dialect.planet_columns()
+    ));
+    for (i, id) in ids.iter().enumerate() {
+        if i > 0 {
+            sql.push(", ");
+        }
+        sql.push_bind(*id);
+    }
+    sql.push(")");
+    sql
+}
@@ -0,0 +1,232 @@
+use crate::{
+    model::{
+        columns::Column, console::ConsoleResult, data::PlanetData, explain::PlanStep,
+        filter::Predicate, plot::PlotPoint, similarity::Candidate, stats::GroupStats,
+        timeline::YearCount,
+    },
+    storage::{candidates_sql, plot_sql, stats_sql, timeline_sql, Dialect, PlotRow, Sql, Storage},
+};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use sqlx::{Column as _, Executor, PgConnection, Row};
+use std::time::Duration;
+
+#[async_trait]
+impl Storage for PgConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    async fn set_timeout(&mut self, timeout: Duration) -> Result<(), sqlx::Error> {
+        Executor::execute(
+            &mut *self,
+            format!("SET statement_timeout = {}", timeout.as_millis()).as_str(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn clear_timeout(&mut self) -> Result<(), sqlx::Error> {
+        Executor::execute(&mut *self, "RESET statement_timeout").await?;
+        Ok(())
+    }
+
+    async fn execute(&mut self, sql: &Sql) -> Result<u64, sqlx::Error> {
+        let mut builder = sql.postgres();
+        Ok(builder.build().execute(&mut *self).await?.rows_affected())
+    }
+
+    async fn execute_script(&mut self, script: &str) -> Result<(), sqlx::Error> {
+        Executor::execute(&mut *self, script).await?;
+        Ok(())
+    }
+
+    async fn table_exists(&mut self, table: &str) -> Result<bool, sqlx::Error> {
+        Ok(
+            sqlx::query_scalar::<_, bool>("SELECT to_regclass($1) IS NOT NULL")
+                .bind(table)
+                .fetch_one(&mut *self)
+                .await?,
+        )
+    }
+
+    async fn table_columns(&mut self, table: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT lower(column_name) FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1",
+        )
+        .bind(table)
+        .fetch_all(&mut *self)
+        .await
+    }
+
+    async fn column_types(&mut self, table: &str) -> Result<Vec<(String, String)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT lower(column_name), lower(data_type) FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1",
+        )
+        .bind(table)
+        .fetch_all(&mut *self)
+        .await
+    }
+
+    async fn fetch_planets(&mut self, sql: &Sql) -> Result<Vec<PlanetData>, sqlx::Error> {
+        let mut builder = sql.postgres();
+        Ok(builder
+            .build_query_as::<'_, PlanetData>()
+            .fetch_all(&mut *self)
+            .await?)
+    }
+
+    async fn explain(&mut self, sql: &Sql) -> Result<Vec<PlanStep>, sqlx::Error> {
+        let mut explain = Sql::new(Dialect::Postgres);
+        explain.push("EXPLAIN ").append(sql.clone());
+        let mut builder = explain.postgres();
+        let lines = builder
+            .build_query_scalar::<String>()
+            .fetch_all(&mut *self)
+            .await?;
+        Ok(plan_steps(&lines))
+    }
+
+    async fn group_stats(
+        &mut self,
+        filters: &[Predicate],
+        group_by: &'static Column,
+        value: &'static Column,
+    ) -> Result<Vec<GroupStats>, sqlx::Error> {
+        let mut builder = stats_sql(Dialect::Postgres, filters, group_by, value).postgres();
+        builder
+            .build_query_as::<'_, GroupStats>()
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn plot_points(
+        &mut self,
+        filters: &[Predicate],
+        x: &'static Column,
+        y: &'static Column,
+        limit: usize,
+    ) -> Result<Vec<PlotPoint>, sqlx::Error> {
+        let mut builder = plot_sql(Dialect::Postgres, filters, x, y, limit).postgres();
+        let rows = builder
+            .build_query_as::<'_, PlotRow>()
+            .fetch_all(&mut *self)
+            .await?;
+        Ok(rows.into_iter().map(PlotPoint::from).collect())
+    }
+
+    async fn year_counts(
+        &mut self,
+        filters: &[Predicate],
+        category: &'static Column,
+    ) -> Result<Vec<YearCount>, sqlx::Error> {
+        let mut builder = timeline_sql(Dialect::Postgres, filters, category).postgres();
+        builder
+            .build_query_as::<'_, YearCount>()
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn similarity_candidates(&mut self, id: i64) -> Result<Vec<Candidate>, sqlx::Error> {
+        let mut builder = candidates_sql(Dialect::Postgres, id).postgres();
+        builder
+            .build_query_as::<'_, Candidate>()
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn fetch_text(
+        &mut self,
+        statement: &str,
+        limit: usize,
+    ) -> Result<ConsoleResult, sqlx::Error> {
+        Executor::execute(&mut *self, "BEGIN READ ONLY").await?;
+        let result = console_rows(self, statement, limit).await;
+        let rolled_back = Executor::execute(&mut *self, "ROLLBACK").await;
+        let result = result?;
+        rolled_back?;
+        Ok(result)
+    }
+}
+
+/// Runs a console statement over the extended protocol, where Postgres
+/// refuses more than one statement, with its columns cast to text so values
+/// of any type can be shown.
+async fn console_rows(
+    conn: &mut PgConnection,
+    statement: &str,
+    limit: usize,
+) -> Result<ConsoleResult, sqlx::Error> {
+    let columns = Executor::describe(&mut *conn, statement)
+        .await?
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect::<Vec<_>>();
+    // Renaming the columns by position copes with duplicate and unnamed
+    // ones. The statement goes on lines of its own in case it ends in a
+    // comment.
+    let names = (1..=columns.len())
+        .map(|i| format!("c{i}"))
+        .collect::<Vec<_>>();
+    let query = match names.is_empty() {
+        true => format!(
+            "SELECT FROM (\n{statement}\n) AS console LIMIT {}",
+            limit + 1
+        ),
+        false => format!(
+            "SELECT {} FROM (\n{statement}\n) AS console({}) LIMIT {}",
+            names
+                .iter()
+                .map(|name| format!("{name}::text"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            names.join(", "),
+            limit + 1
+        ),
+    };
+
+    let mut result = ConsoleResult {
+        columns,
+        ..ConsoleResult::default()
+    };
+    let mut rows = sqlx::query(&query).persistent(false).fetch(&mut *conn);
+    while let Some(row) = rows.try_next().await? {
+        if result.rows.len() == limit {
+            result.truncated = true;
+            break;
+        }
+        result.rows.push(
+            (0..row.len())
+                .map(|i| row.try_get::<Option<String>, _>(i))
+                .collect::<Result<_, _>>()?,
+        );
+    }
+    Ok(result)
+}
+
+/// Turns the lines of Postgres' text plan, whose nodes are nested by
+/// indentation, into steps like SQLite's.
+fn plan_steps(lines: &[String]) -> Vec<PlanStep> {
+    // The indentation and id of each node the current line could be under.
+    let mut ancestors = Vec::<(usize, i64)>::new();
+    lines
+        .iter()
+        .zip(1..)
+        .map(|(line, id)| {
+            let indent = line.len() - line.trim_start().len();
+            while ancestors.last().is_some_and(|(depth, _)| *depth >= indent) {
+                ancestors.pop();
+            }
+            let parent = ancestors.last().map_or(0, |(_, id)| *id);
+            ancestors.push((indent, id));
+            PlanStep {
+                id,
+                parent,
+                detail: line.trim().trim_start_matches("->").trim().to_string(),
+            }
+        })
+        .collect()
+}
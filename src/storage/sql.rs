@@ -0,0 +1,212 @@
+//! SQL text and bound values built independently of the database they'll run
+//! on, so the same query can be sent to SQLite or Postgres.
+
+use crate::model::columns::{ColumnKind, COLUMNS};
+use sqlx::{Postgres, QueryBuilder, Sqlite};
+
+/// The SQL dialect spoken by a database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Sqlite,
+    Postgres,
+}
+
+impl Dialect {
+    /// The dialect of the database at `url`, by its scheme.
+    pub fn from_url(url: &str) -> Option<Dialect> {
+        match url.split_once(':')?.0 {
+            "sqlite" => Some(Dialect::Sqlite),
+            "postgres" | "postgresql" => Some(Dialect::Postgres),
+            _ => None,
+        }
+    }
+
+    pub fn column_type(&self, kind: ColumnKind) -> &'static str {
+        match self {
+            Dialect::Sqlite => kind.sql_type(),
+            Dialect::Postgres => match kind {
+                ColumnKind::Text => "TEXT",
+                ColumnKind::Integer | ColumnKind::Flag => "BIGINT",
+                ColumnKind::Real => "DOUBLE PRECISION",
+            },
+        }
+    }
+
+    /// The larger of two numeric expressions.
+    pub fn greatest(&self, a: &str, b: &str) -> String {
+        match self {
+            Dialect::Sqlite => format!("max({a}, {b})"),
+            Dialect::Postgres => format!("GREATEST({a}, {b})"),
+        }
+    }
+
+    /// The select list for reading rows of `exoplanet_data` as `PlanetData`.
+    pub fn planet_columns(&self) -> String {
+        match self {
+            Dialect::Sqlite => {
+                "discoverymethod AS discovery_method, releasedate AS release_date, *".to_string()
+            }
+            // Postgres won't decode an integer flag as a bool, so list the
+            // columns and convert `default_flag`.
+            Dialect::Postgres => {
+                let mut columns = vec!["id".to_string()];
+                for column in COLUMNS {
+                    for (name, _) in column.stored_columns() {
+                        columns.push(match name.as_str() {
+                            "default_flag" => "(default_flag <> 0) AS default_flag".to_string(),
+                            _ if name == column.sql && column.sql != column.id => {
+                                format!("{name} AS {}", column.id)
+                            }
+                            _ => name,
+                        });
+                    }
+                }
+                columns.join(", ")
+            }
+        }
+    }
+
+    /// The statement creating a table for the columns in `COLUMNS`.
+    pub fn create_table(&self, table: &str) -> String {
+        let id = match self {
+            Dialect::Sqlite => "INTEGER PRIMARY KEY",
+            Dialect::Postgres => "BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY",
+        };
+        let definitions = COLUMNS
+            .iter()
+            .flat_map(|column| column.stored_columns())
+            .map(|(name, kind)| format!("{name} {}", self.column_type(kind)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("CREATE TABLE {table} (id {id}, {definitions})")
+    }
+}
+
+/// A value bound to a query parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bind {
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl From<i64> for Bind {
+    fn from(value: i64) -> Self {
+        Bind::Integer(value)
+    }
+}
+
+impl From<f64> for Bind {
+    fn from(value: f64) -> Self {
+        Bind::Real(value)
+    }
+}
+
+impl From<String> for Bind {
+    fn from(value: String) -> Self {
+        Bind::Text(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Fragment {
+    Text(String),
+    Bind(Bind),
+}
+
+/// A query being built for a dialect, like `sqlx::QueryBuilder` but not tied
+/// to a database until it's run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sql {
+    dialect: Dialect,
+    fragments: Vec<Fragment>,
+}
+
+impl Sql {
+    pub fn new(dialect: Dialect) -> Sql {
+        Sql {
+            dialect,
+            fragments: Vec::new(),
+        }
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn push(&mut self, sql: impl Into<String>) -> &mut Sql {
+        self.fragments.push(Fragment::Text(sql.into()));
+        self
+    }
+
+    pub fn push_bind(&mut self, value: impl Into<Bind>) -> &mut Sql {
+        self.fragments.push(Fragment::Bind(value.into()));
+        self
+    }
+
+    /// Appends another query's text and values.
+    pub fn append(&mut self, other: Sql) -> &mut Sql {
+        self.fragments.extend(other.fragments);
+        self
+    }
+
+    /// The SQL with the dialect's placeholders in place of bound values.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        let mut index = 0;
+        for fragment in &self.fragments {
+            match fragment {
+                Fragment::Text(sql) => text.push_str(sql),
+                Fragment::Bind(_) => {
+                    index += 1;
+                    match self.dialect {
+                        Dialect::Sqlite => text.push('?'),
+                        Dialect::Postgres => text.push_str(&format!("${index}")),
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    pub fn binds(&self) -> Vec<&Bind> {
+        self.fragments
+            .iter()
+            .filter_map(|fragment| match fragment {
+                Fragment::Bind(bind) => Some(bind),
+                Fragment::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// Appends the query to a SQLite query builder.
+    pub fn push_to_sqlite(&self, builder: &mut QueryBuilder<'_, Sqlite>) {
+        for fragment in &self.fragments {
+            match fragment {
+                Fragment::Text(sql) => builder.push(sql),
+                Fragment::Bind(Bind::Integer(value)) => builder.push_bind(*value),
+                Fragment::Bind(Bind::Real(value)) => builder.push_bind(*value),
+                Fragment::Bind(Bind::Text(value)) => builder.push_bind(value.clone()),
+            };
+        }
+    }
+
+    pub fn sqlite<'a>(&self) -> QueryBuilder<'a, Sqlite> {
+        let mut builder = QueryBuilder::new("");
+        self.push_to_sqlite(&mut builder);
+        builder
+    }
+
+    pub fn postgres<'a>(&self) -> QueryBuilder<'a, Postgres> {
+        let mut builder = QueryBuilder::new("");
+        for fragment in &self.fragments {
+            match fragment {
+                Fragment::Text(sql) => builder.push(sql),
+                Fragment::Bind(Bind::Integer(value)) => builder.push_bind(*value),
+                Fragment::Bind(Bind::Real(value)) => builder.push_bind(*value),
+                Fragment::Bind(Bind::Text(value)) => builder.push_bind(value.clone()),
+            };
+        }
+        builder
+    }
+}
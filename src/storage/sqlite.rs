@@ -0,0 +1,197 @@
+use crate::{
+    model::{
+        columns::Column, console::ConsoleResult, data::PlanetData, explain::PlanStep,
+        filter::Predicate, plot::PlotPoint, similarity::Candidate, stats::GroupStats,
+        timeline::YearCount,
+    },
+    storage::{candidates_sql, plot_sql, stats_sql, timeline_sql, Dialect, PlotRow, Sql, Storage},
+};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use sqlx::{sqlite::SqliteRow, Column as _, Executor, Row, SqliteConnection, TypeInfo, ValueRef};
+use std::time::{Duration, Instant};
+
+#[async_trait]
+impl Storage for SqliteConnection {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    async fn set_timeout(&mut self, timeout: Duration) -> Result<(), sqlx::Error> {
+        // SQLite has no statement timeout, so check the time every thousand
+        // VM instructions and interrupt once it has passed.
+        let deadline = Instant::now() + timeout;
+        self.lock_handle()
+            .await?
+            .set_progress_handler(1_000, move || Instant::now() < deadline);
+        Ok(())
+    }
+
+    async fn clear_timeout(&mut self) -> Result<(), sqlx::Error> {
+        self.lock_handle().await?.remove_progress_handler();
+        Ok(())
+    }
+
+    async fn execute(&mut self, sql: &Sql) -> Result<u64, sqlx::Error> {
+        let mut builder = sql.sqlite();
+        Ok(builder.build().execute(&mut *self).await?.rows_affected())
+    }
+
+    async fn execute_script(&mut self, script: &str) -> Result<(), sqlx::Error> {
+        Executor::execute(&mut *self, script).await?;
+        Ok(())
+    }
+
+    async fn table_exists(&mut self, table: &str) -> Result<bool, sqlx::Error> {
+        Ok(
+            sqlx::query("SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?")
+                .bind(table)
+                .fetch_optional(&mut *self)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn table_columns(&mut self, table: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT lower(name) FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn column_types(&mut self, table: &str) -> Result<Vec<(String, String)>, sqlx::Error> {
+        sqlx::query_as("SELECT lower(name), lower(type) FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn fetch_planets(&mut self, sql: &Sql) -> Result<Vec<PlanetData>, sqlx::Error> {
+        let mut builder = sql.sqlite();
+        Ok(builder
+            .build_query_as::<'_, PlanetData>()
+            .fetch_all(&mut *self)
+            .await?)
+    }
+
+    async fn explain(&mut self, sql: &Sql) -> Result<Vec<PlanStep>, sqlx::Error> {
+        let mut builder = sqlx::QueryBuilder::new("EXPLAIN QUERY PLAN ");
+        sql.push_to_sqlite(&mut builder);
+        Ok(builder
+            .build_query_as::<'_, PlanStep>()
+            .fetch_all(&mut *self)
+            .await?)
+    }
+
+    async fn group_stats(
+        &mut self,
+        filters: &[Predicate],
+        group_by: &'static Column,
+        value: &'static Column,
+    ) -> Result<Vec<GroupStats>, sqlx::Error> {
+        let mut builder = stats_sql(Dialect::Sqlite, filters, group_by, value).sqlite();
+        builder
+            .build_query_as::<'_, GroupStats>()
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn plot_points(
+        &mut self,
+        filters: &[Predicate],
+        x: &'static Column,
+        y: &'static Column,
+        limit: usize,
+    ) -> Result<Vec<PlotPoint>, sqlx::Error> {
+        let mut builder = plot_sql(Dialect::Sqlite, filters, x, y, limit).sqlite();
+        let rows = builder
+            .build_query_as::<'_, PlotRow>()
+            .fetch_all(&mut *self)
+            .await?;
+        Ok(rows.into_iter().map(PlotPoint::from).collect())
+    }
+
+    async fn year_counts(
+        &mut self,
+        filters: &[Predicate],
+        category: &'static Column,
+    ) -> Result<Vec<YearCount>, sqlx::Error> {
+        let mut builder = timeline_sql(Dialect::Sqlite, filters, category).sqlite();
+        builder
+            .build_query_as::<'_, YearCount>()
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn similarity_candidates(&mut self, id: i64) -> Result<Vec<Candidate>, sqlx::Error> {
+        let mut builder = candidates_sql(Dialect::Sqlite, id).sqlite();
+        builder
+            .build_query_as::<'_, Candidate>()
+            .fetch_all(&mut *self)
+            .await
+    }
+
+    async fn fetch_text(
+        &mut self,
+        statement: &str,
+        limit: usize,
+    ) -> Result<ConsoleResult, sqlx::Error> {
+        Executor::execute(&mut *self, "BEGIN").await?;
+        let result = console_rows(self, statement, limit).await;
+        let rolled_back = Executor::execute(&mut *self, "ROLLBACK").await;
+        let result = result?;
+        rolled_back?;
+        Ok(result)
+    }
+}
+
+async fn console_rows(
+    conn: &mut SqliteConnection,
+    statement: &str,
+    limit: usize,
+) -> Result<ConsoleResult, sqlx::Error> {
+    let mut result = ConsoleResult::default();
+    {
+        let mut rows = sqlx::query(statement).persistent(false).fetch(&mut *conn);
+        while let Some(row) = rows.try_next().await? {
+            if result.rows.len() == limit {
+                result.truncated = true;
+                break;
+            }
+            result
+                .rows
+                .push((0..row.len()).map(|i| text(&row, i)).collect());
+        }
+    }
+    result.columns = Executor::describe(&mut *conn, statement)
+        .await?
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    Ok(result)
+}
+
+/// A value of any type as text. SQLite values carry their own type, which
+/// needn't match the column's.
+fn text(row: &SqliteRow, i: usize) -> Option<String> {
+    let value = row.try_get_raw(i).ok()?;
+    if value.is_null() {
+        return None;
+    }
+    match value.type_info().name() {
+        "INTEGER" => row
+            .try_get_unchecked::<i64, _>(i)
+            .ok()
+            .map(|v| v.to_string()),
+        "REAL" => row
+            .try_get_unchecked::<f64, _>(i)
+            .ok()
+            .map(|v| v.to_string()),
+        "BLOB" => row
+            .try_get_unchecked::<Vec<u8>, _>(i)
+            .ok()
+            .map(|bytes| format!("<{} bytes>", bytes.len())),
+        _ => row.try_get_unchecked::<String, _>(i).ok(),
+    }
+}
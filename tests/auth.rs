@@ -0,0 +1,341 @@
+//! Covers sign-in: password hashing, the user database and the OpenID
+//! Connect flow against a mock provider.
+//!
+//! Run with `cargo test --features ssr`. The user database runs in memory and
+//! the provider is served on a local port, so nothing outside is needed.
+#![cfg(feature = "ssr")]
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use exoplanet_query_app::{
+    auth::{
+        oidc::OidcProvider,
+        password,
+        store::{self, create_tables},
+        AuthError, Identity, IdentityProvider,
+    },
+    model::{
+        input::{Input, Sort},
+        user::Role,
+    },
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{Connection as _, SqliteConnection};
+use std::net::TcpListener;
+
+async fn user_db() -> SqliteConnection {
+    let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+    create_tables(&mut conn).await.unwrap();
+    conn
+}
+
+#[test]
+fn passwords_verify_against_their_hash() {
+    let hash = password::hash("correct horse").unwrap();
+    assert_ne!(hash, "correct horse");
+    assert!(password::verify("correct horse", &hash));
+    assert!(!password::verify("battery staple", &hash));
+    assert!(!password::verify("correct horse", "not a hash"));
+}
+
+#[actix_web::test]
+async fn local_users_and_sessions() {
+    let mut conn = user_db().await;
+    let user = store::create_user(&mut conn, "vera", "rubin", Role::Viewer)
+        .await
+        .unwrap();
+    assert!(matches!(
+        store::create_user(&mut conn, "vera", "other", Role::Viewer).await,
+        Err(AuthError::UsernameTaken(_))
+    ));
+
+    assert_eq!(
+        store::authenticate(&mut conn, "vera", "rubin")
+            .await
+            .unwrap(),
+        user
+    );
+    assert!(matches!(
+        store::authenticate(&mut conn, "vera", "wrong").await,
+        Err(AuthError::InvalidCredentials)
+    ));
+    assert!(matches!(
+        store::authenticate(&mut conn, "nobody", "rubin").await,
+        Err(AuthError::InvalidCredentials)
+    ));
+
+    let token = store::create_session(&mut conn, &user).await.unwrap();
+    let stored: String = sqlx::query_scalar("SELECT token FROM sessions")
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+    assert_ne!(stored, token);
+    assert_eq!(
+        store::session_user(&mut conn, &token).await.unwrap(),
+        Some(user)
+    );
+    store::delete_session(&mut conn, &token).await.unwrap();
+    assert_eq!(store::session_user(&mut conn, &token).await.unwrap(), None);
+}
+
+#[actix_web::test]
+async fn roles() {
+    let mut conn = user_db().await;
+    let admin = store::create_user(&mut conn, "annie", "cannon", Role::Admin)
+        .await
+        .unwrap();
+    assert_eq!(
+        store::authenticate(&mut conn, "annie", "cannon")
+            .await
+            .unwrap()
+            .role,
+        Role::Admin
+    );
+
+    let user = store::create_user(&mut conn, "vera", "rubin", Role::Viewer)
+        .await
+        .unwrap();
+    let token = store::create_session(&mut conn, &user).await.unwrap();
+    let analyst = store::set_role(&mut conn, "vera", Role::Analyst)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(analyst.role, Role::Analyst);
+    // Sessions see the new role straight away.
+    assert_eq!(
+        store::session_user(&mut conn, &token).await.unwrap(),
+        Some(analyst.clone())
+    );
+    assert_eq!(
+        store::set_role(&mut conn, "nobody", Role::Admin)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(store::users(&mut conn).await.unwrap(), vec![admin, analyst]);
+
+    assert!(Role::Viewer < Role::Analyst && Role::Analyst < Role::Admin);
+    for role in Role::ALL {
+        assert_eq!(Role::parse(role.as_str()), Some(role));
+    }
+}
+
+#[actix_web::test]
+async fn roles_are_added_to_older_user_databases() {
+    let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+    sqlx::query(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, username TEXT NOT NULL UNIQUE, \
+        password_hash TEXT, oidc_subject TEXT UNIQUE)",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+    sqlx::query("INSERT INTO users (username) VALUES ('vera')")
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    create_tables(&mut conn).await.unwrap();
+    create_tables(&mut conn).await.unwrap();
+    assert_eq!(store::users(&mut conn).await.unwrap()[0].role, Role::Viewer);
+}
+
+#[actix_web::test]
+async fn saved_queries_belong_to_their_user() {
+    let mut conn = user_db().await;
+    let vera = store::create_user(&mut conn, "vera", "rubin", Role::Viewer)
+        .await
+        .unwrap();
+    let henrietta = store::create_user(&mut conn, "henrietta", "leavitt", Role::Viewer)
+        .await
+        .unwrap();
+
+    let query = vec![Input::new("pl_rade", "<", "2")];
+    let sort = Some(Sort {
+        field: "pl_bmasse".to_string(),
+        descending: true,
+    });
+    store::save_query(&mut conn, &vera, "small", &query, &sort)
+        .await
+        .unwrap();
+    // Saving under the same name replaces the query.
+    let query = vec![Input::new("pl_rade", "<", "1.5")];
+    store::save_query(&mut conn, &vera, "small", &query, &sort)
+        .await
+        .unwrap();
+
+    let saved = store::saved_queries(&mut conn, &vera).await.unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].query, query);
+    assert_eq!(saved[0].sort, sort);
+    assert!(store::saved_queries(&mut conn, &henrietta)
+        .await
+        .unwrap()
+        .is_empty());
+
+    store::delete_saved_query(&mut conn, &henrietta, saved[0].id)
+        .await
+        .unwrap();
+    assert_eq!(
+        store::saved_queries(&mut conn, &vera).await.unwrap().len(),
+        1
+    );
+    store::delete_saved_query(&mut conn, &vera, saved[0].id)
+        .await
+        .unwrap();
+    assert!(store::saved_queries(&mut conn, &vera)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[actix_web::test]
+async fn history_keeps_the_latest_queries() {
+    let mut conn = user_db().await;
+    let user = store::create_user(&mut conn, "vera", "rubin", Role::Viewer)
+        .await
+        .unwrap();
+    for radius in 0..60 {
+        let query = vec![Input::new("pl_rade", "<", &radius.to_string())];
+        store::record_history(&mut conn, &user, &query, &None)
+            .await
+            .unwrap();
+    }
+
+    let history = store::history(&mut conn, &user).await.unwrap();
+    assert_eq!(history.len(), 50);
+    assert_eq!(history[0].query, vec![Input::new("pl_rade", "<", "59")]);
+    assert_eq!(history[49].query, vec![Input::new("pl_rade", "<", "10")]);
+}
+
+#[actix_web::test]
+async fn column_layouts() {
+    let mut conn = user_db().await;
+    let user = store::create_user(&mut conn, "vera", "rubin", Role::Viewer)
+        .await
+        .unwrap();
+    assert_eq!(store::column_layout(&mut conn, &user).await.unwrap(), None);
+
+    let columns = vec!["pl_name".to_string(), "pl_rade".to_string()];
+    store::save_column_layout(&mut conn, &user, &columns)
+        .await
+        .unwrap();
+    assert_eq!(
+        store::column_layout(&mut conn, &user).await.unwrap(),
+        Some(columns)
+    );
+}
+
+const CODE: &str = "good-code";
+const ACCESS_TOKEN: &str = "access-token";
+
+#[derive(Deserialize)]
+struct TokenForm {
+    grant_type: String,
+    code: String,
+    client_secret: String,
+}
+
+async fn discovery(issuer: web::Data<String>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": issuer.as_str(),
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+        "userinfo_endpoint": format!("{issuer}/userinfo"),
+    }))
+}
+
+async fn token(form: web::Form<TokenForm>) -> HttpResponse {
+    if form.grant_type != "authorization_code"
+        || form.code != CODE
+        || form.client_secret != "secret"
+    {
+        return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+    }
+    HttpResponse::Ok().json(json!({ "access_token": ACCESS_TOKEN, "token_type": "Bearer" }))
+}
+
+async fn userinfo(req: HttpRequest) -> HttpResponse {
+    let authorization = req
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok());
+    if authorization != Some(format!("Bearer {ACCESS_TOKEN}").as_str()) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    HttpResponse::Ok().json(json!({ "sub": "42", "preferred_username": "vera" }))
+}
+
+/// Serves a provider's discovery, token and userinfo endpoints on a local
+/// port, returning its issuer URL.
+fn mock_provider() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let issuer = format!("http://{}", listener.local_addr().unwrap());
+    let data = web::Data::new(issuer.clone());
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(data.clone())
+            .route(
+                "/.well-known/openid-configuration",
+                web::get().to(discovery),
+            )
+            .route("/token", web::post().to(token))
+            .route("/userinfo", web::get().to(userinfo))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    actix_web::rt::spawn(server);
+    issuer
+}
+
+async fn discover(issuer: &str) -> OidcProvider {
+    OidcProvider::discover(
+        issuer,
+        "client".to_string(),
+        "secret".to_string(),
+        "http://localhost:3000/auth/oidc/callback".to_string(),
+    )
+    .await
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn oidc_sign_in() {
+    let issuer = mock_provider();
+    let provider = discover(&issuer).await;
+
+    let url = provider.authorize_url("some-state");
+    assert!(url.starts_with(&format!("{issuer}/authorize?")));
+    assert!(url.contains("client_id=client"));
+    assert!(url.contains("state=some-state"));
+    assert!(url.contains("response_type=code"));
+
+    let identity = provider.exchange(CODE).await.unwrap();
+    assert_eq!(
+        identity,
+        Identity {
+            subject: format!("{issuer} 42"),
+            username: Some("vera".to_string()),
+        }
+    );
+    assert!(matches!(
+        provider.exchange("bad-code").await,
+        Err(AuthError::Provider(_))
+    ));
+
+    // The first sign-in adds the user; later ones find them again.
+    let mut conn = user_db().await;
+    let user = store::oidc_user(&mut conn, &identity).await.unwrap();
+    assert_eq!(user.username, "vera");
+    assert_eq!(store::oidc_user(&mut conn, &identity).await.unwrap(), user);
+
+    // A taken username falls back to the subject.
+    let other = Identity {
+        subject: format!("{issuer} 43"),
+        username: Some("vera".to_string()),
+    };
+    let other_user = store::oidc_user(&mut conn, &other).await.unwrap();
+    assert_eq!(other_user.username, other.subject);
+}
@@ -0,0 +1,224 @@
+//! Covers loading the server configuration: the file, environment overrides
+//! and validation.
+//!
+//! Run with `cargo test --features ssr`.
+#![cfg(feature = "ssr")]
+
+use exoplanet_query_app::config::{Config, ConfigError};
+use std::{collections::HashMap, net::IpAddr};
+
+fn parse(toml: &str) -> Result<Config, ConfigError> {
+    Config::parse("config.toml".into(), toml)
+}
+
+/// Overrides `config` from the given variables alone.
+fn override_from(config: &mut Config, vars: &[(&str, &str)]) -> Result<(), ConfigError> {
+    let vars = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<HashMap<_, _>>();
+    config.override_from(|name| vars.get(name).cloned())
+}
+
+fn invalid_setting(config: &Config) -> Option<&'static str> {
+    match config.validate() {
+        Err(ConfigError::Invalid { setting, .. }) => Some(setting),
+        _ => None,
+    }
+}
+
+#[test]
+fn the_example_is_valid() {
+    let config = parse(include_str!("../config.example.toml")).unwrap();
+    assert_eq!(
+        Config {
+            database: config.database.clone(),
+            ..Config::default()
+        },
+        config
+    );
+    config.validate().unwrap();
+}
+
+#[test]
+fn the_file_overrides_defaults() {
+    let config = parse(
+        r#"
+        [database]
+        url = "postgres://localhost/exoplanets"
+
+        [limits]
+        page_size = 50
+
+        [rate_limit]
+        per_minute = 0
+
+        [auth]
+        database_url = "sqlite://users.db?mode=rwc"
+
+        [auth.oidc]
+        issuer = "https://accounts.example.com"
+        client_id = "client"
+        client_secret = "secret"
+        redirect_url = "http://localhost:3000/auth/oidc/callback"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.limits.page_size, 50);
+    assert_eq!(config.limits.max_filters, 20);
+    assert_eq!(config.rate_limit.per_minute, 0.0);
+    assert_eq!(config.rate_limit.burst, 30.0);
+    assert_eq!(config.auth.oidc.as_ref().unwrap().client_id, "client");
+    config.validate().unwrap();
+}
+
+#[test]
+fn unknown_settings_are_rejected() {
+    assert!(matches!(
+        parse("[limits]\npage_sise = 50"),
+        Err(ConfigError::Parse(..))
+    ));
+    assert!(matches!(
+        parse("[databse]\nurl = \"sqlite:data.db\""),
+        Err(ConfigError::Parse(..))
+    ));
+    assert!(matches!(
+        parse("[limits]\npage_size = \"fifty\""),
+        Err(ConfigError::Parse(..))
+    ));
+}
+
+#[test]
+fn the_environment_overrides_the_file() {
+    let mut config = parse("[database]\nurl = \"sqlite:data.db\"\n[cache]\nsize = 10").unwrap();
+    override_from(
+        &mut config,
+        &[
+            ("DATABASE_URL", "sqlite:other.db"),
+            ("DATABASE_MAX_CONNECTIONS", "4"),
+            ("QUERY_PAGE_SIZE", "25"),
+            ("RATE_LIMIT_BURST", "2.5"),
+            ("SQL_CONSOLE", "1"),
+            ("OPEN_ADMIN", "true"),
+            ("IMPORT_PATH", "data/archive.csv"),
+            ("RATE_LIMIT_TRUSTED_PROXIES", "127.0.0.1, ::1"),
+        ],
+    )
+    .unwrap();
+    assert_eq!(config.database.url, "sqlite:other.db");
+    assert_eq!(config.database.max_connections, 4);
+    assert_eq!(config.limits.page_size, 25);
+    assert_eq!(config.cache.size, 10);
+    assert_eq!(config.rate_limit.burst, 2.5);
+    assert!(config.features.sql_console);
+    assert!(config.features.open_admin);
+    assert_eq!(config.import.path, Some("data/archive.csv".into()));
+    assert_eq!(
+        config.rate_limit.trusted_proxies,
+        [
+            "127.0.0.1".parse::<IpAddr>().unwrap(),
+            "::1".parse().unwrap()
+        ]
+    );
+
+    // The provider's other settings only apply along with an issuer.
+    override_from(&mut config, &[("OIDC_CLIENT_ID", "client")]).unwrap();
+    assert_eq!(config.auth.oidc, None);
+    override_from(
+        &mut config,
+        &[
+            ("OIDC_ISSUER", "https://accounts.example.com"),
+            ("OIDC_CLIENT_ID", "client"),
+        ],
+    )
+    .unwrap();
+    let oidc = config.auth.oidc.as_ref().unwrap();
+    assert_eq!(
+        (oidc.issuer.as_str(), oidc.client_id.as_str()),
+        ("https://accounts.example.com", "client")
+    );
+}
+
+#[test]
+fn malformed_variables_name_themselves() {
+    let mut config = Config::default();
+    let error = override_from(&mut config, &[("QUERY_TIMEOUT_MS", "5s")]).unwrap_err();
+    assert!(matches!(
+        error,
+        ConfigError::Env {
+            name: "QUERY_TIMEOUT_MS",
+            ..
+        }
+    ));
+    assert_eq!(
+        error.to_string(),
+        "QUERY_TIMEOUT_MS is '5s', which isn't a whole number"
+    );
+    assert!(override_from(&mut config, &[("SQL_CONSOLE", "yes")]).is_err());
+    assert!(override_from(&mut config, &[("RATE_LIMIT_TRUSTED_PROXIES", "proxy")]).is_err());
+}
+
+#[test]
+fn validation() {
+    let valid = parse("[database]\nurl = \"sqlite:data.db\"").unwrap();
+    valid.validate().unwrap();
+
+    assert_eq!(invalid_setting(&Config::default()), Some("database.url"));
+    let mut config = valid.clone();
+    config.database.url = "mysql://localhost/exoplanets".to_string();
+    assert_eq!(invalid_setting(&config), Some("database.url"));
+    let mut config = valid.clone();
+    config.database.max_connections = 0;
+    assert_eq!(invalid_setting(&config), Some("database.max_connections"));
+
+    let mut config = valid.clone();
+    config.limits.page_size = 0;
+    assert_eq!(invalid_setting(&config), Some("limits.page_size"));
+    config.limits.page_size = 5000;
+    assert_eq!(invalid_setting(&config), Some("limits.page_size"));
+
+    let mut config = valid.clone();
+    config.limits.timeout_ms = 0;
+    assert_eq!(invalid_setting(&config), Some("limits.timeout_ms"));
+
+    let mut config = valid.clone();
+    config.rate_limit.export_burst = 0.5;
+    assert_eq!(invalid_setting(&config), Some("rate_limit.export_burst"));
+    let mut config = valid.clone();
+    config.rate_limit.per_minute = -1.0;
+    assert_eq!(invalid_setting(&config), Some("rate_limit.per_minute"));
+
+    let mut config = valid.clone();
+    config.auth.database_url = Some(config.database.url.clone());
+    assert_eq!(invalid_setting(&config), Some("auth.database_url"));
+    // Other spellings of the dataset's file are the same database.
+    let absolute = std::env::current_dir().unwrap().join("data.db");
+    for url in [
+        "sqlite://./data.db".to_string(),
+        "sqlite:data.db?mode=rwc".to_string(),
+        format!("sqlite://{}", absolute.display()),
+    ] {
+        config.auth.database_url = Some(url.clone());
+        assert_eq!(invalid_setting(&config), Some("auth.database_url"), "{url}");
+    }
+    config.auth.database_url = Some("postgres://localhost/users".to_string());
+    assert_eq!(invalid_setting(&config), Some("auth.database_url"));
+    config.auth.database_url = Some("sqlite://./users.db".to_string());
+    config.validate().unwrap();
+
+    let mut config = valid.clone();
+    config.features.open_admin = true;
+    config.validate().unwrap();
+    config.auth.database_url = Some("sqlite://users.db?mode=rwc".to_string());
+    assert_eq!(invalid_setting(&config), Some("features.open_admin"));
+
+    let mut config = valid.clone();
+    override_from(
+        &mut config,
+        &[("OIDC_ISSUER", "https://accounts.example.com")],
+    )
+    .unwrap();
+    assert_eq!(invalid_setting(&config), Some("auth.oidc"));
+    config.auth.database_url = Some("sqlite://users.db?mode=rwc".to_string());
+    assert_eq!(invalid_setting(&config), Some("auth.oidc.client_id"));
+}
@@ -0,0 +1,651 @@
+//! Runs the query layer against each database it supports.
+//!
+//! Run with `cargo test --features ssr`. SQLite runs in memory; to cover
+//! Postgres too, point `TEST_POSTGRES_URL` at a scratch database, e.g. one
+//! started with `docker run -e POSTGRES_PASSWORD=test -p 5432:5432 postgres`.
+//! CI sets it, and there the Postgres tests fail rather than skip without it.
+#![cfg(feature = "ssr")]
+
+use exoplanet_query_app::{
+    import::import_csv,
+    migrations::migrate,
+    model::{
+        columns::{find_column, ColumnKind},
+        data::{PlanetData, PAGE_SIZE},
+        filter::Predicate,
+        input::{Input, PageKind, Sort, CONSISTENT_OP, IN_OP, RELATIVE_ERROR_OP},
+    },
+    storage::{
+        is_timeout, planets_sql, push_filter, records_sql, schema_changed, systems_sql, Bind,
+        Connection, Dialect, Pool, Sql, Storage,
+    },
+};
+use std::time::Duration;
+
+/// The scratch Postgres database, if there is one.
+fn postgres_url() -> Option<String> {
+    match std::env::var("TEST_POSTGRES_URL") {
+        Ok(url) => Some(url),
+        Err(_) if std::env::var_os("CI").is_some() => {
+            panic!("TEST_POSTGRES_URL must be set in CI")
+        }
+        Err(_) => {
+            eprintln!("TEST_POSTGRES_URL is not set, skipping");
+            None
+        }
+    }
+}
+
+#[test]
+fn dialect_from_url() {
+    assert_eq!(Dialect::from_url("sqlite:data.db"), Some(Dialect::Sqlite));
+    assert_eq!(
+        Dialect::from_url("postgres://user@localhost/exoplanets"),
+        Some(Dialect::Postgres)
+    );
+    assert_eq!(
+        Dialect::from_url("postgresql://localhost"),
+        Some(Dialect::Postgres)
+    );
+    assert_eq!(Dialect::from_url("mysql://localhost"), None);
+}
+
+#[test]
+fn filters_use_dialect_placeholders() {
+    let mut sqlite = Sql::new(Dialect::Sqlite);
+    let mut postgres = Sql::new(Dialect::Postgres);
+    for sql in [&mut sqlite, &mut postgres] {
+        push_filter(sql, Input::new("disc_year", ">=", "2010")).unwrap();
+        push_filter(sql, Input::new("hostname", "=", "TRAPPIST-1")).unwrap();
+    }
+
+    assert_eq!(sqlite.text(), " AND disc_year >= ? AND hostname = ?");
+    assert_eq!(postgres.text(), " AND disc_year >= $1 AND hostname = $2");
+    assert_eq!(
+        postgres.binds(),
+        [&Bind::Integer(2010), &Bind::Text("TRAPPIST-1".to_string())]
+    );
+
+    let mut sql = Sql::new(Dialect::Postgres);
+    push_filter(&mut sql, Input::new("disc_year", IN_OP, "1995, 2016")).unwrap();
+    assert_eq!(sql.text(), " AND disc_year IN ($1, $2)");
+    assert_eq!(sql.binds(), [&Bind::Integer(1995), &Bind::Integer(2016)]);
+}
+
+#[test]
+fn relative_error_uses_dialect_functions() {
+    let mut sqlite = Sql::new(Dialect::Sqlite);
+    push_filter(&mut sqlite, Input::new("pl_rade", RELATIVE_ERROR_OP, "10")).unwrap();
+    assert!(sqlite
+        .text()
+        .contains("max(abs(pl_radeerr1), abs(pl_radeerr2))"));
+
+    let mut postgres = Sql::new(Dialect::Postgres);
+    push_filter(
+        &mut postgres,
+        Input::new("pl_rade", RELATIVE_ERROR_OP, "10"),
+    )
+    .unwrap();
+    assert!(postgres
+        .text()
+        .contains("GREATEST(abs(pl_radeerr1), abs(pl_radeerr2))"));
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    let mut sql = Sql::new(Dialect::Postgres);
+    assert!(push_filter(
+        &mut sql,
+        Input::new("id; DROP TABLE exoplanet_data", "=", "1")
+    )
+    .is_err());
+    assert!(push_filter(&mut sql, Input::new("disc_year", "LIKE", "1")).is_err());
+}
+
+/// Replaces `exoplanet_data` with three planets, two of them default rows.
+async fn seed(conn: &mut Connection) {
+    let dialect = conn.dialect();
+    conn.execute_script("DROP TABLE IF EXISTS exoplanet_data")
+        .await
+        .unwrap();
+    conn.execute_script(&dialect.create_table("exoplanet_data"))
+        .await
+        .unwrap();
+    for (name, host, year, default_flag) in [
+        ("TRAPPIST-1 b", "TRAPPIST-1", 2016, 1),
+        ("TRAPPIST-1 c", "TRAPPIST-1", 2016, 0),
+        ("51 Peg b", "51 Peg", 1995, 1),
+    ] {
+        let mut sql = Sql::new(dialect);
+        sql.push(
+            "INSERT INTO exoplanet_data (pl_name, hostname, disc_year, default_flag) VALUES (",
+        )
+        .push_bind(name.to_string())
+        .push(", ")
+        .push_bind(host.to_string())
+        .push(", ")
+        .push_bind(year as i64)
+        .push(", ")
+        .push_bind(default_flag as i64)
+        .push(")");
+        conn.execute(&sql).await.unwrap();
+    }
+}
+
+async fn check_records(conn: &mut Connection) {
+    seed(conn).await;
+    let dialect = conn.dialect();
+
+    let (sql, offset) =
+        records_sql(dialect, Vec::new(), None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(offset, None);
+    assert_eq!(
+        planets
+            .iter()
+            .map(|p| p.pl_name.as_str())
+            .collect::<Vec<_>>(),
+        ["TRAPPIST-1 b", "51 Peg b"]
+    );
+    assert!(planets.iter().all(|planet| planet.default_flag));
+
+    let query = vec![Input::new("disc_year", "<", "2000")];
+    let (sql, _) = records_sql(dialect, query, None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(planets.len(), 1);
+    assert_eq!(planets[0].hostname, "51 Peg");
+
+    let query = vec![Input::new("hostname", IN_OP, "\"51 Peg\", Kepler-22")];
+    let (sql, _) = records_sql(dialect, query, None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(names(planets.iter()), ["51 Peg b"]);
+
+    let sort = Some(Sort {
+        field: "disc_year".to_string(),
+        descending: false,
+    });
+    let (sql, offset) =
+        records_sql(dialect, Vec::new(), sort, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(offset, Some(0));
+    assert_eq!(planets[0].pl_name, "51 Peg b");
+
+    let steps = conn.explain(&sql).await.unwrap();
+    assert!(!steps.is_empty());
+
+    let counts = conn
+        .year_counts(&[], find_column("hostname").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        counts
+            .iter()
+            .map(|count| (count.year, count.category.as_deref(), count.count))
+            .collect::<Vec<_>>(),
+        [(1995, Some("51 Peg"), 1), (2016, Some("TRAPPIST-1"), 1)]
+    );
+
+    let ids = planets.iter().map(|planet| planet.id).collect::<Vec<_>>();
+    let found = conn
+        .fetch_planets(&planets_sql(dialect, &ids))
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 2);
+
+    // TRAPPIST-1 c, seeded second, isn't a default row but is still a
+    // candidate when it's the planet searched from.
+    let candidates = conn.similarity_candidates(ids[0]).await.unwrap();
+    assert_eq!(candidates.len(), 2);
+    let candidates = conn.similarity_candidates(2).await.unwrap();
+    assert_eq!(candidates.len(), 3);
+
+    check_consistency(conn).await;
+    check_relative_error(conn).await;
+    check_stats_and_plots(conn).await;
+}
+
+fn names<'a>(planets: impl Iterator<Item = &'a PlanetData>) -> Vec<&'a str> {
+    planets.map(|planet| planet.pl_name.as_str()).collect()
+}
+
+/// Gives TRAPPIST-1 b a measured radius and 51 Peg b an upper limit, then
+/// checks SQL and in-memory filtering agree on which are consistent.
+async fn check_consistency(conn: &mut Connection) {
+    let dialect = conn.dialect();
+    conn.execute_script(
+        "UPDATE exoplanet_data SET pl_rade = 1.1, pl_radeerr1 = 0.1, pl_radeerr2 = -0.1, \
+         pl_radelim = 0 WHERE pl_name = 'TRAPPIST-1 b'; \
+         UPDATE exoplanet_data SET pl_rade = 5, pl_radelim = 1 WHERE pl_name = '51 Peg b'",
+    )
+    .await
+    .unwrap();
+
+    let (sql, _) = records_sql(dialect, Vec::new(), None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+    let all = conn.fetch_planets(&sql).await.unwrap();
+    for (target, expected) in [
+        ("1.15", &["TRAPPIST-1 b", "51 Peg b"][..]),
+        ("3", &["51 Peg b"]),
+        ("1.25, 2σ", &["TRAPPIST-1 b", "51 Peg b"]),
+        ("6", &[]),
+    ] {
+        let query = Input::new("pl_rade", CONSISTENT_OP, target);
+        let predicate = Predicate::compile(query.clone()).unwrap();
+        let (sql, _) =
+            records_sql(dialect, vec![query], None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+        let planets = conn.fetch_planets(&sql).await.unwrap();
+        assert_eq!(names(planets.iter()), expected, "{target}");
+        let in_memory = all.iter().filter(|planet| predicate.matches_planet(planet));
+        assert_eq!(names(in_memory), expected, "{target} in memory");
+    }
+}
+
+/// Checks planets missing an error or with a zero value never pass a
+/// relative error filter, in SQL as in memory, and don't fail the query.
+async fn check_relative_error(conn: &mut Connection) {
+    let dialect = conn.dialect();
+    let query = Input::new("pl_rade", RELATIVE_ERROR_OP, "20");
+    let predicate = Predicate::compile(query.clone()).unwrap();
+    let (sql, _) = records_sql(dialect, vec![query], None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+    let (all_sql, _) =
+        records_sql(dialect, Vec::new(), None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+
+    // 51 Peg b has only an upper error.
+    conn.execute_script("UPDATE exoplanet_data SET pl_radeerr1 = 0.1 WHERE pl_name = '51 Peg b'")
+        .await
+        .unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(names(planets.iter()), ["TRAPPIST-1 b"]);
+
+    conn.execute_script("UPDATE exoplanet_data SET pl_rade = 0 WHERE pl_name = 'TRAPPIST-1 b'")
+        .await
+        .unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert!(planets.is_empty());
+    let all = conn.fetch_planets(&all_sql).await.unwrap();
+    assert!(!all.iter().any(|planet| predicate.matches_planet(planet)));
+}
+
+/// Builds a table from before the catalog gained most of its columns and
+/// checks the migrations bring it up to date. Runs on a connection of its
+/// own, as at startup, since connections cache the columns of statements
+/// they've prepared.
+async fn check_old_tables_are_migrated(url: &str) {
+    let conn = &mut Connection::connect(url).await.unwrap();
+    conn.execute_script(
+        "DROP TABLE IF EXISTS exoplanet_data; \
+         CREATE TABLE exoplanet_data (id BIGINT PRIMARY KEY, pl_name TEXT, hostname TEXT, \
+         default_flag BIGINT, disc_year BIGINT); \
+         INSERT INTO exoplanet_data VALUES (1, '51 Peg b', '51 Peg', 1, 1995)",
+    )
+    .await
+    .unwrap();
+    migrate(conn).await.unwrap();
+    // Migrating twice changes nothing.
+    migrate(conn).await.unwrap();
+
+    let (sql, _) = records_sql(
+        conn.dialect(),
+        vec![Input::new("derived_density", ">", "1")],
+        None,
+        0,
+        &PageKind::Next,
+        PAGE_SIZE,
+    )
+    .unwrap();
+    assert!(conn.fetch_planets(&sql).await.unwrap().is_empty());
+    let (sql, _) = records_sql(
+        conn.dialect(),
+        Vec::new(),
+        None,
+        0,
+        &PageKind::Next,
+        PAGE_SIZE,
+    )
+    .unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(names(planets.iter()), ["51 Peg b"]);
+    assert_eq!(planets[0].pl_rade, None);
+}
+
+/// Builds a table as the original app's CSV loads left it, with every column
+/// declared as text, and checks the migrations retype its values.
+async fn check_text_tables_are_retyped(url: &str) {
+    let conn = &mut Connection::connect(url).await.unwrap();
+    conn.execute_script(
+        "DROP TABLE IF EXISTS exoplanet_data; \
+         CREATE TABLE exoplanet_data (id INTEGER PRIMARY KEY, pl_name TEXT, hostname TEXT, \
+         default_flag TEXT, disc_year TEXT, pl_rade TEXT, releasedate TEXT); \
+         INSERT INTO exoplanet_data VALUES \
+         (1, '51 Peg b', '51 Peg', '1', '1995', '', '2014-05-14'), \
+         (2, 'HD 209458 b', 'HD 209458', '1', '1999', '15.6', '2014-05-14'), \
+         (3, 'TRAPPIST-1 b', 'TRAPPIST-1', '1', '2016', '1.116', '2016-05-02')",
+    )
+    .await
+    .unwrap();
+    migrate(conn).await.unwrap();
+    // Migrating twice changes nothing.
+    migrate(conn).await.unwrap();
+
+    let types = conn.column_types("exoplanet_data").await.unwrap();
+    let declared = |column: &str| {
+        types
+            .iter()
+            .find(|(name, _)| name == column)
+            .map(|(_, declared)| declared.clone())
+    };
+    let real = conn.dialect().column_type(ColumnKind::Real).to_lowercase();
+    assert_eq!(declared("pl_rade"), Some(real));
+    assert_eq!(declared("releasedate").as_deref(), Some("text"));
+
+    // As text, "15.6" would sort below "5".
+    let (sql, _) = records_sql(
+        conn.dialect(),
+        vec![Input::new("pl_rade", ">", "5")],
+        None,
+        0,
+        &PageKind::Next,
+        PAGE_SIZE,
+    )
+    .unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(names(planets.iter()), ["HD 209458 b"]);
+    assert_eq!(planets[0].disc_year, Some(1999));
+
+    let (sql, _) = records_sql(
+        conn.dialect(),
+        Vec::new(),
+        None,
+        0,
+        &PageKind::Next,
+        PAGE_SIZE,
+    )
+    .unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(planets.len(), 3);
+    assert_eq!(planets[0].pl_rade, None);
+}
+
+/// Runs console statements through `read_only`, checking values come back as
+/// text and that nothing written gets through, whatever the statement check
+/// lets by.
+async fn check_console(conn: &mut Connection, read_only: &mut Connection) {
+    seed(conn).await;
+
+    let result = read_only
+        .fetch_text(
+            "SELECT pl_name, disc_year, 1.5 AS ratio, NULL AS missing FROM exoplanet_data \
+            WHERE default_flag = 1 ORDER BY id -- the default rows",
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.columns, ["pl_name", "disc_year", "ratio", "missing"]);
+    assert_eq!(
+        result.rows[0],
+        [
+            Some("TRAPPIST-1 b".to_string()),
+            Some("2016".to_string()),
+            Some("1.5".to_string()),
+            None
+        ]
+    );
+    assert!(!result.truncated);
+    let result = read_only
+        .fetch_text("SELECT id FROM exoplanet_data", 2)
+        .await
+        .unwrap();
+    assert_eq!((result.rows.len(), result.truncated), (2, true));
+
+    for statement in [
+        "DELETE FROM exoplanet_data",
+        "SELECT 1; DELETE FROM exoplanet_data",
+        "COMMIT; DELETE FROM exoplanet_data",
+        "SELECT 1; PRAGMA query_only = OFF; DELETE FROM exoplanet_data",
+        "WITH gone AS (DELETE FROM exoplanet_data RETURNING id) SELECT count(*) FROM gone",
+        "SELECT 1) AS x; DELETE FROM exoplanet_data; SELECT * FROM (SELECT 1",
+    ] {
+        assert!(
+            read_only.fetch_text(statement, 10).await.is_err(),
+            "{statement}"
+        );
+    }
+    // The connection is still usable, and the rows are all still there.
+    let result = read_only
+        .fetch_text("SELECT count(*) AS n FROM exoplanet_data", 10)
+        .await
+        .unwrap();
+    assert_eq!(result.rows, [[Some("3".to_string())]]);
+}
+
+/// Imports a small archive export and checks its rows, derived columns and
+/// systems arrive.
+async fn check_import(conn: &mut Connection) {
+    let path = std::env::temp_dir().join(format!(
+        "exoplanet-import-{:?}-{}.csv",
+        conn.dialect(),
+        std::process::id()
+    ));
+    std::fs::write(
+        &path,
+        "# Exported from the archive\n\
+        pl_name,hostname,sy_pnum,disc_year,pl_rade,pl_radeerr1,pl_radeerr2,pl_bmasse,disc_refname\n\
+        Earth b,Sun,2,2000,1.0,0.1,-0.1,1.0,<a href=https://example.com/ref>Ref 2000</a>\n\
+        Mars b,Sun,2,2001,,,,,\n\
+        51 Peg b,51 Peg,1,1995,,,,150,\n",
+    )
+    .unwrap();
+    let imported = import_csv(conn, &path).await;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(imported.unwrap(), 3);
+
+    let dialect = conn.dialect();
+    let (sql, _) = records_sql(dialect, Vec::new(), None, 0, &PageKind::Next, PAGE_SIZE).unwrap();
+    let planets = conn.fetch_planets(&sql).await.unwrap();
+    assert_eq!(names(planets.iter()), ["Earth b", "Mars b", "51 Peg b"]);
+    assert!(planets.iter().all(|planet| planet.default_flag));
+
+    // A transaction dropped before it commits, as an import stopped part way
+    // through is, is rolled back.
+    let mut transaction = conn.begin().await.unwrap();
+    transaction
+        .execute_script("DELETE FROM exoplanet_data")
+        .await
+        .unwrap();
+    drop(transaction);
+    assert_eq!(conn.fetch_planets(&sql).await.unwrap().len(), 3);
+    assert_eq!(planets[0].disc_refname.as_deref(), Some("Ref 2000"));
+    assert_eq!(planets[1].pl_rade, None);
+
+    let density = find_column("derived_density").unwrap();
+    let points = conn.plot_points(&[], density, density, 10).await.unwrap();
+    assert_eq!(points.len(), 1);
+    assert!((points[0].x.value.unwrap() - 5.51).abs() < 0.01);
+
+    let systems = conn
+        .fetch_planets(&systems_sql(dialect, &[]))
+        .await
+        .unwrap();
+    assert_eq!(names(systems.iter()), ["Earth b", "Mars b"]);
+}
+
+fn statement(dialect: Dialect, sql: &str) -> Sql {
+    let mut statement = Sql::new(dialect);
+    statement.push(sql);
+    statement
+}
+
+/// A pooled connection comes back without its timeout, and isn't reused
+/// once the tables have been replaced. `slow` runs past a millisecond.
+async fn check_pool(url: &str, slow: &str) {
+    let pool = Pool::connect(url, 1).await.unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+    let dialect = conn.dialect();
+    conn.set_timeout(Duration::from_millis(1)).await.unwrap();
+    conn.execute_script("CREATE TEMPORARY TABLE pooled (id INTEGER)")
+        .await
+        .unwrap();
+    drop(conn);
+
+    // There's one connection, so this is the same one.
+    let mut conn = pool.acquire().await.unwrap();
+    conn.execute(&statement(dialect, slow)).await.unwrap();
+    let pooled = statement(dialect, "SELECT id FROM pooled");
+    conn.execute(&pooled).await.unwrap();
+    drop(conn);
+
+    schema_changed();
+    let mut conn = pool.acquire().await.unwrap();
+    assert!(conn.execute(&pooled).await.is_err());
+}
+
+async fn check_stats_and_plots(conn: &mut impl Storage) {
+    let hostname = find_column("hostname").unwrap();
+    let disc_year = find_column("disc_year").unwrap();
+
+    let groups = conn.group_stats(&[], hostname, disc_year).await.unwrap();
+    assert_eq!(
+        groups
+            .iter()
+            .map(|group| (group.group_value.as_deref(), group.count, group.median))
+            .collect::<Vec<_>>(),
+        [
+            (Some("51 Peg"), 1, Some(1995.0)),
+            (Some("TRAPPIST-1"), 1, Some(2016.0))
+        ]
+    );
+
+    let filters = [Predicate::compile(Input::new("disc_year", ">", "2000")).unwrap()];
+    let groups = conn
+        .group_stats(&filters, hostname, disc_year)
+        .await
+        .unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].mean, Some(2016.0));
+
+    let points = conn
+        .plot_points(&[], disc_year, disc_year, 1)
+        .await
+        .unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].pl_name, "TRAPPIST-1 b");
+    assert_eq!(points[0].x.value, Some(2016.0));
+}
+
+#[actix_web::test]
+async fn sqlite_records() {
+    let mut conn = Connection::connect("sqlite::memory:").await.unwrap();
+    check_records(&mut conn).await;
+}
+
+#[cfg(feature = "columnar")]
+#[actix_web::test]
+async fn columnar_stats_and_plots() {
+    use exoplanet_query_app::storage::columnar::{ColumnStore, Columnar};
+    use std::sync::Arc;
+
+    let mut conn = Connection::connect("sqlite::memory:").await.unwrap();
+    seed(&mut conn).await;
+    let (sql, _) = records_sql(
+        Dialect::Sqlite,
+        Vec::new(),
+        None,
+        0,
+        &PageKind::Next,
+        PAGE_SIZE,
+    )
+    .unwrap();
+    let store = ColumnStore::new(&conn.fetch_planets(&sql).await.unwrap());
+    assert_eq!(store.len(), 2);
+
+    let mut columnar = Columnar::new(Arc::new(store), conn);
+    check_stats_and_plots(&mut columnar).await;
+}
+
+#[actix_web::test]
+async fn sqlite_migrations() {
+    check_old_tables_are_migrated("sqlite::memory:").await;
+    check_text_tables_are_retyped("sqlite::memory:").await;
+}
+
+#[actix_web::test]
+async fn sqlite_console() {
+    // The console's connection is separate, so the database is a file.
+    let path = std::env::temp_dir().join(format!("exoplanet-console-{}.db", std::process::id()));
+    let url = format!("sqlite://{}?mode=rwc", path.display());
+    let mut conn = Connection::connect(&url).await.unwrap();
+    let mut read_only = Connection::connect_read_only(&url).await.unwrap();
+    check_console(&mut conn, &mut read_only).await;
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[actix_web::test]
+async fn sqlite_pool() {
+    let path = std::env::temp_dir().join(format!("exoplanet-pool-{}.db", std::process::id()));
+    let url = format!("sqlite://{}?mode=rwc", path.display());
+    check_pool(
+        &url,
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1000000) \
+        SELECT count(*) FROM n",
+    )
+    .await;
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[actix_web::test]
+async fn sqlite_import() {
+    let mut conn = Connection::connect("sqlite::memory:").await.unwrap();
+    check_import(&mut conn).await;
+}
+
+#[actix_web::test]
+async fn postgres_records() {
+    let Some(url) = postgres_url() else {
+        return;
+    };
+    let mut conn = Connection::connect(&url).await.unwrap();
+    check_records(&mut conn).await;
+    check_old_tables_are_migrated(&url).await;
+    check_text_tables_are_retyped(&url).await;
+    check_pool(&url, "SELECT pg_sleep(0.05)").await;
+    check_console(
+        &mut Connection::connect(&url).await.unwrap(),
+        &mut Connection::connect_read_only(&url).await.unwrap(),
+    )
+    .await;
+    // The tests share the database, so the import runs after the others.
+    check_import(&mut Connection::connect(&url).await.unwrap()).await;
+}
+
+#[actix_web::test]
+async fn postgres_timeout() {
+    let Some(url) = postgres_url() else {
+        return;
+    };
+    let mut conn = Connection::connect(&url).await.unwrap();
+    conn.set_timeout(Duration::from_millis(50)).await.unwrap();
+    let Connection::Postgres(pg) = &mut conn else {
+        unreachable!()
+    };
+    let error = sqlx::query("SELECT pg_sleep(1)")
+        .execute(pg.as_mut())
+        .await
+        .unwrap_err();
+    assert!(is_timeout(&error));
+}
+
+#[actix_web::test]
+async fn sqlite_timeout() {
+    let mut conn = Connection::connect("sqlite::memory:").await.unwrap();
+    conn.set_timeout(Duration::from_millis(50)).await.unwrap();
+    let Connection::Sqlite(sqlite) = &mut conn else {
+        unreachable!()
+    };
+    // Counts forever, so only the timeout can stop it.
+    let error = sqlx::query(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) SELECT count(*) FROM n",
+    )
+    .execute(&mut *sqlite)
+    .await
+    .unwrap_err();
+    assert!(is_timeout(&error));
+}